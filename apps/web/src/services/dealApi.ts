@@ -7,6 +7,7 @@ const USE_MOCK_DATA = true;
 
 export interface CreateDealRequest {
   deal_name: string;
+  org_id?: string;
 }
 
 export interface DealResponse {
@@ -17,6 +18,8 @@ export interface DealResponse {
   created_at: string;
   updated_at: string;
   metadata: any;
+  org_id?: string;
+  role?: "viewer" | "analyst" | "approver" | "owner";
   document_count?: number;
   fact_count?: number;
 }
@@ -32,6 +35,7 @@ export interface DocumentResponse {
   created_at: string;
   updated_at: string;
   extracted_at?: string;
+  property_id?: string;
 }
 
 export interface FactResponse {
@@ -42,6 +46,7 @@ export interface FactResponse {
   label: string;
   value: string;
   unit?: string;
+  property_id?: string;
   source_citation: {
     document: string;
     page: number;
@@ -52,6 +57,8 @@ export interface FactResponse {
       width: number;
       height: number;
     };
+    page_width?: number;
+    page_height?: number;
   };
   status: string;
   confidence_score?: number;
@@ -76,15 +83,46 @@ export const createDeal = async (dealName: string): Promise<DealResponse> => {
   return response.data;
 };
 
-// Get all deals for the current user
+export interface DealListParams {
+  page?: number;
+  limit?: number;
+  status?: string;
+  start?: string;
+  end?: string;
+  name?: string;
+  q?: string;
+  sort?: "created_at" | "updated_at" | "deal_name" | "status";
+  order?: "asc" | "desc";
+}
+
+export interface DealPage {
+  deals: DealResponse[];
+  total: number;
+}
+
+// Get one page of the deals the current user can see
+export const getDealsPage = async (params: DealListParams = {}): Promise<DealPage> => {
+  const response = await axiosInstance.get("/api/v1/deals", { params });
+  const total = Number(response.headers["x-total-count"] ?? response.data.length);
+  return { deals: response.data, total };
+};
+
+// Get all deals for the current user, page by page
 export const getDeals = async (): Promise<DealResponse[]> => {
   if (USE_MOCK_DATA) {
     await new Promise((resolve) => setTimeout(resolve, 300));
     return MOCK_DEALS;
   }
   
-  const response = await axiosInstance.get("/api/v1/deals");
-  return response.data;
+  const limit = 500;
+  const deals: DealResponse[] = [];
+  for (let page = 1; ; page++) {
+    const result = await getDealsPage({ page, limit });
+    deals.push(...result.deals);
+    if (result.deals.length < limit || deals.length >= result.total) {
+      return deals;
+    }
+  }
 };
 
 // Get a specific deal by ID
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_documents_task_id;
+
+ALTER TABLE documents DROP COLUMN IF EXISTS task_id;
@@ -0,0 +1,4 @@
+-- Link each deal document to the task that processes it
+ALTER TABLE documents ADD COLUMN task_id TEXT;
+
+CREATE INDEX idx_documents_task_id ON documents(task_id);
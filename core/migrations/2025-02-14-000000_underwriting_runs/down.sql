@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_underwriting_runs_deal_id;
+
+DROP TABLE IF EXISTS underwriting_runs;
@@ -0,0 +1,16 @@
+-- Persist every underwriting calculation so runs can be compared over time
+CREATE TABLE underwriting_runs (
+    run_id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+    deal_id TEXT NOT NULL REFERENCES deals(deal_id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    input_fact_ids TEXT[] NOT NULL DEFAULT '{}',
+    inputs JSONB NOT NULL,
+    result JSONB NOT NULL,
+    audit_trail JSONB NOT NULL DEFAULT '[]'::jsonb,
+    warnings TEXT[] NOT NULL DEFAULT '{}',
+    run_by TEXT NOT NULL REFERENCES users(user_id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (deal_id, version)
+);
+
+CREATE INDEX idx_underwriting_runs_deal_id ON underwriting_runs(deal_id);
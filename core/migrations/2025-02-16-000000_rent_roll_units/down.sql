@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_rent_roll_units_deal_id;
+DROP INDEX IF EXISTS idx_rent_roll_units_document_id;
+
+DROP TABLE IF EXISTS rent_roll_units;
@@ -0,0 +1,20 @@
+-- Unit-level rows parsed from the tables of rent roll documents
+CREATE TABLE rent_roll_units (
+    rent_roll_unit_id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+    document_id TEXT NOT NULL REFERENCES documents(document_id) ON DELETE CASCADE,
+    deal_id TEXT NOT NULL REFERENCES deals(deal_id) ON DELETE CASCADE,
+    row_index INTEGER NOT NULL,
+    unit_id TEXT NOT NULL,
+    tenant TEXT,
+    square_feet FLOAT8,
+    lease_start DATE,
+    lease_end DATE,
+    contract_rent FLOAT8,
+    market_rent FLOAT8,
+    status TEXT NOT NULL,
+    source_citation JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_rent_roll_units_document_id ON rent_roll_units(document_id);
+CREATE INDEX idx_rent_roll_units_deal_id ON rent_roll_units(deal_id);
@@ -0,0 +1,6 @@
+ALTER TABLE facts DROP COLUMN IF EXISTS period_basis;
+
+DROP INDEX IF EXISTS idx_pl_line_items_deal_id;
+DROP INDEX IF EXISTS idx_pl_line_items_document_id;
+
+DROP TABLE IF EXISTS pl_line_items;
@@ -0,0 +1,19 @@
+-- Line items of multi-period P&L statements, one row per line with an amount per period
+CREATE TABLE pl_line_items (
+    pl_line_item_id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+    document_id TEXT NOT NULL REFERENCES documents(document_id) ON DELETE CASCADE,
+    deal_id TEXT NOT NULL REFERENCES deals(deal_id) ON DELETE CASCADE,
+    row_index INTEGER NOT NULL,
+    category TEXT NOT NULL,
+    label TEXT NOT NULL,
+    periods TEXT[] NOT NULL DEFAULT '{}',
+    amounts FLOAT8[] NOT NULL DEFAULT '{}',
+    source_citation JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_pl_line_items_document_id ON pl_line_items(document_id);
+CREATE INDEX idx_pl_line_items_deal_id ON pl_line_items(deal_id);
+
+-- Period a fact was computed over (e.g. trailing_12), for facts derived from multi-period statements
+ALTER TABLE facts ADD COLUMN period_basis TEXT;
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_fact_resolutions_deal_id;
+
+DROP TABLE IF EXISTS fact_resolutions;
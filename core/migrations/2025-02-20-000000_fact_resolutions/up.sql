@@ -0,0 +1,12 @@
+-- The fact a reviewer picked for a fact type when documents of a deal disagree
+CREATE TABLE fact_resolutions (
+    resolution_id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+    deal_id TEXT NOT NULL REFERENCES deals(deal_id) ON DELETE CASCADE,
+    fact_type TEXT NOT NULL,
+    fact_id TEXT NOT NULL REFERENCES facts(fact_id) ON DELETE CASCADE,
+    resolved_by TEXT NOT NULL REFERENCES users(user_id),
+    resolved_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (deal_id, fact_type)
+);
+
+CREATE INDEX idx_fact_resolutions_deal_id ON fact_resolutions(deal_id);
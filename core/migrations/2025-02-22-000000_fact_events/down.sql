@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_fact_events_deal_id;
+DROP INDEX IF EXISTS idx_fact_events_fact_id;
+
+DROP TABLE IF EXISTS fact_events;
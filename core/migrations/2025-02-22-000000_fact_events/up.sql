@@ -0,0 +1,19 @@
+-- Audit log of every change to a fact. Events outlive the facts they describe, which are
+-- replaced when a document is extracted again, so fact_id is not a foreign key.
+CREATE TABLE fact_events (
+    event_id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+    fact_id TEXT NOT NULL,
+    deal_id TEXT NOT NULL REFERENCES deals(deal_id) ON DELETE CASCADE,
+    fact_type TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    old_unit TEXT,
+    new_unit TEXT,
+    actor TEXT REFERENCES users(user_id),
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_fact_events_fact_id ON fact_events(fact_id);
+CREATE INDEX idx_fact_events_deal_id ON fact_events(deal_id);
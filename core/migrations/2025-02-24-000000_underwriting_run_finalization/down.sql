@@ -0,0 +1,2 @@
+ALTER TABLE underwriting_runs DROP COLUMN IF EXISTS finalized_by;
+ALTER TABLE underwriting_runs DROP COLUMN IF EXISTS finalized_at;
@@ -0,0 +1,3 @@
+-- Finalizing an underwriting run completes its deal
+ALTER TABLE underwriting_runs ADD COLUMN finalized_at TIMESTAMPTZ;
+ALTER TABLE underwriting_runs ADD COLUMN finalized_by TEXT REFERENCES users(user_id);
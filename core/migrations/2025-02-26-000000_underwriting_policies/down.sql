@@ -0,0 +1,5 @@
+ALTER TABLE deals DROP COLUMN IF EXISTS policy_id;
+
+DROP INDEX IF EXISTS idx_underwriting_policies_user_id;
+
+DROP TABLE IF EXISTS underwriting_policies;
@@ -0,0 +1,17 @@
+-- Lender-specific underwriting covenants. Thresholds and severities are stored as JSON so
+-- policies only need to list the values that differ from the defaults.
+CREATE TABLE underwriting_policies (
+    policy_id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+    user_id TEXT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    thresholds JSONB NOT NULL DEFAULT '{}'::jsonb,
+    required_fact_types TEXT[] NOT NULL DEFAULT ARRAY['collected_rent', 'operating_expenses'],
+    severities JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_underwriting_policies_user_id ON underwriting_policies(user_id);
+
+-- Deals without a policy use the default thresholds
+ALTER TABLE deals ADD COLUMN policy_id TEXT REFERENCES underwriting_policies(policy_id) ON DELETE SET NULL;
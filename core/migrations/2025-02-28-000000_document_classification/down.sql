@@ -0,0 +1,3 @@
+ALTER TABLE documents DROP COLUMN IF EXISTS classification_source;
+ALTER TABLE documents DROP COLUMN IF EXISTS classification_alternatives;
+ALTER TABLE documents DROP COLUMN IF EXISTS classification_confidence;
@@ -0,0 +1,7 @@
+-- How each document's type was determined. The source is 'layout' or 'llm' when it was
+-- classified from its content and 'user' when it was chosen at upload or overridden.
+ALTER TABLE documents ADD COLUMN classification_confidence DOUBLE PRECISION;
+ALTER TABLE documents ADD COLUMN classification_alternatives JSONB NOT NULL DEFAULT '[]'::jsonb;
+ALTER TABLE documents ADD COLUMN classification_source TEXT;
+
+UPDATE documents SET classification_source = 'user' WHERE document_type <> 'other';
@@ -0,0 +1,21 @@
+ALTER TABLE underwriting_runs DROP COLUMN IF EXISTS property_results;
+
+DROP INDEX IF EXISTS idx_fact_resolutions_deal_id_fact_type;
+
+-- Keep one resolution per fact type before restoring the constraint
+DELETE FROM fact_resolutions r
+USING fact_resolutions newer
+WHERE r.deal_id = newer.deal_id
+  AND r.fact_type = newer.fact_type
+  AND r.resolved_at < newer.resolved_at;
+
+ALTER TABLE fact_resolutions ADD CONSTRAINT fact_resolutions_deal_id_fact_type_key UNIQUE (deal_id, fact_type);
+
+DROP INDEX IF EXISTS idx_facts_property_id;
+
+ALTER TABLE facts DROP COLUMN IF EXISTS property_id;
+ALTER TABLE documents DROP COLUMN IF EXISTS property_id;
+
+DROP INDEX IF EXISTS idx_properties_deal_id;
+
+DROP TABLE IF EXISTS properties;
@@ -0,0 +1,23 @@
+-- Properties of portfolio deals. Deals without properties are underwritten as a single property.
+CREATE TABLE properties (
+    property_id TEXT PRIMARY KEY DEFAULT gen_random_uuid()::text,
+    deal_id TEXT NOT NULL REFERENCES deals(deal_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    address TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_properties_deal_id ON properties(deal_id);
+
+-- Documents and facts without a property cover the whole portfolio, such as a blanket loan
+ALTER TABLE documents ADD COLUMN property_id TEXT REFERENCES properties(property_id) ON DELETE SET NULL;
+ALTER TABLE facts ADD COLUMN property_id TEXT REFERENCES properties(property_id) ON DELETE SET NULL;
+
+CREATE INDEX idx_facts_property_id ON facts(property_id);
+
+-- Conflicts are resolved per property, so a fact type can have one resolution for each
+ALTER TABLE fact_resolutions DROP CONSTRAINT IF EXISTS fact_resolutions_deal_id_fact_type_key;
+
+CREATE INDEX idx_fact_resolutions_deal_id_fact_type ON fact_resolutions(deal_id, fact_type);
+
+ALTER TABLE underwriting_runs ADD COLUMN property_results JSONB NOT NULL DEFAULT '[]'::jsonb;
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_api_keys_org_id_user_id;
+DROP INDEX IF EXISTS idx_deals_org_id;
+
+ALTER TABLE deals DROP COLUMN IF EXISTS org_id;
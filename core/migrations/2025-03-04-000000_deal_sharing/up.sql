@@ -0,0 +1,6 @@
+-- Deals shared with an organization. Members get the role of the access level on their API key
+-- for that organization: viewer, analyst or approver.
+ALTER TABLE deals ADD COLUMN org_id TEXT;
+
+CREATE INDEX idx_deals_org_id ON deals(org_id);
+CREATE INDEX idx_api_keys_org_id_user_id ON api_keys(org_id, user_id);
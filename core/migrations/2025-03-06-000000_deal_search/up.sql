@@ -0,0 +1,5 @@
+-- Full-text search over deal names and metadata, matching the expression the deal listing filters on
+CREATE INDEX idx_deals_search ON deals
+    USING GIN (to_tsvector('simple', deal_name || ' ' || coalesce(metadata::text, '')));
+
+CREATE INDEX idx_deals_user_id_created_at ON deals(user_id, created_at DESC);
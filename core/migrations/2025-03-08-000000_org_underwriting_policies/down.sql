@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_underwriting_policies_org_id;
+
+ALTER TABLE underwriting_policies DROP COLUMN IF EXISTS org_id;
@@ -0,0 +1,4 @@
+-- Policies shared with an organization can be selected on the organization's deals
+ALTER TABLE underwriting_policies ADD COLUMN org_id TEXT;
+
+CREATE INDEX idx_underwriting_policies_org_id ON underwriting_policies(org_id);
@@ -295,6 +295,10 @@ macro_rules! prompt_templates {
 }
 
 const PROMPT_TEMPLATES: &[(&str, &str)] = prompt_templates![
+    "document_classification_system",
+    "document_classification_user",
+    "fact_extraction_system",
+    "fact_extraction_user",
     "formula",
     "formula_extended",
     "html_caption",
@@ -340,7 +344,9 @@ const PROMPT_TEMPLATES: &[(&str, &str)] = prompt_templates![
     "md_text",
     "md_text_extended",
     "md_title",
-    "md_title_extended"
+    "md_title_extended",
+    "structured_extraction_system",
+    "structured_extraction_user"
 ];
 
 fn load_prompt_template(prompt_name: &str) -> Result<String, std::io::Error> {
@@ -404,6 +410,31 @@ mod tests {
         TEST_TEMPLATE_JSON.to_string()
     }
 
+    #[test]
+    fn test_default_model_without_model_id() {
+        let model = |id: &str, default: bool| LlmModel {
+            id: id.to_string(),
+            model: id.to_string(),
+            provider_url: "https://example.com/v1".to_string(),
+            api_key: "key".to_string(),
+            default,
+            fallback: false,
+            rate_limit: None,
+        };
+        let config = Config {
+            fallback_model: None,
+            key: None,
+            model: None,
+            url: None,
+            llm_models: Some(vec![model("small", false), model("large", true)]),
+        };
+
+        // The structured extraction route runs with the default processing settings
+        let model_id = LlmProcessing::default().model_id;
+        assert_eq!(config.get_model(model_id).unwrap().id, "large");
+        assert_eq!(config.get_model(Some("small".to_string())).unwrap().id, "small");
+    }
+
     #[tokio::test]
     async fn test_load_template() -> Result<(), Box<dyn std::error::Error>> {
         let prompt = load_prompt_template("formula")?;
@@ -1,5 +1,7 @@
 use config::{Config as ConfigTrait, ConfigError};
 use deadpool_postgres::Runtime;
+use diesel::r2d2::{ConnectionManager, Pool as R2d2Pool, PooledConnection};
+use diesel::PgConnection;
 pub use deadpool_postgres::{Client, Pool};
 use dotenvy::dotenv_override;
 use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
@@ -7,6 +9,9 @@ use postgres_openssl::MakeTlsConnector;
 use serde::Deserialize;
 pub use tokio_postgres::Error;
 
+pub type DieselPool = R2d2Pool<ConnectionManager<PgConnection>>;
+pub type DieselConnection = PooledConnection<ConnectionManager<PgConnection>>;
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub pg: deadpool_postgres::Config,
@@ -32,3 +37,9 @@ pub fn create_pool() -> Pool {
         .create_pool(Some(Runtime::Tokio1), connector)
         .unwrap()
 }
+
+pub fn create_diesel_pool() -> DieselPool {
+    let cfg = Config::from_env().unwrap();
+    let url = cfg.pg.url.expect("PG__URL must be set");
+    R2d2Pool::builder().build_unchecked(ConnectionManager::<PgConnection>::new(url))
+}
@@ -8,8 +8,20 @@ pub enum FileUrlFormat {
     Url,
 }
 
+/// How facts are extracted from deal documents:
+/// - `Llm`: Sends the chunked document to the LLM with a schema per document type, falling back to `Regex` on failure.
+/// - `Regex`: Only uses the keyword patterns.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FactExtractionMode {
+    Llm,
+    Regex,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
+    #[serde(default = "default_fact_extraction_mode")]
+    pub fact_extraction_mode: FactExtractionMode,
     #[serde(default = "default_file_url_format")]
     pub file_url_format: FileUrlFormat,
     #[serde(default = "default_general_ocr_url")]
@@ -36,6 +48,10 @@ pub struct Config {
     pub version: String,
 }
 
+fn default_fact_extraction_mode() -> FactExtractionMode {
+    FactExtractionMode::Llm
+}
+
 fn default_file_url_format() -> FileUrlFormat {
     FileUrlFormat::Base64
 }
@@ -26,6 +26,8 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         metadata -> Jsonb,
+        policy_id -> Nullable<Text>,
+        org_id -> Nullable<Text>,
     }
 }
 
@@ -40,6 +42,11 @@ diesel::table! {
         page_count -> Nullable<Int4>,
         ocr_output -> Nullable<Jsonb>,
         created_at -> Timestamptz,
+        task_id -> Nullable<Text>,
+        classification_confidence -> Nullable<Float8>,
+        classification_alternatives -> Jsonb,
+        classification_source -> Nullable<Text>,
+        property_id -> Nullable<Text>,
     }
 }
 
@@ -59,6 +66,112 @@ diesel::table! {
         approved_by -> Nullable<Text>,
         locked -> Bool,
         created_at -> Timestamptz,
+        period_basis -> Nullable<Text>,
+        property_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    fact_events (event_id) {
+        event_id -> Text,
+        fact_id -> Text,
+        deal_id -> Text,
+        fact_type -> Text,
+        event_type -> Text,
+        old_value -> Nullable<Text>,
+        new_value -> Nullable<Text>,
+        old_unit -> Nullable<Text>,
+        new_unit -> Nullable<Text>,
+        actor -> Nullable<Text>,
+        reason -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    fact_resolutions (resolution_id) {
+        resolution_id -> Text,
+        deal_id -> Text,
+        fact_type -> Text,
+        fact_id -> Text,
+        resolved_by -> Text,
+        resolved_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    pl_line_items (pl_line_item_id) {
+        pl_line_item_id -> Text,
+        document_id -> Text,
+        deal_id -> Text,
+        row_index -> Int4,
+        category -> Text,
+        label -> Text,
+        periods -> Array<Text>,
+        amounts -> Array<Nullable<Float8>>,
+        source_citation -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    properties (property_id) {
+        property_id -> Text,
+        deal_id -> Text,
+        name -> Text,
+        address -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    rent_roll_units (rent_roll_unit_id) {
+        rent_roll_unit_id -> Text,
+        document_id -> Text,
+        deal_id -> Text,
+        row_index -> Int4,
+        unit_id -> Text,
+        tenant -> Nullable<Text>,
+        square_feet -> Nullable<Float8>,
+        lease_start -> Nullable<Date>,
+        lease_end -> Nullable<Date>,
+        contract_rent -> Nullable<Float8>,
+        market_rent -> Nullable<Float8>,
+        status -> Text,
+        source_citation -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    underwriting_policies (policy_id) {
+        policy_id -> Text,
+        user_id -> Text,
+        name -> Text,
+        thresholds -> Jsonb,
+        required_fact_types -> Array<Text>,
+        severities -> Jsonb,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        org_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    underwriting_runs (run_id) {
+        run_id -> Text,
+        deal_id -> Text,
+        version -> Int4,
+        input_fact_ids -> Array<Text>,
+        inputs -> Jsonb,
+        result -> Jsonb,
+        audit_trail -> Jsonb,
+        warnings -> Array<Text>,
+        run_by -> Text,
+        created_at -> Timestamptz,
+        finalized_at -> Nullable<Timestamptz>,
+        finalized_by -> Nullable<Text>,
+        property_results -> Jsonb,
     }
 }
 
@@ -173,7 +286,8 @@ diesel::table! {
     usage (id) {
         id -> Int4,
         user_id -> Nullable<Text>,
-        usage -> Nullable<Int4>,
+        #[sql_name = "usage"]
+        usage_ -> Nullable<Int4>,
         usage_limit -> Nullable<Int4>,
         usage_type -> Nullable<Text>,
         unit -> Nullable<Text>,
@@ -219,21 +333,38 @@ diesel::table! {
 
 diesel::joinable!(deals -> users (user_id));
 diesel::joinable!(documents -> deals (deal_id));
+diesel::joinable!(fact_events -> deals (deal_id));
+diesel::joinable!(fact_resolutions -> deals (deal_id));
+diesel::joinable!(fact_resolutions -> facts (fact_id));
 diesel::joinable!(facts -> deals (deal_id));
 diesel::joinable!(facts -> documents (document_id));
+diesel::joinable!(pl_line_items -> deals (deal_id));
+diesel::joinable!(pl_line_items -> documents (document_id));
+diesel::joinable!(properties -> deals (deal_id));
+diesel::joinable!(rent_roll_units -> deals (deal_id));
+diesel::joinable!(rent_roll_units -> documents (document_id));
+diesel::joinable!(underwriting_policies -> users (user_id));
+diesel::joinable!(underwriting_runs -> deals (deal_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     api_keys,
     deals,
     discounts,
     documents,
+    fact_events,
+    fact_resolutions,
     facts,
     invoices,
     monthly_usage,
+    pl_line_items,
     pre_applied_free_pages,
+    properties,
+    rent_roll_units,
     segment_process,
     task_invoices,
     tasks,
+    underwriting_policies,
+    underwriting_runs,
     usage,
     usage_limits,
     usage_type,
@@ -19,6 +19,7 @@ use utoipa_redoc::{Redoc, Servable};
 use utoipa_swagger_ui::SwaggerUi;
 
 pub mod configs;
+pub mod data;
 pub mod jobs;
 pub mod middleware;
 pub mod models;
@@ -30,9 +31,15 @@ pub mod utils;
 use jobs::init::init_jobs;
 use middleware::auth::AuthMiddlewareFactory;
 use routes::deal::{
-    approve_facts_route, calculate_underwriting_route, create_deal_route, get_deal_documents,
-    get_deal_facts, get_deal_route, get_deals_route, reset_facts_route, update_fact_route,
-    upload_deal_documents,
+    approve_facts_route, calculate_underwriting_route, create_deal_route, create_property_route,
+    diff_underwriting_runs_route, export_credit_memo_route, export_deal_workbook_route,
+    finalize_underwriting_run_route, get_deal_documents, get_deal_facts,
+    get_deal_recommendations_route, get_deal_route, get_deals_route, get_fact_conflicts_route,
+    get_fact_history_route, get_pro_forma_route, get_profit_and_loss_route, get_properties_route,
+    get_rent_roll_units_route, get_underwriting_runs_route, reject_fact_route, reset_facts_route,
+    resolve_fact_conflict_route, set_deal_policy_route, set_document_property_route,
+    set_document_type_route, set_pro_forma_assumptions_route, share_deal_route,
+    stress_test_route, update_fact_route, upload_deal_documents,
 };
 use routes::github::get_github_repo_info;
 use routes::health::health_check;
@@ -42,11 +49,17 @@ use routes::stripe::{
     get_billing_portal_session, get_checkout_session, get_invoice_detail, get_monthly_usage,
     get_user_invoices, stripe_webhook,
 };
+use routes::structured_extraction::handle_structured_extraction_route;
 use routes::task::{
     cancel_task_route, create_task_route, create_task_route_multipart, delete_task_route,
     get_task_route, update_task_route, update_task_route_multipart,
 };
 use routes::tasks::get_tasks_route;
+use routes::underwriting_policy::{
+    create_underwriting_policy_route, delete_underwriting_policy_route,
+    get_underwriting_policies_route, get_underwriting_policy_route,
+    update_underwriting_policy_route,
+};
 use routes::user::get_or_create_user;
 use utils::clients::initialize;
 use utils::routes::admin_user::get_or_create_admin_user;
@@ -81,6 +94,7 @@ fn run_migrations(url: &str) {
         routes::task::cancel_task_route,
         routes::task::update_task_route,
         routes::tasks::get_tasks_route,
+        routes::structured_extraction::handle_structured_extraction_route,
     ),
     components(
         schemas(
@@ -96,6 +110,11 @@ fn run_migrations(url: &str) {
             models::segment_processing::GenerationStrategy,
             models::segment_processing::LlmGenerationConfig,
             models::segment_processing::SegmentProcessing,
+            models::structured_extraction::ExtractionType,
+            models::structured_extraction::JsonSchema,
+            models::structured_extraction::StructuredExtraction,
+            models::structured_extraction::StructuredExtractionRequest,
+            models::structured_extraction::StructuredExtractionResponse,
             models::task::Configuration,
             models::task::Model,
             models::task::Status,
@@ -112,7 +131,8 @@ fn run_migrations(url: &str) {
     tags(
         (name = "Health", description = "Endpoint for checking the health of the service."),
         (name = "Task", description = "Endpoints for managing individual tasks - create, read, update, delete and cancel operations"),
-        (name = "Tasks", description = "Endpoints for listing multiple tasks")
+        (name = "Tasks", description = "Endpoints for listing multiple tasks"),
+        (name = "Structured Extraction", description = "Endpoint for extracting structured data from document chunks")
     )
 )]
 pub struct ApiDoc;
@@ -180,6 +200,7 @@ pub fn main() -> std::io::Result<()> {
                         .allow_any_origin()
                         .allow_any_method()
                         .allow_any_header()
+                        .expose_headers(["X-Total-Count"])
                 )
                 .wrap(Logger::default())
                 .wrap(Logger::new("%a %{User-Agent}i"))
@@ -208,13 +229,54 @@ pub fn main() -> std::io::Result<()> {
                         .route("", web::post().to(create_deal_route))
                         .route("", web::get().to(get_deals_route))
                         .route("/{deal_id}", web::get().to(get_deal_route))
+                        .route("/{deal_id}/policy", web::put().to(set_deal_policy_route))
+                        .route("/{deal_id}/org", web::put().to(share_deal_route))
                         .route("/{deal_id}/documents", web::post().to(upload_deal_documents))
                         .route("/{deal_id}/documents", web::get().to(get_deal_documents))
+                        .route("/{deal_id}/documents/{document_id}/type", web::put().to(set_document_type_route))
+                        .route("/{deal_id}/documents/{document_id}/property", web::put().to(set_document_property_route))
+                        .route("/{deal_id}/properties", web::post().to(create_property_route))
+                        .route("/{deal_id}/properties", web::get().to(get_properties_route))
+                        .route("/{deal_id}/documents/{document_id}/rent-roll", web::get().to(get_rent_roll_units_route))
+                        .route("/{deal_id}/documents/{document_id}/profit-and-loss", web::get().to(get_profit_and_loss_route))
                         .route("/{deal_id}/facts", web::get().to(get_deal_facts))
                         .route("/{deal_id}/facts/{fact_id}", web::patch().to(update_fact_route))
                         .route("/{deal_id}/facts/approve", web::post().to(approve_facts_route))
                         .route("/{deal_id}/facts/reset", web::post().to(reset_facts_route))
-                        .route("/{deal_id}/underwrite", web::post().to(calculate_underwriting_route)),
+                        .route(
+                            "/{deal_id}/facts/{fact_id}/history",
+                            web::get().to(get_fact_history_route),
+                        )
+                        .route(
+                            "/{deal_id}/facts/{fact_id}/reject",
+                            web::post().to(reject_fact_route),
+                        )
+                        .route("/{deal_id}/underwrite", web::post().to(calculate_underwriting_route))
+                        .route("/{deal_id}/export.xlsx", web::get().to(export_deal_workbook_route))
+                        .route("/{deal_id}/memo.pdf", web::get().to(export_credit_memo_route))
+                        .route("/{deal_id}/underwriting-runs", web::get().to(get_underwriting_runs_route))
+                        .route("/{deal_id}/underwriting-runs/diff", web::get().to(diff_underwriting_runs_route))
+                        .route(
+                            "/{deal_id}/underwriting-runs/{run_id}/finalize",
+                            web::post().to(finalize_underwriting_run_route),
+                        )
+                        .route("/{deal_id}/stress-test", web::post().to(stress_test_route))
+                        .route("/{deal_id}/pro-forma", web::get().to(get_pro_forma_route))
+                        .route(
+                            "/{deal_id}/pro-forma/assumptions",
+                            web::put().to(set_pro_forma_assumptions_route),
+                        )
+                        .route("/{deal_id}/recommendations", web::get().to(get_deal_recommendations_route))
+                        .route("/{deal_id}/conflicts", web::get().to(get_fact_conflicts_route))
+                        .route("/{deal_id}/conflicts/{fact_type}/resolve", web::post().to(resolve_fact_conflict_route)),
+                )
+                .service(
+                    web::scope("/underwriting-policies")
+                        .route("", web::post().to(create_underwriting_policy_route))
+                        .route("", web::get().to(get_underwriting_policies_route))
+                        .route("/{policy_id}", web::get().to(get_underwriting_policy_route))
+                        .route("/{policy_id}", web::patch().to(update_underwriting_policy_route))
+                        .route("/{policy_id}", web::delete().to(delete_underwriting_policy_route)),
                 )
                 .service(
                     web::scope("/task")
@@ -227,6 +289,10 @@ pub fn main() -> std::io::Result<()> {
                         .route("/{task_id}/cancel", web::get().to(cancel_task_route)),
                 )
                 .route("/tasks", web::get().to(get_tasks_route))
+                .route(
+                    "/structured_extract",
+                    web::post().to(handle_structured_extraction_route),
+                )
                 .route("/usage/monthly", web::get().to(get_monthly_usage));
 
             if std::env::var("STRIPE__API_KEY").is_ok() {
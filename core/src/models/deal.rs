@@ -16,6 +16,9 @@ pub struct Deal {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub metadata: JsonValue,
+    pub policy_id: Option<String>,
+    /// Organization the deal is shared with
+    pub org_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
@@ -27,6 +30,10 @@ pub struct NewDeal {
     pub status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, AsChangeset, ToSchema)]
@@ -40,7 +47,7 @@ pub struct UpdateDeal {
     pub metadata: Option<JsonValue>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
 pub enum DealStatus {
     Draft,
     ProcessingDocuments,
@@ -60,6 +67,7 @@ impl DealStatus {
         }
     }
     
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "draft" => Some(DealStatus::Draft),
@@ -70,11 +78,167 @@ impl DealStatus {
             _ => None,
         }
     }
+
+    /// Whether a deal may move from this status to `next`
+    ///
+    /// Uploading documents reopens a deal under review, and resetting facts moves a deal ready for
+    /// underwriting back to review. A complete deal is final.
+    pub fn can_transition_to(&self, next: &DealStatus) -> bool {
+        matches!(
+            (self, next),
+            (DealStatus::Draft, DealStatus::ProcessingDocuments)
+                | (DealStatus::ProcessingDocuments, DealStatus::FactReview)
+                | (DealStatus::FactReview, DealStatus::ProcessingDocuments)
+                | (DealStatus::FactReview, DealStatus::ReadyForUnderwriting)
+                | (DealStatus::ReadyForUnderwriting, DealStatus::ProcessingDocuments)
+                | (DealStatus::ReadyForUnderwriting, DealStatus::FactReview)
+                | (DealStatus::ReadyForUnderwriting, DealStatus::Complete)
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateDealRequest {
     pub deal_name: String,
+    /// Underwriting policy to apply, defaults to the standard thresholds
+    #[serde(default)]
+    pub policy_id: Option<String>,
+    /// Organization to share the deal with, which the creator must belong to
+    #[serde(default)]
+    pub org_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetDealPolicyRequest {
+    /// Policy to select, or null to fall back to the standard thresholds
+    pub policy_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ShareDealRequest {
+    /// Organization to share the deal with, or null to make it private to its creator
+    pub org_id: Option<String>,
+}
+
+/// Deals listed per page when the request does not say
+pub const DEFAULT_DEAL_PAGE_SIZE: i64 = 50;
+/// Most deals listed per page
+pub const MAX_DEAL_PAGE_SIZE: i64 = 500;
+
+/// Column the deal listing is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum DealSort {
+    CreatedAt,
+    UpdatedAt,
+    DealName,
+    Status,
+}
+
+impl DealSort {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DealSort::CreatedAt => "created_at",
+            DealSort::UpdatedAt => "updated_at",
+            DealSort::DealName => "deal_name",
+            DealSort::Status => "status",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "created_at" => Some(DealSort::CreatedAt),
+            "updated_at" => Some(DealSort::UpdatedAt),
+            "deal_name" => Some(DealSort::DealName),
+            "status" => Some(DealSort::Status),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DealListQuery {
+    /// Page to return, starting at 1
+    pub page: Option<i64>,
+    /// Deals per page, 50 by default and at most 500
+    pub limit: Option<i64>,
+    /// Comma-separated statuses to keep, e.g. `fact_review,ready_for_underwriting`
+    pub status: Option<String>,
+    /// Keep deals created at or after this time
+    pub start: Option<DateTime<Utc>>,
+    /// Keep deals created at or before this time
+    pub end: Option<DateTime<Utc>>,
+    /// Keep deals whose name contains this text, ignoring case
+    pub name: Option<String>,
+    /// Full-text search over deal names and metadata
+    pub q: Option<String>,
+    /// `created_at` (default), `updated_at`, `deal_name` or `status`
+    pub sort: Option<String>,
+    /// `asc` or `desc`, newest or last first by default
+    pub order: Option<String>,
+}
+
+impl DealListQuery {
+    /// Offset and limit of the requested page
+    pub fn pagination(&self) -> Result<(i64, i64), String> {
+        let limit = self.limit.unwrap_or(DEFAULT_DEAL_PAGE_SIZE);
+        if !(1..=MAX_DEAL_PAGE_SIZE).contains(&limit) {
+            return Err(format!("limit must be between 1 and {}", MAX_DEAL_PAGE_SIZE));
+        }
+        let page = self.page.unwrap_or(1);
+        if page < 1 {
+            return Err("page must be 1 or more".to_string());
+        }
+        let offset = (page - 1)
+            .checked_mul(limit)
+            .ok_or_else(|| "page is too large".to_string())?;
+        Ok((offset, limit))
+    }
+
+    pub fn statuses(&self) -> Result<Vec<String>, String> {
+        let Some(status) = &self.status else {
+            return Ok(vec![]);
+        };
+        status
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| match DealStatus::from_str(s) {
+                Some(status) => Ok(status.as_str().to_string()),
+                None => Err(format!("Unknown deal status '{}'", s)),
+            })
+            .collect()
+    }
+
+    /// Sort column and whether it is ascending
+    pub fn sorting(&self) -> Result<(DealSort, bool), String> {
+        let sort = match self.sort.as_deref() {
+            Some(sort) => DealSort::from_str(sort)
+                .ok_or_else(|| format!("Unknown sort '{}'", sort))?,
+            None => DealSort::CreatedAt,
+        };
+        let ascending = match self.order.as_deref() {
+            Some("asc") => true,
+            Some("desc") => false,
+            Some(order) => return Err(format!("Unknown order '{}'", order)),
+            // Names and statuses read alphabetically, dates newest first
+            None => matches!(sort, DealSort::DealName | DealSort::Status),
+        };
+        Ok((sort, ascending))
+    }
+
+    /// `ILIKE` pattern matching names that contain the `name` filter literally
+    pub fn name_pattern(&self) -> Option<String> {
+        let name = self.name.as_deref()?.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let escaped = name
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        Some(format!("%{}%", escaped))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -86,6 +250,11 @@ pub struct DealResponse {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub metadata: JsonValue,
+    pub policy_id: Option<String>,
+    pub org_id: Option<String>,
+    /// Role of the requesting user on the deal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
     pub document_count: Option<i64>,
     pub fact_count: Option<i64>,
 }
@@ -100,9 +269,47 @@ impl From<Deal> for DealResponse {
             created_at: deal.created_at,
             updated_at: deal.updated_at,
             metadata: deal.metadata,
+            policy_id: deal.policy_id,
+            org_id: deal.org_id,
+            role: None,
             document_count: None,
             fact_count: None,
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deal_list_query() {
+        let query = DealListQuery {
+            page: Some(3),
+            limit: Some(20),
+            status: Some("fact_review, complete".to_string()),
+            name: Some("50%_off".to_string()),
+            sort: Some("deal_name".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(query.pagination(), Ok((40, 20)));
+        assert_eq!(
+            query.statuses(),
+            Ok(vec!["fact_review".to_string(), "complete".to_string()])
+        );
+        assert_eq!(query.sorting(), Ok((DealSort::DealName, true)));
+        assert_eq!(query.name_pattern().as_deref(), Some("%50\\%\\_off%"));
+
+        let defaults = DealListQuery::default();
+        assert_eq!(defaults.pagination(), Ok((0, DEFAULT_DEAL_PAGE_SIZE)));
+        assert_eq!(defaults.sorting(), Ok((DealSort::CreatedAt, false)));
+        assert!(DealListQuery { limit: Some(0), ..Default::default() }.pagination().is_err());
+        assert!(DealListQuery { page: Some(i64::MAX), ..Default::default() }
+            .pagination()
+            .is_err());
+        assert!(DealListQuery { status: Some("closed".to_string()), ..Default::default() }
+            .statuses()
+            .is_err());
+    }
+}
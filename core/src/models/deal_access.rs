@@ -0,0 +1,124 @@
+use crate::data::schema::api_keys;
+use crate::models::deal::Deal;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+/// What a user may do on a deal, each role allowing everything the ones before it do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub enum DealRole {
+    /// Read the deal, its facts and underwriting
+    Viewer,
+    /// Upload documents, edit facts and run underwriting
+    Analyst,
+    /// Approve and lock facts, select the policy and finalize underwriting
+    Approver,
+    /// The deal's creator, who also decides who it is shared with
+    Owner,
+}
+
+impl DealRole {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DealRole::Viewer => "viewer",
+            DealRole::Analyst => "analyst",
+            DealRole::Approver => "approver",
+            DealRole::Owner => "owner",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "viewer" => Some(DealRole::Viewer),
+            "analyst" => Some(DealRole::Analyst),
+            "approver" => Some(DealRole::Approver),
+            "owner" => Some(DealRole::Owner),
+            _ => None,
+        }
+    }
+
+    /// Role of a user on a deal, `None` when the user cannot see it
+    pub fn for_user(
+        conn: &mut PgConnection,
+        deal: &Deal,
+        user_id: &str,
+    ) -> QueryResult<Option<Self>> {
+        if deal.user_id == user_id {
+            return Ok(Some(DealRole::Owner));
+        }
+        match &deal.org_id {
+            Some(org_id) => Ok(OrgMembership::for_user(conn, user_id)?
+                .into_iter()
+                .find(|membership| membership.org_id == *org_id)
+                .map(|membership| membership.role)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Why a user may not act on a deal
+#[derive(Debug, Error)]
+pub enum DealAccessError {
+    /// The deal does not exist or the user cannot see it
+    #[error("Deal not found")]
+    NotFound,
+
+    /// The user can see the deal but lacks the role the action needs
+    #[error("This requires the {} role on the deal", .0.as_str())]
+    Forbidden(DealRole),
+
+    #[error(transparent)]
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for DealAccessError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => DealAccessError::NotFound,
+            e => DealAccessError::Database(e),
+        }
+    }
+}
+
+/// A user's role in an organization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct OrgMembership {
+    pub org_id: String,
+    pub role: DealRole,
+}
+
+impl OrgMembership {
+    /// Organizations a user belongs to, read from the org and access level of their active API
+    /// keys
+    ///
+    /// A user with several keys in an organization gets the highest of their roles. Keys with an
+    /// access level other than viewer, analyst or approver give no role.
+    pub fn for_user(conn: &mut PgConnection, user_id: &str) -> QueryResult<Vec<Self>> {
+        let keys: Vec<(Option<String>, Option<String>)> = api_keys::table
+            .filter(api_keys::user_id.eq(user_id))
+            .filter(api_keys::org_id.is_not_null())
+            .filter(api_keys::active.eq(true))
+            .filter(api_keys::deleted.eq(false))
+            .select((api_keys::org_id, api_keys::access_level))
+            .load(conn)?;
+
+        let mut memberships: Vec<OrgMembership> = Vec::new();
+        for (org_id, access_level) in keys {
+            let role = match access_level.as_deref().and_then(DealRole::from_str) {
+                Some(role) if role != DealRole::Owner => role,
+                _ => continue,
+            };
+            let org_id = match org_id {
+                Some(org_id) => org_id,
+                None => continue,
+            };
+            match memberships.iter_mut().find(|m| m.org_id == org_id) {
+                Some(membership) => membership.role = membership.role.max(role),
+                None => memberships.push(OrgMembership { org_id, role }),
+            }
+        }
+        Ok(memberships)
+    }
+}
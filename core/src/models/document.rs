@@ -19,6 +19,12 @@ pub struct Document {
     pub page_count: Option<i32>,
     pub ocr_output: Option<JsonValue>,
     pub created_at: DateTime<Utc>,
+    pub task_id: Option<String>,
+    pub classification_confidence: Option<f64>,
+    pub classification_alternatives: JsonValue,
+    pub classification_source: Option<String>,
+    /// The portfolio property the document describes
+    pub property_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
@@ -35,6 +41,12 @@ pub struct NewDocument {
     pub page_count: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ocr_output: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification_source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, AsChangeset, ToSchema)]
@@ -48,9 +60,88 @@ pub struct UpdateDocument {
     pub page_count: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ocr_output: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification_confidence: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification_alternatives: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification_source: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+impl Document {
+    /// Find the deal document that is processed by the given task
+    pub fn find_by_task_id(conn: &mut PgConnection, task_id: &str) -> QueryResult<Option<Self>> {
+        documents::table
+            .filter(documents::task_id.eq(task_id))
+            .first::<Self>(conn)
+            .optional()
+    }
+
+    pub fn update(
+        conn: &mut PgConnection,
+        document_id: &str,
+        update: &UpdateDocument,
+    ) -> QueryResult<Self> {
+        diesel::update(documents::table.filter(documents::document_id.eq(document_id)))
+            .set(update)
+            .get_result::<Self>(conn)
+    }
+
+    /// Point a document at the task processing its file
+    pub fn set_task(
+        conn: &mut PgConnection,
+        document_id: &str,
+        task_id: &str,
+        storage_location: &str,
+    ) -> QueryResult<Self> {
+        diesel::update(documents::table.filter(documents::document_id.eq(document_id)))
+            .set((
+                documents::task_id.eq(task_id),
+                documents::storage_location.eq(storage_location),
+            ))
+            .get_result::<Self>(conn)
+    }
+
+    /// Type chosen by the user at upload or as an override of the classification
+    pub fn user_document_type(&self) -> Option<DocumentType> {
+        match self.classification_source.as_deref() {
+            Some("user") => DocumentType::from_str(&self.document_type),
+            _ => None,
+        }
+    }
+
+    /// Other types the classifier considered, most likely first
+    pub fn classification_alternatives(&self) -> Vec<ClassificationAlternative> {
+        serde_json::from_value(self.classification_alternatives.clone()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+impl Document {
+    /// A one page document of deal-1 being processed as doc-1, with no classification
+    pub fn for_test(file_name: &str, document_type: &str) -> Self {
+        Document {
+            document_id: "doc-1".to_string(),
+            deal_id: "deal-1".to_string(),
+            file_name: file_name.to_string(),
+            document_type: document_type.to_string(),
+            status: "processing".to_string(),
+            storage_location: None,
+            page_count: Some(1),
+            ocr_output: None,
+            created_at: Utc::now(),
+            task_id: Some("task-1".to_string()),
+            classification_confidence: None,
+            classification_alternatives: serde_json::json!([]),
+            classification_source: None,
+            property_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub enum DocumentType {
     RentRoll,
     ProfitAndLoss,
@@ -59,6 +150,8 @@ pub enum DocumentType {
     BankStatement,
     PropertyDeed,
     InsurancePolicy,
+    Lease,
+    Appraisal,
     Other,
 }
 
@@ -72,10 +165,13 @@ impl DocumentType {
             DocumentType::BankStatement => "bank_statement",
             DocumentType::PropertyDeed => "property_deed",
             DocumentType::InsurancePolicy => "insurance_policy",
+            DocumentType::Lease => "lease",
+            DocumentType::Appraisal => "appraisal",
             DocumentType::Other => "other",
         }
     }
     
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "rent_roll" => Some(DocumentType::RentRoll),
@@ -85,12 +181,53 @@ impl DocumentType {
             "bank_statement" => Some(DocumentType::BankStatement),
             "property_deed" => Some(DocumentType::PropertyDeed),
             "insurance_policy" => Some(DocumentType::InsurancePolicy),
+            "lease" => Some(DocumentType::Lease),
+            "appraisal" => Some(DocumentType::Appraisal),
             "other" => Some(DocumentType::Other),
             _ => None,
         }
     }
 }
 
+/// How the type of a document was determined
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassificationSource {
+    /// Scored from the titles, section headers and tables of the first pages
+    Layout,
+    /// Chosen by the LLM when the layout was inconclusive
+    Llm,
+    /// Chosen at upload or overridden by the user
+    User,
+}
+
+impl ClassificationSource {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ClassificationSource::Layout => "layout",
+            ClassificationSource::Llm => "llm",
+            ClassificationSource::User => "user",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "layout" => Some(ClassificationSource::Layout),
+            "llm" => Some(ClassificationSource::Llm),
+            "user" => Some(ClassificationSource::User),
+            _ => None,
+        }
+    }
+}
+
+/// A document type the classifier considered, with its confidence between 0 and 1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ClassificationAlternative {
+    pub document_type: String,
+    pub confidence: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum DocumentStatus {
     Pending,
@@ -109,6 +246,7 @@ impl DocumentStatus {
         }
     }
     
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "pending" => Some(DocumentStatus::Pending),
@@ -129,12 +267,18 @@ pub struct DocumentResponse {
     pub status: String,
     pub storage_location: Option<String>,
     pub page_count: Option<i32>,
+    pub task_id: Option<String>,
     pub created_at: DateTime<Utc>,
     pub fact_count: Option<i64>,
+    pub classification_confidence: Option<f64>,
+    pub classification_alternatives: Vec<ClassificationAlternative>,
+    pub classification_source: Option<String>,
+    pub property_id: Option<String>,
 }
 
 impl From<Document> for DocumentResponse {
     fn from(doc: Document) -> Self {
+        let classification_alternatives = doc.classification_alternatives();
         DocumentResponse {
             document_id: doc.document_id,
             deal_id: doc.deal_id,
@@ -143,9 +287,19 @@ impl From<Document> for DocumentResponse {
             status: doc.status,
             storage_location: doc.storage_location,
             page_count: doc.page_count,
+            task_id: doc.task_id,
             created_at: doc.created_at,
             fact_count: None,
+            classification_confidence: doc.classification_confidence,
+            classification_alternatives,
+            classification_source: doc.classification_source,
+            property_id: doc.property_id,
         }
     }
 }
 
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetDocumentTypeRequest {
+    pub document_type: String,
+}
@@ -25,6 +25,9 @@ pub struct Fact {
     pub approved_by: Option<String>,
     pub locked: bool,
     pub created_at: DateTime<Utc>,
+    pub period_basis: Option<String>,
+    /// The portfolio property the fact describes, taken from its document
+    pub property_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
@@ -42,6 +45,10 @@ pub struct NewFact {
     pub status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub confidence_score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_basis: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, AsChangeset, ToSchema)]
@@ -67,8 +74,13 @@ pub struct SourceCitation {
     pub page: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub line: Option<String>,
+    /// Position on the page, in the same coordinates as the page dimensions
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bbox: Option<BoundingBox>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_width: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_height: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -91,6 +103,17 @@ pub enum FactType {
     PropertyValue,
     MortgageBalance,
     InterestRate,
+    AverageBankBalance,
+    BankDeposits,
+    InsuranceCoverage,
+    InsurancePremium,
+    InsuranceExpiration,
+    LeaseTermMonths,
+    LeaseBaseRent,
+    RentEscalation,
+    LeaseExpiration,
+    AppraisedValue,
+    AppraisalCapRate,
     Other,
 }
 
@@ -107,10 +130,22 @@ impl FactType {
             FactType::PropertyValue => "property_value",
             FactType::MortgageBalance => "mortgage_balance",
             FactType::InterestRate => "interest_rate",
+            FactType::AverageBankBalance => "average_bank_balance",
+            FactType::BankDeposits => "bank_deposits",
+            FactType::InsuranceCoverage => "insurance_coverage",
+            FactType::InsurancePremium => "insurance_premium",
+            FactType::InsuranceExpiration => "insurance_expiration",
+            FactType::LeaseTermMonths => "lease_term_months",
+            FactType::LeaseBaseRent => "lease_base_rent",
+            FactType::RentEscalation => "rent_escalation",
+            FactType::LeaseExpiration => "lease_expiration",
+            FactType::AppraisedValue => "appraised_value",
+            FactType::AppraisalCapRate => "appraisal_cap_rate",
             FactType::Other => "other",
         }
     }
     
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "unit_count" => Some(FactType::UnitCount),
@@ -123,12 +158,55 @@ impl FactType {
             "property_value" => Some(FactType::PropertyValue),
             "mortgage_balance" => Some(FactType::MortgageBalance),
             "interest_rate" => Some(FactType::InterestRate),
+            "average_bank_balance" => Some(FactType::AverageBankBalance),
+            "bank_deposits" => Some(FactType::BankDeposits),
+            "insurance_coverage" => Some(FactType::InsuranceCoverage),
+            "insurance_premium" => Some(FactType::InsurancePremium),
+            "insurance_expiration" => Some(FactType::InsuranceExpiration),
+            "lease_term_months" => Some(FactType::LeaseTermMonths),
+            "lease_base_rent" => Some(FactType::LeaseBaseRent),
+            "rent_escalation" => Some(FactType::RentEscalation),
+            "lease_expiration" => Some(FactType::LeaseExpiration),
+            "appraised_value" => Some(FactType::AppraisedValue),
+            "appraisal_cap_rate" => Some(FactType::AppraisalCapRate),
             "other" => Some(FactType::Other),
             _ => None,
         }
     }
 }
 
+/// Period a fact derived from a multi-period statement was computed over
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum PeriodBasis {
+    /// Sum of the last 12 months
+    #[default]
+    Trailing12,
+    /// Sum of the last 3 months, annualized
+    Trailing3,
+    /// Sum of every period in the statement, annualized
+    Annualized,
+}
+
+impl PeriodBasis {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PeriodBasis::Trailing12 => "trailing_12",
+            PeriodBasis::Trailing3 => "trailing_3",
+            PeriodBasis::Annualized => "annualized",
+        }
+    }
+    
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "trailing_12" => Some(PeriodBasis::Trailing12),
+            "trailing_3" => Some(PeriodBasis::Trailing3),
+            "annualized" => Some(PeriodBasis::Annualized),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum FactStatus {
     PendingApproval,
@@ -145,6 +223,7 @@ impl FactStatus {
         }
     }
     
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "pending_approval" => Some(FactStatus::PendingApproval),
@@ -171,6 +250,8 @@ pub struct FactResponse {
     pub approved_by: Option<String>,
     pub locked: bool,
     pub created_at: DateTime<Utc>,
+    pub period_basis: Option<String>,
+    pub property_id: Option<String>,
 }
 
 impl Fact {
@@ -190,19 +271,54 @@ impl Fact {
             approved_by: self.approved_by.clone(),
             locked: self.locked,
             created_at: self.created_at,
+            period_basis: self.period_basis.clone(),
+            property_id: self.property_id.clone(),
         })
     }
 }
 
+#[cfg(test)]
+impl Fact {
+    /// An approved and locked fact of deal-1, cited on page 1 of doc-1
+    pub fn for_test(fact_id: &str, fact_type: &str, value: &str) -> Self {
+        Fact {
+            fact_id: fact_id.to_string(),
+            document_id: "doc-1".to_string(),
+            deal_id: "deal-1".to_string(),
+            fact_type: fact_type.to_string(),
+            label: fact_type.to_string(),
+            value: value.to_string(),
+            unit: None,
+            source_citation: serde_json::json!({ "document": "doc-1", "page": 1 }),
+            status: "approved".to_string(),
+            confidence_score: Some(0.9),
+            approved_at: None,
+            approved_by: None,
+            locked: true,
+            created_at: Utc::now(),
+            period_basis: None,
+            property_id: None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UpdateFactValueRequest {
+    /// Read according to the fact's unit, e.g. "$1,234,567", "1.2M", "(45,000)" or "$4,000/mo"
     pub value: String,
+    /// Defaults to the fact's current unit. A period given with the value replaces its period.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub unit: Option<String>,
+    /// Reason recorded in the audit log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ApproveFactsRequest {
     pub fact_ids: Vec<String>,
+    /// Reason recorded in the audit log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
@@ -0,0 +1,257 @@
+use crate::data::schema::fact_events;
+use crate::models::fact::{Fact, NewFact};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Associations, ToSchema)]
+#[diesel(table_name = fact_events)]
+#[diesel(primary_key(event_id))]
+#[diesel(belongs_to(crate::models::deal::Deal, foreign_key = deal_id))]
+pub struct FactEvent {
+    pub event_id: String,
+    pub fact_id: String,
+    pub deal_id: String,
+    pub fact_type: String,
+    pub event_type: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub old_unit: Option<String>,
+    pub new_unit: Option<String>,
+    /// User who made the change, `None` for the extraction pipeline
+    pub actor: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = fact_events)]
+pub struct NewFactEvent {
+    pub event_id: String,
+    pub fact_id: String,
+    pub deal_id: String,
+    pub fact_type: String,
+    pub event_type: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub old_unit: Option<String>,
+    pub new_unit: Option<String>,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl NewFactEvent {
+    /// An event that does not change the value of the fact, e.g. an approval
+    pub fn for_fact(
+        fact: &Fact,
+        event_type: FactEventType,
+        actor: Option<&str>,
+        reason: Option<String>,
+    ) -> Self {
+        Self::edited(fact, fact, event_type, actor, reason)
+    }
+
+    /// An event moving a fact from `before` to `after`
+    pub fn edited(
+        before: &Fact,
+        after: &Fact,
+        event_type: FactEventType,
+        actor: Option<&str>,
+        reason: Option<String>,
+    ) -> Self {
+        NewFactEvent {
+            event_id: uuid::Uuid::new_v4().to_string(),
+            fact_id: after.fact_id.clone(),
+            deal_id: after.deal_id.clone(),
+            fact_type: after.fact_type.clone(),
+            event_type: event_type.as_str().to_string(),
+            old_value: Some(before.value.clone()),
+            new_value: Some(after.value.clone()),
+            old_unit: before.unit.clone(),
+            new_unit: after.unit.clone(),
+            actor: actor.map(str::to_string),
+            reason,
+        }
+    }
+
+    /// A fact created by the extraction pipeline
+    pub fn extracted(fact: &NewFact, reason: Option<String>) -> Self {
+        NewFactEvent {
+            event_id: uuid::Uuid::new_v4().to_string(),
+            fact_id: fact.fact_id.clone(),
+            deal_id: fact.deal_id.clone(),
+            fact_type: fact.fact_type.clone(),
+            event_type: FactEventType::Extracted.as_str().to_string(),
+            old_value: None,
+            new_value: Some(fact.value.clone()),
+            old_unit: None,
+            new_unit: fact.unit.clone(),
+            actor: None,
+            reason,
+        }
+    }
+
+    /// A fact removed by the extraction pipeline, keeping its last value in the log
+    pub fn replaced(fact: &Fact, reason: Option<String>) -> Self {
+        NewFactEvent {
+            new_value: None,
+            new_unit: None,
+            ..Self::for_fact(fact, FactEventType::Replaced, None, reason)
+        }
+    }
+}
+
+impl FactEvent {
+    pub fn record(conn: &mut PgConnection, events: &[NewFactEvent]) -> QueryResult<usize> {
+        if events.is_empty() {
+            return Ok(0);
+        }
+        diesel::insert_into(fact_events::table)
+            .values(events)
+            .execute(conn)
+    }
+
+    /// Events of a fact, oldest first
+    pub fn history(
+        conn: &mut PgConnection,
+        deal_id: &str,
+        fact_id: &str,
+    ) -> QueryResult<Vec<Self>> {
+        fact_events::table
+            .filter(fact_events::deal_id.eq(deal_id))
+            .filter(fact_events::fact_id.eq(fact_id))
+            .order((fact_events::created_at.asc(), fact_events::event_id.asc()))
+            .load::<Self>(conn)
+    }
+
+    /// User who last edited each of the given facts, for the facts a user has edited
+    pub fn last_editors(
+        conn: &mut PgConnection,
+        deal_id: &str,
+        fact_ids: &[String],
+    ) -> QueryResult<HashMap<String, String>> {
+        let edits = fact_events::table
+            .filter(fact_events::deal_id.eq(deal_id))
+            .filter(fact_events::fact_id.eq_any(fact_ids))
+            .filter(fact_events::event_type.eq(FactEventType::Edited.as_str()))
+            .order((fact_events::created_at.asc(), fact_events::event_id.asc()))
+            .load::<Self>(conn)?;
+        Ok(last_editors(&edits))
+    }
+}
+
+/// Actor of the latest edit of each fact, from events ordered oldest first
+pub fn last_editors(events: &[FactEvent]) -> HashMap<String, String> {
+    events
+        .iter()
+        .filter(|event| event.event_type == FactEventType::Edited.as_str())
+        .filter_map(|event| Some((event.fact_id.clone(), event.actor.clone()?)))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum FactEventType {
+    Extracted,
+    Edited,
+    Approved,
+    Rejected,
+    Reset,
+    /// Picked as the value of a conflicting fact type
+    Resolved,
+    /// Removed when its document was extracted again
+    Replaced,
+}
+
+impl FactEventType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FactEventType::Extracted => "extracted",
+            FactEventType::Edited => "edited",
+            FactEventType::Approved => "approved",
+            FactEventType::Rejected => "rejected",
+            FactEventType::Reset => "reset",
+            FactEventType::Resolved => "resolved",
+            FactEventType::Replaced => "replaced",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "extracted" => Some(FactEventType::Extracted),
+            "edited" => Some(FactEventType::Edited),
+            "approved" => Some(FactEventType::Approved),
+            "rejected" => Some(FactEventType::Rejected),
+            "reset" => Some(FactEventType::Reset),
+            "resolved" => Some(FactEventType::Resolved),
+            "replaced" => Some(FactEventType::Replaced),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RejectFactRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ResetFactsRequest {
+    /// Reason recorded in the audit log
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(fact_id: &str, event_type: FactEventType, actor: Option<&str>) -> FactEvent {
+        FactEvent {
+            event_id: uuid::Uuid::new_v4().to_string(),
+            fact_id: fact_id.to_string(),
+            deal_id: "deal-1".to_string(),
+            fact_type: "collected_rent".to_string(),
+            event_type: event_type.as_str().to_string(),
+            old_value: None,
+            new_value: None,
+            old_unit: None,
+            new_unit: None,
+            actor: actor.map(str::to_string),
+            reason: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_last_editors() {
+        let events = vec![
+            event("f-rent", FactEventType::Extracted, None),
+            event("f-rent", FactEventType::Edited, Some("analyst-1")),
+            event("f-rent", FactEventType::Edited, Some("analyst-2")),
+            event("f-rent", FactEventType::Approved, Some("approver-1")),
+            event("f-opex", FactEventType::Extracted, None),
+            event("f-opex", FactEventType::Rejected, Some("analyst-1")),
+        ];
+        let editors = last_editors(&events);
+        assert_eq!(editors.len(), 1);
+        assert_eq!(editors.get("f-rent").map(String::as_str), Some("analyst-2"));
+    }
+
+    #[test]
+    fn test_replaced_event() {
+        let fact = Fact {
+            unit: Some("USD/month".to_string()),
+            locked: false,
+            ..Fact::for_test("f-rent", "collected_rent", "4200")
+        };
+        let event = NewFactEvent::replaced(&fact, Some("Extracted from t12.pdf".to_string()));
+        assert_eq!(event.event_type, "replaced");
+        assert_eq!(event.old_value.as_deref(), Some("4200"));
+        assert_eq!(event.old_unit.as_deref(), Some("USD/month"));
+        assert_eq!(event.new_value, None);
+        assert_eq!(event.actor, None);
+    }
+}
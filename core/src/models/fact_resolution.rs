@@ -0,0 +1,78 @@
+use crate::data::schema::fact_resolutions;
+use crate::models::fact::SourceCitation;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Associations, ToSchema)]
+#[diesel(table_name = fact_resolutions)]
+#[diesel(primary_key(resolution_id))]
+#[diesel(belongs_to(crate::models::deal::Deal, foreign_key = deal_id))]
+pub struct FactResolution {
+    pub resolution_id: String,
+    pub deal_id: String,
+    pub fact_type: String,
+    pub fact_id: String,
+    pub resolved_by: String,
+    pub resolved_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = fact_resolutions)]
+pub struct NewFactResolution {
+    pub resolution_id: String,
+    pub deal_id: String,
+    pub fact_type: String,
+    pub fact_id: String,
+    pub resolved_by: String,
+    pub resolved_at: DateTime<Utc>,
+}
+
+impl FactResolution {
+    pub fn for_deal(conn: &mut PgConnection, deal_id: &str) -> QueryResult<Vec<Self>> {
+        fact_resolutions::table
+            .filter(fact_resolutions::deal_id.eq(deal_id))
+            .load::<Self>(conn)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ResolveConflictRequest {
+    /// The fact to use for the conflicting fact type
+    pub fact_id: String,
+    /// Reason recorded in the audit log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConflictsQuery {
+    /// Relative difference above which values disagree, e.g. `0.02` for 2%
+    pub tolerance: Option<f64>,
+}
+
+/// A value reported for a fact type by one of the deal's documents
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConflictingFact {
+    pub fact_id: String,
+    pub document_id: String,
+    pub label: String,
+    pub value: f64,
+    pub status: String,
+    pub period_basis: Option<String>,
+    pub source_citation: Option<SourceCitation>,
+}
+
+/// Documents of a deal that disagree on a fact type beyond the tolerance
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FactConflict {
+    pub fact_type: String,
+    /// The portfolio property whose documents disagree, if any
+    pub property_id: Option<String>,
+    /// Difference between the highest and lowest value, relative to the largest magnitude
+    pub spread: f64,
+    pub facts: Vec<ConflictingFact>,
+    /// The fact picked by the reviewer, if the conflict has been resolved
+    pub resolved_fact_id: Option<String>,
+}
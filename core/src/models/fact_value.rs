@@ -0,0 +1,363 @@
+use crate::models::fact::{Fact, FactType};
+use crate::services::rent_roll::parse_date;
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Period at the end of a value, e.g. "$4,000/mo", "48k per year" or "18 months"
+static PERIOD_SUFFIX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"(\s*/\s*|\s+per\s+|\s+)",
+        r"(monthly|months|month|mos|mo|annually|annual|annum|years|year|yrs|yr",
+        r"|quarterly|quarters|quarter|qtrs|qtr)\.?$",
+    ))
+    .unwrap()
+});
+
+/// A number with an optional magnitude suffix, e.g. "1.2M" or "450 thousand"
+static MAGNITUDE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(\d*\.?\d+)\s*(k|thousand|m|mm|mn|million|b|bn|billion)?$").unwrap()
+});
+
+/// What a fact's value measures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueKind {
+    Currency,
+    Percent,
+    Count,
+    Number,
+    Date,
+    /// Free text, for facts without a known type or unit
+    Text,
+}
+
+impl ValueKind {
+    /// Kind of a fact's value, read from its unit, or from its type when it has no unit
+    pub fn of(fact_type: &str, unit: Option<&str>) -> Self {
+        if let Some(unit) = unit {
+            let base = FactUnit::parse(unit).base.to_lowercase();
+            return match base.as_str() {
+                "usd" | "$" => ValueKind::Currency,
+                "%" => ValueKind::Percent,
+                "units" | "unit" => ValueKind::Count,
+                "date" => ValueKind::Date,
+                _ => ValueKind::Number,
+            };
+        }
+        match FactType::from_str(fact_type) {
+            Some(FactType::UnitCount) => ValueKind::Count,
+            Some(
+                FactType::GrossScheduledRent
+                | FactType::CollectedRent
+                | FactType::OperatingExpenses
+                | FactType::NetOperatingIncome
+                | FactType::DebtService
+                | FactType::PropertyValue
+                | FactType::MortgageBalance
+                | FactType::AverageBankBalance
+                | FactType::BankDeposits
+                | FactType::InsuranceCoverage
+                | FactType::InsurancePremium
+                | FactType::LeaseBaseRent
+                | FactType::AppraisedValue,
+            ) => ValueKind::Currency,
+            Some(FactType::InsuranceExpiration | FactType::LeaseExpiration) => ValueKind::Date,
+            Some(FactType::Other) | None => ValueKind::Text,
+            Some(_) => ValueKind::Number,
+        }
+    }
+
+    fn description(&self) -> &str {
+        match self {
+            ValueKind::Currency => "an amount",
+            ValueKind::Percent => "a percentage",
+            ValueKind::Count => "a count",
+            ValueKind::Number => "a number",
+            ValueKind::Date => "a date",
+            ValueKind::Text => "text",
+        }
+    }
+}
+
+/// Period an amount is reported over
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValuePeriod {
+    Monthly,
+    Quarterly,
+    Annual,
+}
+
+impl ValuePeriod {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ValuePeriod::Monthly => "month",
+            ValuePeriod::Quarterly => "quarter",
+            ValuePeriod::Annual => "year",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "month" | "months" | "monthly" | "mo" | "mos" => Some(ValuePeriod::Monthly),
+            "quarter" | "quarters" | "quarterly" | "qtr" | "qtrs" => Some(ValuePeriod::Quarterly),
+            "year" | "years" | "yearly" | "annual" | "annually" | "annum" | "yr" | "yrs" => {
+                Some(ValuePeriod::Annual)
+            }
+            _ => None,
+        }
+    }
+
+    /// Number of periods in a year
+    pub fn per_year(&self) -> f64 {
+        match self {
+            ValuePeriod::Monthly => 12.0,
+            ValuePeriod::Quarterly => 4.0,
+            ValuePeriod::Annual => 1.0,
+        }
+    }
+}
+
+/// A unit such as "USD/month", split into its base and period
+#[derive(Debug, Clone, PartialEq)]
+pub struct FactUnit {
+    pub base: String,
+    pub period: Option<ValuePeriod>,
+}
+
+impl FactUnit {
+    pub fn parse(unit: &str) -> Self {
+        match unit.split_once('/') {
+            Some((base, period)) => match ValuePeriod::from_str(period) {
+                Some(period) => FactUnit {
+                    base: base.trim().to_string(),
+                    period: Some(period),
+                },
+                None => FactUnit {
+                    base: unit.trim().to_string(),
+                    period: None,
+                },
+            },
+            None => FactUnit {
+                base: unit.trim().to_string(),
+                period: None,
+            },
+        }
+    }
+
+    pub fn as_string(&self) -> String {
+        match self.period {
+            Some(period) => format!("{}/{}", self.base, period.as_str()),
+            None => self.base.clone(),
+        }
+    }
+}
+
+/// A fact's value read according to its kind
+#[derive(Debug, Clone, PartialEq)]
+pub enum FactValue {
+    /// A number, with the period it was written over when the text gave one
+    Amount {
+        amount: f64,
+        period: Option<ValuePeriod>,
+    },
+    Date(NaiveDate),
+    Text(String),
+}
+
+impl FactValue {
+    /// Read a value written as "$1,234,567", "1.2M", "(45,000)", "12.5%" or "$4,000/mo"
+    pub fn parse(text: &str, kind: ValueKind) -> Result<Self, String> {
+        let parsed = match kind {
+            ValueKind::Text => Some(FactValue::Text(text.trim().to_string())),
+            ValueKind::Date => parse_date(&text.replace('.', "")).map(FactValue::Date),
+            _ => parse_amount(text, kind)
+                .map(|(amount, period)| FactValue::Amount { amount, period }),
+        };
+        parsed.ok_or_else(|| format!("Cannot read \"{}\" as {}", text.trim(), kind.description()))
+    }
+
+    /// The value as stored on a fact: a plain number, an ISO date or the text
+    pub fn to_value_string(&self, kind: ValueKind) -> String {
+        match self {
+            FactValue::Amount { amount, .. } if kind == ValueKind::Count => {
+                (amount.round() as i64).to_string()
+            }
+            FactValue::Amount { amount, .. } => amount.to_string(),
+            FactValue::Date(date) => date.format("%Y-%m-%d").to_string(),
+            FactValue::Text(text) => text.clone(),
+        }
+    }
+}
+
+/// Parse a number with its sign, magnitude suffix and period
+///
+/// Only amounts are reported over a period without "per" or "/": for other kinds, "18 months"
+/// is a duration of 18. A bare "m" is read as million for amounts only, as "360m" may well be
+/// months.
+fn parse_amount(text: &str, kind: ValueKind) -> Option<(f64, Option<ValuePeriod>)> {
+    let mut text = text.trim().to_lowercase();
+    let suffix = PERIOD_SUFFIX.captures(&text).map(|captures| {
+        let per = !captures[1].trim().is_empty() || kind == ValueKind::Currency;
+        let period = ValuePeriod::from_str(&captures[2]).filter(|_| per);
+        (captures.get(0).map_or(0, |m| m.start()), period)
+    });
+    let period = suffix.and_then(|(start, period)| {
+        text.truncate(start);
+        period
+    });
+
+    let mut text = text.trim().to_string();
+    let mut negative = false;
+    if text.starts_with('(') && text.ends_with(')') {
+        negative = true;
+        text = text[1..text.len() - 1].to_string();
+    }
+    let mut cleaned: String = text
+        .replace("usd", "")
+        .chars()
+        .filter(|c| !matches!(c, '$' | ',' | '%' | ' '))
+        .collect();
+    if let Some(rest) = cleaned.strip_prefix(['-', '\u{2212}']) {
+        negative = !negative;
+        cleaned = rest.to_string();
+    }
+
+    let captures = MAGNITUDE.captures(&cleaned)?;
+    let number = captures[1].parse::<f64>().ok()?;
+    let multiplier = match captures.get(2).map(|m| m.as_str()) {
+        Some("k" | "thousand") => 1e3,
+        Some("m") if kind != ValueKind::Currency => return None,
+        Some("m" | "mm" | "mn" | "million") => 1e6,
+        Some("b" | "bn" | "billion") => 1e9,
+        _ => 1.0,
+    };
+    let amount = number * multiplier;
+    Some((if negative { -amount } else { amount }, period))
+}
+
+/// Read a value entered for a fact, returning the value and unit to store
+///
+/// A period written with an amount, as in "$4,000/mo", is recorded on the unit, which is
+/// `USD/<period>` for an amount without a unit. Other values without a unit cannot carry a
+/// period other than a year, as it would be lost.
+pub fn normalize_fact_value(
+    fact_type: &str,
+    value: &str,
+    unit: Option<&str>,
+) -> Result<(String, Option<String>), String> {
+    let kind = ValueKind::of(fact_type, unit);
+    let parsed = FactValue::parse(value, kind)?;
+    let unit = match (&parsed, unit) {
+        (FactValue::Amount { period: Some(period), .. }, Some(unit)) => {
+            let mut unit = FactUnit::parse(unit);
+            unit.period = Some(*period);
+            Some(unit.as_string())
+        }
+        (FactValue::Amount { period: Some(period), .. }, None) => match kind {
+            ValueKind::Currency => Some(format!("USD/{}", period.as_str())),
+            _ if *period == ValuePeriod::Annual => None,
+            _ => {
+                return Err(format!(
+                    "Give a unit to record \"{}\" per {}",
+                    value.trim(),
+                    period.as_str()
+                ))
+            }
+        },
+        (_, unit) => unit.map(str::to_string),
+    };
+    Ok((parsed.to_value_string(kind), unit))
+}
+
+impl Fact {
+    /// Value of the fact read according to its unit
+    pub fn typed_value(&self) -> Result<FactValue, String> {
+        FactValue::parse(&self.value, ValueKind::of(&self.fact_type, self.unit.as_deref()))
+    }
+
+    /// Numeric value of the fact, with amounts reported monthly or quarterly converted to a
+    /// yearly figure
+    pub fn annual_amount(&self) -> Option<f64> {
+        let kind = ValueKind::of(&self.fact_type, self.unit.as_deref());
+        let (amount, period) = match FactValue::parse(&self.value, kind).ok()? {
+            FactValue::Amount { amount, period } => (amount, period),
+            _ => return None,
+        };
+        if kind != ValueKind::Currency {
+            return Some(amount);
+        }
+        let period = period.or_else(|| self.unit.as_deref().and_then(|u| FactUnit::parse(u).period));
+        Some(amount * period.map(|p| p.per_year()).unwrap_or(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_amounts() {
+        let amount = |text: &str| match FactValue::parse(text, ValueKind::Currency) {
+            Ok(FactValue::Amount { amount, period }) => Some((amount, period)),
+            _ => None,
+        };
+        assert_eq!(amount("$1,234,567"), Some((1234567.0, None)));
+        assert_eq!(amount("1.2M"), Some((1200000.0, None)));
+        assert_eq!(amount("(45,000)"), Some((-45000.0, None)));
+        assert_eq!(amount("-$3.5k"), Some((-3500.0, None)));
+        assert_eq!(amount("$4,000/mo"), Some((4000.0, Some(ValuePeriod::Monthly))));
+        assert_eq!(amount("48 thousand per year"), Some((48000.0, Some(ValuePeriod::Annual))));
+        assert_eq!(amount("n/a"), None);
+        assert_eq!(
+            FactValue::parse("12.5%", ValueKind::Percent),
+            Ok(FactValue::Amount { amount: 12.5, period: None })
+        );
+        assert!(FactValue::parse("soon", ValueKind::Date).is_err());
+    }
+
+    #[test]
+    fn test_normalize_fact_value() {
+        assert_eq!(
+            normalize_fact_value("collected_rent", "$42,500 / month", Some("USD/year")),
+            Ok(("42500".to_string(), Some("USD/month".to_string())))
+        );
+        // Without a unit the period is kept on a currency unit, so the amount is annualized
+        assert_eq!(
+            normalize_fact_value("collected_rent", "$4,000/mo", None),
+            Ok(("4000".to_string(), Some("USD/month".to_string())))
+        );
+        assert!(normalize_fact_value("occupancy_rate", "95/mo", None).is_err());
+        // Plural periods, which are durations for anything but amounts
+        assert_eq!(
+            normalize_fact_value("collected_rent", "$4,000 per months", None),
+            Ok(("4000".to_string(), Some("USD/month".to_string())))
+        );
+        assert_eq!(
+            normalize_fact_value("lease_term_months", "18 months", None),
+            Ok(("18".to_string(), None))
+        );
+        assert_eq!(
+            normalize_fact_value("lease_term_months", "2 yrs", Some("years")),
+            Ok(("2".to_string(), Some("years".to_string())))
+        );
+        // A bare "m" is million for amounts only
+        assert_eq!(
+            normalize_fact_value("mortgage_balance", "1.2m", None),
+            Ok(("1200000".to_string(), None))
+        );
+        assert!(normalize_fact_value("lease_term_months", "360m", None).is_err());
+        assert_eq!(
+            normalize_fact_value("unit_count", "24.0", None),
+            Ok(("24".to_string(), None))
+        );
+        assert_eq!(
+            normalize_fact_value("lease_expiration", "June 30, 2030", Some("date")),
+            Ok(("2030-06-30".to_string(), Some("date".to_string())))
+        );
+        assert_eq!(
+            normalize_fact_value("operating_expenses", "about 40", Some("USD/year")),
+            Err("Cannot read \"about 40\" as an amount".to_string())
+        );
+    }
+}
@@ -3,19 +3,28 @@ pub mod azure;
 pub mod chunk_processing;
 pub mod cropping;
 pub mod deal;
+pub mod deal_access;
 pub mod document;
 pub mod fact;
+pub mod fact_event;
+pub mod fact_value;
+pub mod fact_resolution;
 pub mod general_ocr;
 pub mod llm;
 pub mod open_ai;
 pub mod output;
 pub mod pipeline;
+pub mod pl_line_item;
+pub mod property;
+pub mod rent_roll_unit;
 pub mod search;
 pub mod segment_processing;
 pub mod segmentation;
-// pub mod structured_extraction;
+pub mod structured_extraction;
 pub mod task;
 pub mod tasks;
+pub mod underwriting_policy;
+pub mod underwriting_run;
 pub mod upload;
 pub mod upload_multipart;
 pub mod user;
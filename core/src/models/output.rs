@@ -1,6 +1,6 @@
 use crate::models::{
     chunk_processing::TokenizerType, search::SimpleChunk, segment_processing::EmbedSource,
-    task::Configuration,
+    structured_extraction::StructuredExtractionResponse, task::Configuration,
 };
 use lru::LruCache;
 use once_cell::sync::Lazy;
@@ -41,6 +41,9 @@ pub struct OutputResponse {
     #[deprecated]
     /// The extracted JSON from the document.
     pub extracted_json: Option<serde_json::Value>,
+    /// The structured data extracted according to `configuration.structured_extraction`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_extraction: Option<StructuredExtractionResponse>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -506,6 +509,7 @@ mod tests {
             ocr_strategy: OcrStrategy::All,
             segment_processing: SegmentProcessing::default(),
             segmentation_strategy: SegmentationStrategy::LayoutAnalysis,
+            structured_extraction: None,
             target_chunk_length: None,
             error_handling: ErrorHandlingStrategy::default(),
             llm_processing: LlmProcessing::default(),
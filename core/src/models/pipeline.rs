@@ -1,5 +1,6 @@
 use crate::configs::worker_config;
 use crate::models::output::Chunk;
+use crate::models::structured_extraction::StructuredExtractionResponse;
 use crate::models::task::{Status, Task, TaskPayload};
 use crate::utils::services::file_operations::convert_to_pdf;
 use crate::utils::services::pdf::count_pages;
@@ -29,8 +30,12 @@ pub enum PipelineStep {
     ConvertToImages,
     #[strum(serialize = "crop")]
     Crop,
+    #[strum(serialize = "fact_extraction")]
+    FactExtraction,
     #[strum(serialize = "segment_processing")]
     SegmentProcessing,
+    #[strum(serialize = "structured_extraction")]
+    StructuredExtraction,
 }
 
 pub trait PipelineStepMessages {
@@ -47,7 +52,9 @@ impl PipelineStepMessages for PipelineStep {
             PipelineStep::ChunkrAnalysis => "Running Orin extraction".to_string(),
             PipelineStep::ConvertToImages => "Converting pages to images".to_string(),
             PipelineStep::Crop => "Cropping segments".to_string(),
+            PipelineStep::FactExtraction => "Extracting facts".to_string(),
             PipelineStep::SegmentProcessing => "Processing segments".to_string(),
+            PipelineStep::StructuredExtraction => "Extracting structured data".to_string(),
         }
     }
 
@@ -59,9 +66,13 @@ impl PipelineStepMessages for PipelineStep {
             PipelineStep::ChunkrAnalysis => "Failed to run Orin extraction".to_string(),
             PipelineStep::ConvertToImages => "Failed to convert pages to images".to_string(),
             PipelineStep::Crop => "Failed to crop segments".to_string(),
+            PipelineStep::FactExtraction => "Failed to extract facts".to_string(),
             PipelineStep::SegmentProcessing => {
                 "Failed to process segments - LLM processing error".to_string()
             }
+            PipelineStep::StructuredExtraction => {
+                "Failed to extract structured data".to_string()
+            }
         }
     }
 }
@@ -73,6 +84,7 @@ pub struct Pipeline {
     pub page_images: Option<Vec<Arc<NamedTempFile>>>,
     pub pdf_file: Option<Arc<NamedTempFile>>,
     pub segment_images: DashMap<String, Arc<NamedTempFile>>,
+    pub structured_extraction: Option<StructuredExtractionResponse>,
     pub task: Option<Task>,
     pub task_payload: Option<TaskPayload>,
 }
@@ -91,6 +103,7 @@ impl Pipeline {
             page_images: None,
             pdf_file: None,
             segment_images: DashMap::new(),
+            structured_extraction: None,
             task: None,
             task_payload: None,
         }
@@ -134,6 +147,7 @@ impl Pipeline {
                 .map(|(k, v)| (k, Arc::new(v)))
                 .collect();
             self.chunks = output.chunks;
+            self.structured_extraction = output.structured_extraction;
             println!("Task initialized with artifacts");
         } else {
             self.input_file = Some(Arc::new(
@@ -160,6 +174,8 @@ impl Pipeline {
             None,
         )
         .await?;
+        crate::pipeline::fact_extraction::sync_document_status(&task.task_id, &task.status)
+            .await?;
         self.task_payload = Some(task_payload.clone());
         self.task = Some(task.clone());
         Ok(())
@@ -255,12 +271,18 @@ impl Pipeline {
                     crate::pipeline::convert_to_images::process(self).await
                 }
                 PipelineStep::Crop => crate::pipeline::crop::process(self).await,
+                PipelineStep::FactExtraction => {
+                    crate::pipeline::fact_extraction::process(self, tracer).await
+                }
                 PipelineStep::ChunkrAnalysis => {
                     crate::pipeline::chunkr_analysis::process(self).await
                 }
                 PipelineStep::SegmentProcessing => {
                     crate::pipeline::segment_processing::process(self, tracer).await
                 }
+                PipelineStep::StructuredExtraction => {
+                    crate::pipeline::structured_extraction::process(self, tracer).await
+                }
             };
 
             let duration = start.elapsed();
@@ -348,6 +370,7 @@ impl Pipeline {
             Ok(())
         }
 
+        #[allow(clippy::too_many_arguments)]
         async fn update_success(
             task: &mut Task,
             status: Status,
@@ -355,12 +378,19 @@ impl Pipeline {
             page_images: Vec<Arc<NamedTempFile>>,
             segment_images: &DashMap<String, Arc<NamedTempFile>>,
             chunks: Vec<Chunk>,
+            structured_extraction: Option<StructuredExtractionResponse>,
             pdf_file: Arc<NamedTempFile>,
             finished_at: DateTime<Utc>,
             expires_at: Option<DateTime<Utc>>,
         ) -> Result<(), Box<dyn Error>> {
-            task.upload_artifacts(page_images, segment_images, chunks, &pdf_file)
-                .await?;
+            task.upload_artifacts(
+                page_images,
+                segment_images,
+                chunks,
+                structured_extraction,
+                &pdf_file,
+            )
+            .await?;
             task.update(
                 Some(status),
                 message,
@@ -374,7 +404,7 @@ impl Pipeline {
             Ok(())
         }
 
-        if status == Status::Failed {
+        let result = if status == Status::Failed {
             if task_payload.previous_configuration.is_none() {
                 task.update(
                     Some(status),
@@ -398,6 +428,7 @@ impl Pipeline {
                 self.page_images.clone().unwrap(),
                 &self.segment_images,
                 self.chunks.clone(),
+                self.structured_extraction.clone(),
                 self.pdf_file.clone().unwrap(),
                 finished_at,
                 expires_at,
@@ -411,6 +442,18 @@ impl Pipeline {
                     Err(e)
                 }
             }
+        };
+
+        let document_status = match result {
+            Ok(_) => task.status.clone(),
+            Err(_) => Status::Failed,
+        };
+        if let Err(e) =
+            crate::pipeline::fact_extraction::sync_document_status(&task.task_id, &document_status)
+                .await
+        {
+            println!("Error updating document status: {:?}", e);
         }
+        result
     }
 }
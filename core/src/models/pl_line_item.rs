@@ -0,0 +1,112 @@
+use crate::data::schema::pl_line_items;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Associations, ToSchema)]
+#[diesel(table_name = pl_line_items)]
+#[diesel(primary_key(pl_line_item_id))]
+#[diesel(belongs_to(crate::models::deal::Deal, foreign_key = deal_id))]
+#[diesel(belongs_to(crate::models::document::Document, foreign_key = document_id))]
+pub struct PlLineItem {
+    pub pl_line_item_id: String,
+    pub document_id: String,
+    pub deal_id: String,
+    pub row_index: i32,
+    pub category: String,
+    pub label: String,
+    /// Period labels, `YYYY-MM` when the year is known
+    pub periods: Vec<String>,
+    /// Amount for each period, aligned with `periods`
+    pub amounts: Vec<Option<f64>>,
+    pub source_citation: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = pl_line_items)]
+pub struct NewPlLineItem {
+    pub pl_line_item_id: String,
+    pub document_id: String,
+    pub deal_id: String,
+    pub row_index: i32,
+    pub category: String,
+    pub label: String,
+    pub periods: Vec<String>,
+    pub amounts: Vec<Option<f64>>,
+    pub source_citation: JsonValue,
+}
+
+/// Period × line item matrix of a P&L document
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProfitAndLossStatement {
+    pub document_id: String,
+    pub periods: Vec<String>,
+    pub line_items: Vec<PlLineItem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum LineItemCategory {
+    RentalIncome,
+    /// Vacancy, concessions and bad debt, deducted from rental income
+    VacancyAndCreditLoss,
+    OtherIncome,
+    Taxes,
+    Insurance,
+    Utilities,
+    RepairsAndMaintenance,
+    Management,
+    Payroll,
+    Administrative,
+    Marketing,
+    OtherExpense,
+}
+
+impl LineItemCategory {
+    pub fn as_str(&self) -> &str {
+        match self {
+            LineItemCategory::RentalIncome => "rental_income",
+            LineItemCategory::VacancyAndCreditLoss => "vacancy_and_credit_loss",
+            LineItemCategory::OtherIncome => "other_income",
+            LineItemCategory::Taxes => "taxes",
+            LineItemCategory::Insurance => "insurance",
+            LineItemCategory::Utilities => "utilities",
+            LineItemCategory::RepairsAndMaintenance => "repairs_and_maintenance",
+            LineItemCategory::Management => "management",
+            LineItemCategory::Payroll => "payroll",
+            LineItemCategory::Administrative => "administrative",
+            LineItemCategory::Marketing => "marketing",
+            LineItemCategory::OtherExpense => "other_expense",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "rental_income" => Some(LineItemCategory::RentalIncome),
+            "vacancy_and_credit_loss" => Some(LineItemCategory::VacancyAndCreditLoss),
+            "other_income" => Some(LineItemCategory::OtherIncome),
+            "taxes" => Some(LineItemCategory::Taxes),
+            "insurance" => Some(LineItemCategory::Insurance),
+            "utilities" => Some(LineItemCategory::Utilities),
+            "repairs_and_maintenance" => Some(LineItemCategory::RepairsAndMaintenance),
+            "management" => Some(LineItemCategory::Management),
+            "payroll" => Some(LineItemCategory::Payroll),
+            "administrative" => Some(LineItemCategory::Administrative),
+            "marketing" => Some(LineItemCategory::Marketing),
+            "other_expense" => Some(LineItemCategory::OtherExpense),
+            _ => None,
+        }
+    }
+
+    pub fn is_income(&self) -> bool {
+        matches!(
+            self,
+            LineItemCategory::RentalIncome
+                | LineItemCategory::VacancyAndCreditLoss
+                | LineItemCategory::OtherIncome
+        )
+    }
+}
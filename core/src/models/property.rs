@@ -0,0 +1,59 @@
+use crate::data::schema::properties;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// One of the properties of a portfolio deal
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Associations, ToSchema)]
+#[diesel(table_name = properties)]
+#[diesel(primary_key(property_id))]
+#[diesel(belongs_to(crate::models::deal::Deal, foreign_key = deal_id))]
+pub struct Property {
+    pub property_id: String,
+    pub deal_id: String,
+    pub name: String,
+    pub address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = properties)]
+pub struct NewProperty {
+    pub property_id: String,
+    pub deal_id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+impl Property {
+    /// Properties of a deal, in the order they were added
+    pub fn for_deal(conn: &mut PgConnection, deal_id: &str) -> QueryResult<Vec<Self>> {
+        properties::table
+            .filter(properties::deal_id.eq(deal_id))
+            .order(properties::created_at.asc())
+            .load::<Self>(conn)
+    }
+
+    /// Find a property belonging to the given deal
+    pub fn find(conn: &mut PgConnection, deal_id: &str, property_id: &str) -> QueryResult<Self> {
+        properties::table
+            .filter(properties::property_id.eq(property_id))
+            .filter(properties::deal_id.eq(deal_id))
+            .first::<Self>(conn)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatePropertyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetDocumentPropertyRequest {
+    /// Property the document describes, or null when it covers the whole portfolio
+    pub property_id: Option<String>,
+}
@@ -0,0 +1,72 @@
+use crate::data::schema::rent_roll_units;
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Associations, ToSchema)]
+#[diesel(table_name = rent_roll_units)]
+#[diesel(primary_key(rent_roll_unit_id))]
+#[diesel(belongs_to(crate::models::deal::Deal, foreign_key = deal_id))]
+#[diesel(belongs_to(crate::models::document::Document, foreign_key = document_id))]
+pub struct RentRollUnit {
+    pub rent_roll_unit_id: String,
+    pub document_id: String,
+    pub deal_id: String,
+    pub row_index: i32,
+    pub unit_id: String,
+    pub tenant: Option<String>,
+    pub square_feet: Option<f64>,
+    pub lease_start: Option<NaiveDate>,
+    pub lease_end: Option<NaiveDate>,
+    /// Monthly rent of the current lease
+    pub contract_rent: Option<f64>,
+    /// Monthly market rent of the unit
+    pub market_rent: Option<f64>,
+    pub status: String,
+    pub source_citation: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = rent_roll_units)]
+pub struct NewRentRollUnit {
+    pub rent_roll_unit_id: String,
+    pub document_id: String,
+    pub deal_id: String,
+    pub row_index: i32,
+    pub unit_id: String,
+    pub tenant: Option<String>,
+    pub square_feet: Option<f64>,
+    pub lease_start: Option<NaiveDate>,
+    pub lease_end: Option<NaiveDate>,
+    pub contract_rent: Option<f64>,
+    pub market_rent: Option<f64>,
+    pub status: String,
+    pub source_citation: JsonValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum UnitStatus {
+    Occupied,
+    Vacant,
+}
+
+impl UnitStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            UnitStatus::Occupied => "occupied",
+            UnitStatus::Vacant => "vacant",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "occupied" => Some(UnitStatus::Occupied),
+            "vacant" => Some(UnitStatus::Vacant),
+            _ => None,
+        }
+    }
+}
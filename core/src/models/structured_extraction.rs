@@ -5,7 +5,6 @@ use utoipa::ToSchema;
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct StructuredExtractionRequest {
-    #[serde(flatten)]
     pub contents: Vec<ChunkContent>,
     pub structured_extraction: StructuredExtraction,
 }
@@ -35,5 +34,6 @@ pub enum ExtractionType {
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema, ToSql, FromSql)]
 pub struct StructuredExtractionResponse {
-    pub response: String,
+    /// The extracted data, validated against the requested JSON schema.
+    pub response: serde_json::Value,
 }
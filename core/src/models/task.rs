@@ -5,6 +5,7 @@ use crate::models::output::{Chunk, OutputResponse, Segment, SegmentType};
 use crate::models::segment_processing::{
     GenerationStrategy, PictureGenerationConfig, SegmentProcessing,
 };
+use crate::models::structured_extraction::{StructuredExtraction, StructuredExtractionResponse};
 use crate::models::upload::{ErrorHandlingStrategy, OcrStrategy, SegmentationStrategy};
 use crate::utils::clients::get_pg_client;
 use crate::utils::services::file_operations::check_file_type;
@@ -470,6 +471,7 @@ impl Task {
         page_images: Vec<Arc<NamedTempFile>>,
         segment_images: &DashMap<String, Arc<NamedTempFile>>,
         chunks: Vec<Chunk>,
+        structured_extraction: Option<StructuredExtractionResponse>,
         pdf_file: &NamedTempFile,
     ) -> Result<(), Box<dyn std::error::Error>> {
         self.update(
@@ -488,6 +490,7 @@ impl Task {
             page_count: self.page_count,
             pdf_url: Some(self.pdf_location.clone()),
             extracted_json: None,
+            structured_extraction,
         };
         for (idx, page) in page_images.iter().enumerate() {
             let s3_key = format!(
@@ -753,6 +756,9 @@ pub struct Configuration {
     pub segment_processing: SegmentProcessing,
     pub segmentation_strategy: SegmentationStrategy,
     #[serde(skip_serializing_if = "Option::is_none")]
+    /// The JSON schema used to extract structured data from the document after chunking.
+    pub structured_extraction: Option<StructuredExtraction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[deprecated]
     /// The target number of words in each chunk. If 0, each chunk will contain a single segment.
     pub target_chunk_length: Option<u32>,
@@ -785,6 +791,7 @@ impl<'de> Deserialize<'de> for Configuration {
             segment_processing: Option<SegmentProcessing>,
             #[serde(default)]
             segmentation_strategy: Option<SegmentationStrategy>,
+            structured_extraction: Option<StructuredExtraction>,
             target_chunk_length: Option<u32>,
             #[cfg(feature = "azure")]
             pipeline: Option<PipelineType>,
@@ -821,6 +828,7 @@ impl<'de> Deserialize<'de> for Configuration {
             segmentation_strategy: helper
                 .segmentation_strategy
                 .unwrap_or(SegmentationStrategy::default()),
+            structured_extraction: helper.structured_extraction,
             target_chunk_length: helper.target_chunk_length,
             #[cfg(feature = "azure")]
             pipeline: helper.pipeline,
@@ -0,0 +1,327 @@
+use crate::data::schema::underwriting_policies;
+use crate::models::deal::Deal;
+use crate::services::deal_agent::Severity;
+use crate::services::underwriting::MAX_LOAN_YEARS;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::ToSchema;
+
+/// Fact types that must be approved before underwriting when no policy says otherwise
+pub const DEFAULT_REQUIRED_FACT_TYPES: &[&str] = &["collected_rent", "operating_expenses"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, ToSchema)]
+#[diesel(table_name = underwriting_policies)]
+#[diesel(primary_key(policy_id))]
+pub struct UnderwritingPolicy {
+    pub policy_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub thresholds: JsonValue,
+    pub required_fact_types: Vec<String>,
+    pub severities: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Organization whose deals may also use the policy
+    pub org_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = underwriting_policies)]
+pub struct NewUnderwritingPolicy {
+    pub policy_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub thresholds: JsonValue,
+    pub required_fact_types: Vec<String>,
+    pub severities: JsonValue,
+    pub org_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, AsChangeset)]
+#[diesel(table_name = underwriting_policies)]
+pub struct UpdateUnderwritingPolicy {
+    pub name: Option<String>,
+    pub thresholds: Option<JsonValue>,
+    pub required_fact_types: Option<Vec<String>>,
+    pub severities: Option<JsonValue>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Covenant thresholds a deal is checked against. Ratios are plain numbers, percentages are
+/// in percent and cash flow is in dollars per year.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct PolicyThresholds {
+    /// DSCR below which the property cannot cover its debt service
+    pub critical_dscr: f64,
+    pub min_dscr: f64,
+    /// DSCR at or above which coverage is reported as strong
+    pub strong_dscr: f64,
+    pub max_ltv: f64,
+    /// LTV below which leverage is reported as conservative
+    pub conservative_ltv: f64,
+    pub min_cap_rate: f64,
+    pub max_cap_rate: f64,
+    /// Cash flow after debt below which the safety margin is reported as low
+    pub min_cash_flow: f64,
+    /// Fact confidence below which facts should be verified manually
+    pub min_confidence: f64,
+    /// NOI as a percentage of the loan amount
+    pub min_debt_yield: f64,
+    /// Loan terms assumed for sized loans, and for existing loans whose facts do not give them
+    pub amortization_years: f64,
+    pub interest_only_years: f64,
+    pub loan_term_years: f64,
+    /// Annualized bank deposits as a percentage of reported rent
+    pub min_deposit_coverage: f64,
+    /// Insurance coverage as a percentage of the property value
+    pub min_insurance_coverage: f64,
+    /// Days before its expiration at which an insurance policy should be renewed
+    pub insurance_renewal_days: f64,
+    /// Percentage points the cap rate may differ from the appraisal's
+    pub max_appraisal_cap_rate_variance: f64,
+}
+
+impl Default for PolicyThresholds {
+    fn default() -> Self {
+        PolicyThresholds {
+            critical_dscr: 1.0,
+            min_dscr: 1.25,
+            strong_dscr: 1.5,
+            max_ltv: 80.0,
+            conservative_ltv: 60.0,
+            min_cap_rate: 4.0,
+            max_cap_rate: 12.0,
+            min_cash_flow: 5000.0,
+            min_confidence: 0.7,
+            min_debt_yield: 8.0,
+            amortization_years: 30.0,
+            interest_only_years: 0.0,
+            loan_term_years: 10.0,
+            min_deposit_coverage: 90.0,
+            min_insurance_coverage: 80.0,
+            insurance_renewal_days: 60.0,
+            max_appraisal_cap_rate_variance: 1.0,
+        }
+    }
+}
+
+impl PolicyThresholds {
+    /// Check that paired thresholds are ordered
+    pub fn validate(&self) -> Result<(), String> {
+        if self.critical_dscr > self.min_dscr {
+            return Err("critical_dscr must not exceed min_dscr".to_string());
+        }
+        if self.min_dscr > self.strong_dscr {
+            return Err("min_dscr must not exceed strong_dscr".to_string());
+        }
+        if self.conservative_ltv > self.max_ltv {
+            return Err("conservative_ltv must not exceed max_ltv".to_string());
+        }
+        if self.min_cap_rate > self.max_cap_rate {
+            return Err("min_cap_rate must not exceed max_cap_rate".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.min_confidence) {
+            return Err("min_confidence must be between 0 and 1".to_string());
+        }
+        if self.amortization_years <= 0.0 || self.loan_term_years <= 0.0 {
+            return Err("amortization_years and loan_term_years must be positive".to_string());
+        }
+        if self.interest_only_years < 0.0 {
+            return Err("interest_only_years must not be negative".to_string());
+        }
+        if [self.amortization_years, self.interest_only_years, self.loan_term_years]
+            .iter()
+            .any(|years| *years > MAX_LOAN_YEARS)
+        {
+            return Err(format!(
+                "amortization_years, interest_only_years and loan_term_years must not exceed {}",
+                MAX_LOAN_YEARS
+            ));
+        }
+        if self.insurance_renewal_days < 0.0 || self.max_appraisal_cap_rate_variance < 0.0 {
+            return Err(
+                "insurance_renewal_days and max_appraisal_cap_rate_variance must not be negative"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Severity reported when each covenant check fails
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct PolicySeverities {
+    pub dscr_below_critical: Severity,
+    pub dscr_below_minimum: Severity,
+    pub negative_noi: Severity,
+    pub negative_cash_flow: Severity,
+    pub low_cash_flow: Severity,
+    pub cap_rate_out_of_range: Severity,
+    pub ltv_above_maximum: Severity,
+    pub debt_yield_below_minimum: Severity,
+    pub loan_above_max_sizing: Severity,
+    pub missing_required_fact: Severity,
+    pub pending_facts: Severity,
+    pub low_confidence: Severity,
+    pub deposits_below_rent: Severity,
+    pub insufficient_insurance: Severity,
+    pub insurance_expiring: Severity,
+    pub lease_rollover: Severity,
+    pub appraisal_cap_rate_variance: Severity,
+}
+
+impl Default for PolicySeverities {
+    fn default() -> Self {
+        PolicySeverities {
+            dscr_below_critical: Severity::Critical,
+            dscr_below_minimum: Severity::Warning,
+            negative_noi: Severity::Critical,
+            negative_cash_flow: Severity::Critical,
+            low_cash_flow: Severity::Warning,
+            cap_rate_out_of_range: Severity::Info,
+            ltv_above_maximum: Severity::Warning,
+            debt_yield_below_minimum: Severity::Warning,
+            loan_above_max_sizing: Severity::Warning,
+            missing_required_fact: Severity::Critical,
+            pending_facts: Severity::Warning,
+            low_confidence: Severity::Info,
+            deposits_below_rent: Severity::Warning,
+            insufficient_insurance: Severity::Warning,
+            insurance_expiring: Severity::Warning,
+            lease_rollover: Severity::Info,
+            appraisal_cap_rate_variance: Severity::Info,
+        }
+    }
+}
+
+/// The rules of a policy, or the standard rules for deals without one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PolicyRules {
+    pub thresholds: PolicyThresholds,
+    pub required_fact_types: Vec<String>,
+    pub severities: PolicySeverities,
+}
+
+impl Default for PolicyRules {
+    fn default() -> Self {
+        PolicyRules {
+            thresholds: PolicyThresholds::default(),
+            required_fact_types: DEFAULT_REQUIRED_FACT_TYPES
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            severities: PolicySeverities::default(),
+        }
+    }
+}
+
+impl PolicyRules {
+    /// Rules of the policy selected for the deal
+    pub fn for_deal(conn: &mut PgConnection, deal: &Deal) -> QueryResult<Self> {
+        match &deal.policy_id {
+            Some(policy_id) => Ok(underwriting_policies::table
+                .filter(underwriting_policies::policy_id.eq(policy_id))
+                .first::<UnderwritingPolicy>(conn)?
+                .rules()),
+            None => Ok(PolicyRules::default()),
+        }
+    }
+}
+
+impl UnderwritingPolicy {
+    /// Parse the stored thresholds and severities, filling anything missing with the defaults
+    pub fn rules(&self) -> PolicyRules {
+        PolicyRules {
+            thresholds: serde_json::from_value(self.thresholds.clone()).unwrap_or_default(),
+            required_fact_types: self.required_fact_types.clone(),
+            severities: serde_json::from_value(self.severities.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// Find a policy owned by the given user
+    pub fn find(conn: &mut PgConnection, policy_id: &str, user_id: &str) -> QueryResult<Self> {
+        underwriting_policies::table
+            .filter(underwriting_policies::policy_id.eq(policy_id))
+            .filter(underwriting_policies::user_id.eq(user_id))
+            .first::<Self>(conn)
+    }
+
+    /// Find a policy that can be selected on a deal: one owned by the given user or by the
+    /// deal's creator, or one shared with the deal's organization
+    pub fn find_for_deal(
+        conn: &mut PgConnection,
+        policy_id: &str,
+        user_id: &str,
+        deal_user_id: &str,
+        deal_org_id: Option<&str>,
+    ) -> QueryResult<Self> {
+        let policy = underwriting_policies::table
+            .filter(underwriting_policies::policy_id.eq(policy_id))
+            .first::<Self>(conn)?;
+        let selectable = policy.user_id == user_id
+            || policy.user_id == deal_user_id
+            || (policy.org_id.is_some() && policy.org_id.as_deref() == deal_org_id);
+        if selectable {
+            Ok(policy)
+        } else {
+            Err(diesel::result::Error::NotFound)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UnderwritingPolicyResponse {
+    pub policy_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub thresholds: PolicyThresholds,
+    pub required_fact_types: Vec<String>,
+    pub severities: PolicySeverities,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub org_id: Option<String>,
+}
+
+impl From<UnderwritingPolicy> for UnderwritingPolicyResponse {
+    fn from(policy: UnderwritingPolicy) -> Self {
+        let rules = policy.rules();
+        UnderwritingPolicyResponse {
+            policy_id: policy.policy_id,
+            user_id: policy.user_id,
+            name: policy.name,
+            thresholds: rules.thresholds,
+            required_fact_types: rules.required_fact_types,
+            severities: rules.severities,
+            created_at: policy.created_at,
+            updated_at: policy.updated_at,
+            org_id: policy.org_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateUnderwritingPolicyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub thresholds: PolicyThresholds,
+    /// Defaults to collected rent and operating expenses
+    #[serde(default)]
+    pub required_fact_types: Option<Vec<String>>,
+    #[serde(default)]
+    pub severities: PolicySeverities,
+    /// Organization to share the policy with, which the creator must belong to
+    #[serde(default)]
+    pub org_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateUnderwritingPolicyRequest {
+    pub name: Option<String>,
+    pub thresholds: Option<PolicyThresholds>,
+    pub required_fact_types: Option<Vec<String>>,
+    pub severities: Option<PolicySeverities>,
+}
@@ -0,0 +1,118 @@
+use crate::data::schema::underwriting_runs;
+use crate::services::portfolio::PropertyUnderwriting;
+use crate::services::underwriting::UnderwritingResult;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Associations, ToSchema)]
+#[diesel(table_name = underwriting_runs)]
+#[diesel(primary_key(run_id))]
+#[diesel(belongs_to(crate::models::deal::Deal, foreign_key = deal_id))]
+pub struct UnderwritingRun {
+    pub run_id: String,
+    pub deal_id: String,
+    pub version: i32,
+    pub input_fact_ids: Vec<String>,
+    pub inputs: JsonValue,
+    pub result: JsonValue,
+    pub audit_trail: JsonValue,
+    pub warnings: Vec<String>,
+    pub run_by: String,
+    pub created_at: DateTime<Utc>,
+    /// Set once the run is accepted as the deal's final underwriting
+    pub finalized_at: Option<DateTime<Utc>>,
+    pub finalized_by: Option<String>,
+    /// Results of each property of a portfolio deal, empty for single property deals
+    pub property_results: JsonValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = underwriting_runs)]
+pub struct NewUnderwritingRun {
+    pub run_id: String,
+    pub deal_id: String,
+    pub version: i32,
+    pub input_fact_ids: Vec<String>,
+    pub inputs: JsonValue,
+    pub result: JsonValue,
+    pub audit_trail: JsonValue,
+    pub warnings: Vec<String>,
+    pub run_by: String,
+    pub property_results: JsonValue,
+}
+
+impl UnderwritingRun {
+    /// Version the next run of the given deal will be stored under
+    ///
+    /// Locks the deal row so that concurrent runs get distinct versions, and must be called in the
+    /// transaction that inserts the run.
+    pub fn next_version(conn: &mut PgConnection, deal_id: &str) -> QueryResult<i32> {
+        use crate::data::schema::deals;
+
+        deals::table
+            .find(deal_id)
+            .select(deals::deal_id)
+            .for_update()
+            .first::<String>(conn)?;
+        let latest: Option<i32> = underwriting_runs::table
+            .filter(underwriting_runs::deal_id.eq(deal_id))
+            .select(diesel::dsl::max(underwriting_runs::version))
+            .first(conn)?;
+        Ok(latest.unwrap_or(0) + 1)
+    }
+
+    /// Find a run belonging to the given deal
+    pub fn find(conn: &mut PgConnection, deal_id: &str, run_id: &str) -> QueryResult<Self> {
+        underwriting_runs::table
+            .filter(underwriting_runs::run_id.eq(run_id))
+            .filter(underwriting_runs::deal_id.eq(deal_id))
+            .first::<Self>(conn)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UnderwriteResponse {
+    pub run_id: String,
+    pub version: i32,
+    #[serde(flatten)]
+    pub result: UnderwritingResult,
+    /// Results of each property of a portfolio deal, next to the portfolio roll-up in `result`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<PropertyUnderwriting>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnderwriteQuery {
+    /// Period basis of the facts to prefer: `trailing_12` (default), `trailing_3` or `annualized`
+    pub period_basis: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnderwritingRunDiffQuery {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MetricDiff {
+    pub metric: String,
+    pub from: Option<f64>,
+    pub to: Option<f64>,
+    pub change: Option<f64>,
+    pub change_pct: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UnderwritingRunDiff {
+    pub from_run_id: String,
+    pub to_run_id: String,
+    pub from_version: i32,
+    pub to_version: i32,
+    pub metrics: Vec<MetricDiff>,
+    pub inputs: Vec<MetricDiff>,
+    pub added_fact_ids: Vec<String>,
+    pub removed_fact_ids: Vec<String>,
+}
@@ -2,6 +2,7 @@ use crate::configs::{job_config, llm_config};
 use crate::models::chunk_processing::ChunkProcessing;
 use crate::models::llm::LlmProcessing;
 use crate::models::segment_processing::SegmentProcessing;
+use crate::models::structured_extraction::StructuredExtraction;
 use crate::models::task::Configuration;
 #[cfg(feature = "azure")]
 use crate::models::task::PipelineType;
@@ -102,6 +103,8 @@ pub struct CreateForm {
     pub segment_processing: Option<SegmentProcessing>,
     #[schema(default = "LayoutAnalysis")]
     pub segmentation_strategy: Option<SegmentationStrategy>,
+    /// The JSON schema used to extract structured data from the document after chunking.
+    pub structured_extraction: Option<StructuredExtraction>,
     #[schema(default = "Fail")]
     pub error_handling: Option<ErrorHandlingStrategy>,
     pub llm_processing: Option<LlmProcessing>,
@@ -193,6 +196,7 @@ impl CreateForm {
             pipeline: self.get_pipeline(),
             segment_processing: self.get_segment_processing(),
             segmentation_strategy: self.get_segmentation_strategy(),
+            structured_extraction: self.structured_extraction.clone(),
             target_chunk_length: None,
             error_handling: self.error_handling.clone().unwrap_or_default(),
             llm_processing: self.llm_processing.clone().unwrap_or_default(),
@@ -215,6 +219,8 @@ pub struct UpdateForm {
     pub pipeline: Option<PipelineType>,
     pub segment_processing: Option<SegmentProcessing>,
     pub segmentation_strategy: Option<SegmentationStrategy>,
+    /// The JSON schema used to extract structured data from the document after chunking.
+    pub structured_extraction: Option<StructuredExtraction>,
     pub error_handling: Option<ErrorHandlingStrategy>,
     pub llm_processing: Option<LlmProcessing>,
 }
@@ -301,6 +307,10 @@ impl UpdateForm {
                 .segmentation_strategy
                 .clone()
                 .unwrap_or(current_config.segmentation_strategy.clone()),
+            structured_extraction: self
+                .structured_extraction
+                .clone()
+                .or_else(|| current_config.structured_extraction.clone()),
             target_chunk_length: None,
             error_handling: self
                 .error_handling
@@ -2,6 +2,7 @@ use crate::configs::job_config;
 use crate::models::chunk_processing::ChunkProcessing;
 use crate::models::llm::LlmProcessing;
 use crate::models::segment_processing::SegmentProcessing;
+use crate::models::structured_extraction::StructuredExtraction;
 use crate::models::task::Configuration;
 #[cfg(feature = "azure")]
 use crate::models::task::PipelineType;
@@ -45,6 +46,10 @@ pub struct CreateFormMultipart {
     #[param(style = Form, value_type = String, format = "binary")]
     #[schema(value_type = Option<SegmentationStrategy>, default = "LayoutAnalysis", format = "binary")]
     pub segmentation_strategy: Option<MPJson<SegmentationStrategy>>,
+    #[param(style = Form, value_type = String, format = "binary")]
+    #[schema(value_type = Option<StructuredExtraction>, format = "binary")]
+    /// The JSON schema used to extract structured data from the document after chunking.
+    pub structured_extraction: Option<MPJson<StructuredExtraction>>,
 }
 
 impl CreateFormMultipart {
@@ -146,6 +151,7 @@ impl CreateFormMultipart {
             pipeline: self.get_pipeline(),
             segment_processing: self.get_segment_processing(),
             segmentation_strategy: self.get_segmentation_strategy(),
+            structured_extraction: self.structured_extraction.as_ref().map(|e| e.0.clone()),
             target_chunk_length: None,
             error_handling: ErrorHandlingStrategy::default(),
             llm_processing: LlmProcessing::default(),
@@ -184,6 +190,10 @@ pub struct UpdateFormMultipart {
     #[param(style = Form, value_type = Option<SegmentationStrategy>, format = "binary")]
     #[schema(value_type = Option<SegmentationStrategy>, format = "binary")]
     pub segmentation_strategy: Option<MPJson<SegmentationStrategy>>,
+    #[param(style = Form, value_type = Option<StructuredExtraction>, format = "binary")]
+    #[schema(value_type = Option<StructuredExtraction>, format = "binary")]
+    /// The JSON schema used to extract structured data from the document after chunking.
+    pub structured_extraction: Option<MPJson<StructuredExtraction>>,
 }
 
 impl UpdateFormMultipart {
@@ -267,6 +277,11 @@ impl UpdateFormMultipart {
                 .as_ref()
                 .map(|e| e.0.clone())
                 .unwrap_or(current_config.segmentation_strategy.clone()),
+            structured_extraction: self
+                .structured_extraction
+                .as_ref()
+                .map(|e| e.0.clone())
+                .or_else(|| current_config.structured_extraction.clone()),
             target_chunk_length: None,
             error_handling: ErrorHandlingStrategy::default(),
             llm_processing: LlmProcessing::default(),
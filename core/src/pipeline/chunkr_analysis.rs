@@ -169,7 +169,7 @@ pub async fn process_segmentation(
         }
         SegmentationStrategy::Page => {
             let mut segments = Vec::new();
-            for (idx, (page, ocr)) in pages.iter().zip(ocr_results.into_iter()).enumerate() {
+            for (idx, (page, ocr)) in pages.iter().zip(ocr_results).enumerate() {
                 segments.push(page_segmentation(page, ocr, idx as u32 + 1)?);
             }
             Ok(segments)
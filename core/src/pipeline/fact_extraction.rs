@@ -1,66 +1,354 @@
-use crate::models::document::{Document, DocumentType};
-use crate::models::fact::{FactType, NewFact, SourceCitation, BoundingBox};
-use crate::models::output::OCRResult;
+use crate::configs::worker_config::{self, FactExtractionMode};
+use crate::models::document::{
+    ClassificationSource, Document, DocumentStatus, DocumentType, UpdateDocument,
+};
+use crate::models::fact::{BoundingBox, Fact, FactType, NewFact, SourceCitation};
+use crate::models::fact_event::{FactEvent, NewFactEvent};
+use crate::models::llm::LlmProcessing;
+use crate::models::output::{Chunk, OCRResult};
+use crate::models::pipeline::Pipeline;
+use crate::models::pl_line_item::NewPlLineItem;
+use crate::models::rent_roll_unit::NewRentRollUnit;
+use crate::models::task::Status;
+use crate::services::deal_status::sync_after_documents;
+use crate::services::document_classifier::{
+    classify_segments, DocumentClassification, LLM_FALLBACK_CONFIDENCE,
+};
+use crate::services::profit_and_loss::{facts_from_line_items, line_items_from_chunks};
+use crate::services::rent_roll::{facts_from_units, parse_date, units_from_chunks};
+use crate::utils::clients::get_diesel_conn;
+use crate::utils::services::document_classification::classify_document_with_llm;
+use crate::utils::services::fact_extraction::extract_facts_with_llm;
+use diesel::prelude::*;
 use regex::Regex;
 use serde_json::json;
 use std::error::Error;
+use std::ops::Range;
 use uuid::Uuid;
 
-/// Classify document type based on OCR results and file name
-pub fn classify_document_type(document: &Document, ocr_results: &[OCRResult]) -> DocumentType {
-    let file_name = document.file_name.to_lowercase();
-    let ocr_text: String = ocr_results
-        .iter()
-        .map(|r| r.text.to_lowercase())
-        .collect::<Vec<_>>()
-        .join(" ");
+/// Extract facts for the deal document processed by this task
+///
+/// Tasks that were not created from a deal document upload are left untouched.
+/// The document is classified first, unless the user chose its type, and the classification is
+/// stored on the document. Previously extracted facts that have not been locked are replaced.
+/// In `Llm` mode the regex extractors are only used when the LLM extraction fails.
+/// Rent rolls and P&L statements also store their table rows, as units and as a period ×
+/// line item matrix, and the facts derived from them take precedence over extracted ones.
+pub async fn process(
+    pipeline: &mut Pipeline,
+    tracer: &opentelemetry::global::BoxedTracer,
+) -> Result<(), Box<dyn Error>> {
+    let task = pipeline.get_task()?;
+    let task_id = task.task_id.clone();
+    let document = tokio::task::spawn_blocking(move || {
+        let mut conn = get_diesel_conn()?;
+        Ok::<_, Box<dyn Error + Send + Sync>>(Document::find_by_task_id(&mut conn, &task_id)?)
+    })
+    .await?
+    .map_err(|e| e.to_string())?;
 
-    // Check filename first
-    if file_name.contains("rent") && file_name.contains("roll") {
-        return DocumentType::RentRoll;
-    }
-    if file_name.contains("p&l") || file_name.contains("profit") || file_name.contains("loss") {
-        return DocumentType::ProfitAndLoss;
+    let document = match document {
+        Some(document) => document,
+        None => return Ok(()),
+    };
+
+    let page_count = task.page_count.unwrap_or(0);
+    let ocr_results = ocr_results_by_page(&pipeline.chunks, page_count);
+    let worker_config = worker_config::Config::from_env()?;
+    let classification = classify_document(
+        &document,
+        &pipeline.chunks,
+        &worker_config.fact_extraction_mode,
+        task.configuration.llm_processing.clone(),
+        tracer,
+    )
+    .await;
+    let document_type = classification.document_type.clone();
+    let llm_facts = match worker_config.fact_extraction_mode {
+        FactExtractionMode::Llm => match extract_facts_with_llm(
+            &document,
+            &document_type,
+            &pipeline.chunks,
+            task.configuration.llm_processing.clone(),
+            tracer,
+        )
+        .await
+        {
+            Ok(facts) => Some(facts),
+            Err(e) => {
+                println!(
+                    "LLM fact extraction failed for document {}, falling back to regex: {}",
+                    document.document_id, e
+                );
+                None
+            }
+        },
+        FactExtractionMode::Regex => None,
+    };
+    let mut new_facts = match llm_facts {
+        Some(facts) => facts,
+        None => extract_facts_from_document(&document, &document_type, &ocr_results)
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+
+    let rent_roll_units: Vec<NewRentRollUnit> = match document_type {
+        DocumentType::RentRoll => units_from_chunks(&document, &pipeline.chunks),
+        _ => vec![],
+    };
+    let pl_line_items: Vec<NewPlLineItem> = match document_type {
+        DocumentType::ProfitAndLoss => line_items_from_chunks(&document, &pipeline.chunks),
+        _ => vec![],
+    };
+    let mut table_facts = facts_from_units(&document, &rent_roll_units);
+    table_facts.extend(facts_from_line_items(&document, &pl_line_items));
+    if !table_facts.is_empty() {
+        new_facts.retain(|f| !table_facts.iter().any(|t| t.fact_type == f.fact_type));
+        new_facts.extend(table_facts);
+        println!(
+            "Parsed {} rent roll units and {} P&L line items from document {}",
+            rent_roll_units.len(),
+            pl_line_items.len(),
+            document.document_id
+        );
     }
-    if file_name.contains("mortgage") || file_name.contains("loan") {
-        return DocumentType::MortgageStatement;
+    println!(
+        "Extracted {} facts from document {}",
+        new_facts.len(),
+        document.document_id
+    );
+    let mut document_update = UpdateDocument {
+        status: None,
+        storage_location: None,
+        page_count: Some(page_count as i32),
+        ocr_output: None,
+        document_type: None,
+        classification_confidence: None,
+        classification_alternatives: None,
+        classification_source: None,
+    };
+    if classification.source != ClassificationSource::User {
+        document_update.document_type = Some(document_type.as_str().to_string());
+        document_update.classification_confidence = Some(classification.confidence);
+        document_update.classification_alternatives = Some(json!(classification.alternatives));
+        document_update.classification_source = Some(classification.source.as_str().to_string());
     }
-    if file_name.contains("tax") || file_name.contains("1099") || file_name.contains("1098") {
-        return DocumentType::TaxDocument;
+
+    tokio::task::spawn_blocking(move || {
+        use crate::data::schema::{facts, pl_line_items, rent_roll_units};
+
+        let mut conn = get_diesel_conn()?;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Log the unlocked facts being replaced, which may carry edits not yet approved
+            let replaced = diesel::delete(
+                facts::table
+                    .filter(facts::document_id.eq(&document.document_id))
+                    .filter(facts::locked.eq(false)),
+            )
+            .get_results::<Fact>(conn)?;
+            diesel::insert_into(facts::table)
+                .values(&new_facts)
+                .execute(conn)?;
+            let reason = format!("Extracted from {}", document.file_name);
+            let events: Vec<NewFactEvent> = replaced
+                .iter()
+                .map(|fact| NewFactEvent::replaced(fact, Some(reason.clone())))
+                .chain(
+                    new_facts
+                        .iter()
+                        .map(|fact| NewFactEvent::extracted(fact, Some(reason.clone()))),
+                )
+                .collect();
+            FactEvent::record(conn, &events)?;
+            diesel::delete(
+                rent_roll_units::table
+                    .filter(rent_roll_units::document_id.eq(&document.document_id)),
+            )
+            .execute(conn)?;
+            diesel::insert_into(rent_roll_units::table)
+                .values(&rent_roll_units)
+                .execute(conn)?;
+            diesel::delete(
+                pl_line_items::table.filter(pl_line_items::document_id.eq(&document.document_id)),
+            )
+            .execute(conn)?;
+            diesel::insert_into(pl_line_items::table)
+                .values(&pl_line_items)
+                .execute(conn)?;
+            Document::update(conn, &document.document_id, &document_update)?;
+            Ok(())
+        })?;
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    })
+    .await?
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Mirror the task status onto the deal document processed by the task, if any
+///
+/// `Processing` moves the document to processing, `Succeeded` to completed and
+/// `Failed` or `Cancelled` to failed. Other statuses are ignored. Once every document of the deal
+/// has finished, the deal moves on to fact review.
+pub async fn sync_document_status(task_id: &str, status: &Status) -> Result<(), Box<dyn Error>> {
+    let document_status = match status {
+        Status::Processing => DocumentStatus::Processing,
+        Status::Succeeded => DocumentStatus::Completed,
+        Status::Failed | Status::Cancelled => DocumentStatus::Failed,
+        _ => return Ok(()),
+    };
+    let task_id = task_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = get_diesel_conn()?;
+        if let Some(document) = Document::find_by_task_id(&mut conn, &task_id)? {
+            Document::update(
+                &mut conn,
+                &document.document_id,
+                &UpdateDocument {
+                    status: Some(document_status.as_str().to_string()),
+                    storage_location: None,
+                    page_count: None,
+                    ocr_output: None,
+                    document_type: None,
+                    classification_confidence: None,
+                    classification_alternatives: None,
+                    classification_source: None,
+                },
+            )?;
+            if let Err(e) = sync_after_documents(&mut conn, &document.deal_id) {
+                println!("Failed to update status of deal {}: {}", document.deal_id, e);
+            }
+        }
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    })
+    .await?
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The OCR results of a page, positioned on the page rather than in their segment
+#[derive(Debug, Clone, Default)]
+pub struct PageOcr {
+    pub results: Vec<OCRResult>,
+    /// Dimensions of the page, zero when no segment was found on it
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PageOcr {
+    /// Text of the page, as the keyword patterns are matched against
+    pub fn text(&self) -> String {
+        self.results
+            .iter()
+            .map(|r| r.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
     }
-    if file_name.contains("bank") || file_name.contains("statement") {
-        return DocumentType::BankStatement;
+
+    /// Union of the boxes of the OCR results that a byte range of `text()` overlaps
+    pub fn span_bbox(&self, span: Range<usize>) -> Option<BoundingBox> {
+        let mut start = 0;
+        let mut bounds: Option<(f32, f32, f32, f32)> = None;
+        for result in &self.results {
+            let end = start + result.text.len();
+            if start < span.end && end > span.start {
+                let bbox = &result.bbox;
+                let (left, top, right, bottom) =
+                    (bbox.left, bbox.top, bbox.left + bbox.width, bbox.top + bbox.height);
+                bounds = Some(match bounds {
+                    Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+                    None => (left, top, right, bottom),
+                });
+            }
+            // Results are joined by a single space
+            start = end + 1;
+        }
+        bounds.map(|(left, top, right, bottom)| BoundingBox {
+            left: left as f64,
+            top: top as f64,
+            width: (right - left) as f64,
+            height: (bottom - top) as f64,
+        })
     }
+}
 
-    // Check OCR text for keywords
-    if ocr_text.contains("rent roll") || (ocr_text.contains("unit") && ocr_text.contains("tenant")) {
-        return DocumentType::RentRoll;
+/// Group the OCR results of every segment by page, in reading order
+///
+/// OCR boxes are relative to their segment and are moved to page coordinates.
+pub fn ocr_results_by_page(chunks: &[Chunk], page_count: u32) -> Vec<PageOcr> {
+    let mut pages: Vec<PageOcr> = vec![PageOcr::default(); page_count as usize];
+    for segment in chunks.iter().flat_map(|chunk| chunk.segments.iter()) {
+        let page_idx = segment.page_number.saturating_sub(1) as usize;
+        if page_idx >= pages.len() {
+            pages.resize(page_idx + 1, PageOcr::default());
+        }
+        let page = &mut pages[page_idx];
+        page.width = segment.page_width;
+        page.height = segment.page_height;
+        if let Some(ocr) = &segment.ocr {
+            page.results.extend(ocr.iter().map(|result| {
+                let mut result = result.clone();
+                result.bbox.left += segment.bbox.left;
+                result.bbox.top += segment.bbox.top;
+                result
+            }));
+        }
     }
-    if ocr_text.contains("net operating income") || ocr_text.contains("noi") {
-        return DocumentType::ProfitAndLoss;
+    pages
+}
+
+/// Classify a document unless the user chose its type
+///
+/// The layout of the first pages is scored first. When it is inconclusive and facts are extracted
+/// with the LLM, the LLM classifies the document too and the more confident of the two is kept.
+pub async fn classify_document(
+    document: &Document,
+    chunks: &[Chunk],
+    fact_extraction_mode: &FactExtractionMode,
+    llm_processing: LlmProcessing,
+    tracer: &opentelemetry::global::BoxedTracer,
+) -> DocumentClassification {
+    if let Some(document_type) = document.user_document_type() {
+        return DocumentClassification::user(document_type);
     }
-    if ocr_text.contains("mortgage") || ocr_text.contains("principal") && ocr_text.contains("interest") {
-        return DocumentType::MortgageStatement;
+
+    let classification = classify_segments(document, chunks);
+    if classification.confidence >= LLM_FALLBACK_CONFIDENCE
+        || *fact_extraction_mode != FactExtractionMode::Llm
+    {
+        return classification;
     }
-    if ocr_text.contains("tax") || ocr_text.contains("form 1099") || ocr_text.contains("form 1098") {
-        return DocumentType::TaxDocument;
+    match classify_document_with_llm(document, chunks, llm_processing, tracer).await {
+        Ok(llm_classification) if llm_classification.confidence > classification.confidence => {
+            llm_classification
+        }
+        Ok(_) => classification,
+        Err(e) => {
+            println!(
+                "LLM classification failed for document {}, keeping the layout classification: {}",
+                document.document_id, e
+            );
+            classification
+        }
     }
-
-    DocumentType::Other
 }
 
-/// Extract facts from a document based on its type
+/// Extract facts from a document based on its type using keyword patterns
 pub async fn extract_facts_from_document(
     document: &Document,
-    ocr_results: &[Vec<OCRResult>],
+    document_type: &DocumentType,
+    ocr_results: &[PageOcr],
 ) -> Result<Vec<NewFact>, Box<dyn Error + Send + Sync>> {
-    let document_type = classify_document_type(document, &ocr_results.concat());
-    
     match document_type {
         DocumentType::RentRoll => extract_rent_roll_facts(document, ocr_results),
         DocumentType::ProfitAndLoss => extract_pl_facts(document, ocr_results),
         DocumentType::MortgageStatement => extract_mortgage_facts(document, ocr_results),
         DocumentType::TaxDocument => extract_tax_facts(document, ocr_results),
+        DocumentType::BankStatement => Ok(extract_bank_statement_facts(document, ocr_results)),
+        DocumentType::InsurancePolicy => Ok(extract_insurance_facts(document, ocr_results)),
+        DocumentType::Lease => Ok(extract_lease_facts(document, ocr_results)),
+        DocumentType::Appraisal => Ok(extract_appraisal_facts(document, ocr_results)),
         _ => Ok(vec![]),
     }
 }
@@ -68,7 +356,7 @@ pub async fn extract_facts_from_document(
 /// Extract facts from rent roll document
 fn extract_rent_roll_facts(
     document: &Document,
-    ocr_results: &[Vec<OCRResult>],
+    ocr_results: &[PageOcr],
 ) -> Result<Vec<NewFact>, Box<dyn Error + Send + Sync>> {
     let mut facts = Vec::new();
     
@@ -98,7 +386,7 @@ fn extract_rent_roll_facts(
 /// Extract facts from P&L document
 fn extract_pl_facts(
     document: &Document,
-    ocr_results: &[Vec<OCRResult>],
+    ocr_results: &[PageOcr],
 ) -> Result<Vec<NewFact>, Box<dyn Error + Send + Sync>> {
     let mut facts = Vec::new();
     
@@ -123,7 +411,7 @@ fn extract_pl_facts(
 /// Extract facts from mortgage statement
 fn extract_mortgage_facts(
     document: &Document,
-    ocr_results: &[Vec<OCRResult>],
+    ocr_results: &[PageOcr],
 ) -> Result<Vec<NewFact>, Box<dyn Error + Send + Sync>> {
     let mut facts = Vec::new();
     
@@ -148,7 +436,7 @@ fn extract_mortgage_facts(
 /// Extract facts from tax document
 fn extract_tax_facts(
     document: &Document,
-    ocr_results: &[Vec<OCRResult>],
+    ocr_results: &[PageOcr],
 ) -> Result<Vec<NewFact>, Box<dyn Error + Send + Sync>> {
     let mut facts = Vec::new();
     
@@ -162,25 +450,21 @@ fn extract_tax_facts(
 
 // Helper functions for specific fact extraction
 
-fn extract_unit_count(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_unit_count(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let unit_pattern = Regex::new(r"(?i)(total\s+units?|unit\s+count)[:\s]+(\d+)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = unit_pattern.captures(&page_text) {
             if let Some(count_str) = captures.get(2) {
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::UnitCount.as_str().to_string(),
                     label: "Unit Count".to_string(),
                     value: count_str.as_str().to_string(),
@@ -188,6 +472,7 @@ fn extract_unit_count(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Op
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.8),
+                    period_basis: None,
                 });
             }
         }
@@ -196,25 +481,21 @@ fn extract_unit_count(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Op
     None
 }
 
-fn extract_occupancy_rate(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_occupancy_rate(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let occupancy_pattern = Regex::new(r"(?i)occupancy[:\s]+(\d+\.?\d*)%").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = occupancy_pattern.captures(&page_text) {
             if let Some(rate_str) = captures.get(1) {
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::OccupancyRate.as_str().to_string(),
                     label: "Occupancy Rate".to_string(),
                     value: rate_str.as_str().to_string(),
@@ -222,6 +503,7 @@ fn extract_occupancy_rate(document: &Document, ocr_results: &[Vec<OCRResult>]) -
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.85),
+                    period_basis: None,
                 });
             }
         }
@@ -230,27 +512,23 @@ fn extract_occupancy_rate(document: &Document, ocr_results: &[Vec<OCRResult>]) -
     None
 }
 
-fn extract_gross_scheduled_rent(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_gross_scheduled_rent(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let rent_pattern = Regex::new(r"(?i)gross\s+scheduled\s+rent[:\s]+\$?([\d,]+\.?\d*)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = rent_pattern.captures(&page_text) {
             if let Some(amount_str) = captures.get(1) {
                 let amount = amount_str.as_str().replace(",", "");
                 
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::GrossScheduledRent.as_str().to_string(),
                     label: "Gross Scheduled Rent".to_string(),
                     value: amount,
@@ -258,6 +536,7 @@ fn extract_gross_scheduled_rent(document: &Document, ocr_results: &[Vec<OCRResul
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.9),
+                    period_basis: None,
                 });
             }
         }
@@ -266,27 +545,23 @@ fn extract_gross_scheduled_rent(document: &Document, ocr_results: &[Vec<OCRResul
     None
 }
 
-fn extract_collected_rent(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_collected_rent(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let rent_pattern = Regex::new(r"(?i)collected\s+rent[:\s]+\$?([\d,]+\.?\d*)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = rent_pattern.captures(&page_text) {
             if let Some(amount_str) = captures.get(1) {
                 let amount = amount_str.as_str().replace(",", "");
                 
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::CollectedRent.as_str().to_string(),
                     label: "Collected Rent".to_string(),
                     value: amount,
@@ -294,6 +569,7 @@ fn extract_collected_rent(document: &Document, ocr_results: &[Vec<OCRResult>]) -
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.9),
+                    period_basis: None,
                 });
             }
         }
@@ -302,27 +578,23 @@ fn extract_collected_rent(document: &Document, ocr_results: &[Vec<OCRResult>]) -
     None
 }
 
-fn extract_operating_expenses(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_operating_expenses(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let expense_pattern = Regex::new(r"(?i)(operating\s+expenses?|total\s+expenses?)[:\s]+\$?([\d,]+\.?\d*)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = expense_pattern.captures(&page_text) {
             if let Some(amount_str) = captures.get(2) {
                 let amount = amount_str.as_str().replace(",", "");
                 
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::OperatingExpenses.as_str().to_string(),
                     label: "Operating Expenses".to_string(),
                     value: amount,
@@ -330,6 +602,7 @@ fn extract_operating_expenses(document: &Document, ocr_results: &[Vec<OCRResult>
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.85),
+                    period_basis: None,
                 });
             }
         }
@@ -338,27 +611,23 @@ fn extract_operating_expenses(document: &Document, ocr_results: &[Vec<OCRResult>
     None
 }
 
-fn extract_noi(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_noi(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let noi_pattern = Regex::new(r"(?i)(net\s+operating\s+income|noi)[:\s]+\$?([\d,]+\.?\d*)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = noi_pattern.captures(&page_text) {
             if let Some(amount_str) = captures.get(2) {
                 let amount = amount_str.as_str().replace(",", "");
                 
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::NetOperatingIncome.as_str().to_string(),
                     label: "Net Operating Income".to_string(),
                     value: amount,
@@ -366,6 +635,7 @@ fn extract_noi(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<Ne
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.95),
+                    period_basis: None,
                 });
             }
         }
@@ -374,27 +644,23 @@ fn extract_noi(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<Ne
     None
 }
 
-fn extract_rental_income(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_rental_income(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let income_pattern = Regex::new(r"(?i)rental\s+income[:\s]+\$?([\d,]+\.?\d*)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = income_pattern.captures(&page_text) {
             if let Some(amount_str) = captures.get(1) {
                 let amount = amount_str.as_str().replace(",", "");
                 
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::CollectedRent.as_str().to_string(),
                     label: "Collected Rent".to_string(),
                     value: amount,
@@ -402,6 +668,7 @@ fn extract_rental_income(document: &Document, ocr_results: &[Vec<OCRResult>]) ->
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.85),
+                    period_basis: None,
                 });
             }
         }
@@ -410,27 +677,23 @@ fn extract_rental_income(document: &Document, ocr_results: &[Vec<OCRResult>]) ->
     None
 }
 
-fn extract_mortgage_balance(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_mortgage_balance(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let balance_pattern = Regex::new(r"(?i)(principal\s+balance|outstanding\s+balance|loan\s+balance)[:\s]+\$?([\d,]+\.?\d*)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = balance_pattern.captures(&page_text) {
             if let Some(amount_str) = captures.get(2) {
                 let amount = amount_str.as_str().replace(",", "");
                 
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::MortgageBalance.as_str().to_string(),
                     label: "Mortgage Balance".to_string(),
                     value: amount,
@@ -438,6 +701,7 @@ fn extract_mortgage_balance(document: &Document, ocr_results: &[Vec<OCRResult>])
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.9),
+                    period_basis: None,
                 });
             }
         }
@@ -446,25 +710,21 @@ fn extract_mortgage_balance(document: &Document, ocr_results: &[Vec<OCRResult>])
     None
 }
 
-fn extract_interest_rate(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_interest_rate(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let rate_pattern = Regex::new(r"(?i)interest\s+rate[:\s]+(\d+\.?\d*)%").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = rate_pattern.captures(&page_text) {
             if let Some(rate_str) = captures.get(1) {
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::InterestRate.as_str().to_string(),
                     label: "Interest Rate".to_string(),
                     value: rate_str.as_str().to_string(),
@@ -472,6 +732,7 @@ fn extract_interest_rate(document: &Document, ocr_results: &[Vec<OCRResult>]) ->
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.95),
+                    period_basis: None,
                 });
             }
         }
@@ -480,27 +741,23 @@ fn extract_interest_rate(document: &Document, ocr_results: &[Vec<OCRResult>]) ->
     None
 }
 
-fn extract_debt_service(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_debt_service(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let payment_pattern = Regex::new(r"(?i)(monthly\s+payment|debt\s+service)[:\s]+\$?([\d,]+\.?\d*)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = payment_pattern.captures(&page_text) {
             if let Some(amount_str) = captures.get(2) {
                 let amount = amount_str.as_str().replace(",", "");
                 
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::DebtService.as_str().to_string(),
                     label: "Debt Service".to_string(),
                     value: amount,
@@ -508,6 +765,7 @@ fn extract_debt_service(document: &Document, ocr_results: &[Vec<OCRResult>]) ->
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.9),
+                    period_basis: None,
                 });
             }
         }
@@ -516,27 +774,23 @@ fn extract_debt_service(document: &Document, ocr_results: &[Vec<OCRResult>]) ->
     None
 }
 
-fn extract_property_value(document: &Document, ocr_results: &[Vec<OCRResult>]) -> Option<NewFact> {
+fn extract_property_value(document: &Document, ocr_results: &[PageOcr]) -> Option<NewFact> {
     let value_pattern = Regex::new(r"(?i)(assessed\s+value|property\s+value|market\s+value)[:\s]+\$?([\d,]+\.?\d*)").ok()?;
     
-    for (page_idx, page_results) in ocr_results.iter().enumerate() {
-        let page_text = page_results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+    for (page_idx, page) in ocr_results.iter().enumerate() {
+        let page_text = page.text();
         
         if let Some(captures) = value_pattern.captures(&page_text) {
             if let Some(amount_str) = captures.get(2) {
                 let amount = amount_str.as_str().replace(",", "");
                 
-                let citation = SourceCitation {
-                    document: document.file_name.clone(),
-                    page: (page_idx + 1) as i32,
-                    line: Some(captures.get(0)?.as_str().to_string()),
-                    bbox: None,
-                };
+                let citation = cite_match(document, page, page_idx, &captures);
                 
                 return Some(NewFact {
                     fact_id: Uuid::new_v4().to_string(),
                     document_id: document.document_id.clone(),
                     deal_id: document.deal_id.clone(),
+                    property_id: document.property_id.clone(),
                     fact_type: FactType::PropertyValue.as_str().to_string(),
                     label: "Property Value".to_string(),
                     value: amount,
@@ -544,6 +798,7 @@ fn extract_property_value(document: &Document, ocr_results: &[Vec<OCRResult>]) -
                     source_citation: json!(citation),
                     status: "pending_approval".to_string(),
                     confidence_score: Some(0.8),
+                    period_basis: None,
                 });
             }
         }
@@ -552,3 +807,426 @@ fn extract_property_value(document: &Document, ocr_results: &[Vec<OCRResult>]) -
     None
 }
 
+/// Dates as written in statements, policies and leases
+const DATE_PATTERN: &str = r"\d{1,2}/\d{1,2}/\d{2,4}|\d{4}-\d{2}-\d{2}|[A-Za-z]{3,9}\.?\s+\d{1,2},\s+\d{4}";
+
+/// Text of each page, in reading order
+fn page_texts(ocr_results: &[PageOcr]) -> Vec<String> {
+    ocr_results.iter().map(PageOcr::text).collect()
+}
+
+/// Cite a pattern match by its page, its matched text and the box around the words it spans
+fn cite_match(
+    document: &Document,
+    page: &PageOcr,
+    page_idx: usize,
+    captures: &regex::Captures,
+) -> SourceCitation {
+    let matched = captures.get(0);
+    SourceCitation {
+        document: document.file_name.clone(),
+        page: (page_idx + 1) as i32,
+        line: matched.map(|m| m.as_str().to_string()),
+        bbox: matched.and_then(|m| page.span_bbox(m.range())),
+        page_width: (page.width > 0.0).then_some(page.width as f64),
+        page_height: (page.height > 0.0).then_some(page.height as f64),
+    }
+}
+
+/// First match of a pattern, with the index of the page it was found on
+fn find_pattern<'a>(pattern: &str, pages: &'a [String]) -> Option<(usize, regex::Captures<'a>)> {
+    let pattern = Regex::new(pattern).ok()?;
+    pages
+        .iter()
+        .enumerate()
+        .find_map(|(page_idx, text)| pattern.captures(text).map(|captures| (page_idx, captures)))
+}
+
+/// A pending fact read by a keyword pattern
+fn pattern_fact(
+    document: &Document,
+    fact_type: FactType,
+    label: &str,
+    value: String,
+    unit: &str,
+    citation: SourceCitation,
+    confidence: f64,
+) -> NewFact {
+    NewFact {
+        fact_id: Uuid::new_v4().to_string(),
+        document_id: document.document_id.clone(),
+        deal_id: document.deal_id.clone(),
+        property_id: document.property_id.clone(),
+        fact_type: fact_type.as_str().to_string(),
+        label: label.to_string(),
+        value,
+        unit: Some(unit.to_string()),
+        source_citation: json!(citation),
+        status: "pending_approval".to_string(),
+        confidence_score: Some(confidence),
+        period_basis: None,
+    }
+}
+
+/// Parse an amount such as "$12,500.00"
+fn parse_amount(text: &str) -> Option<f64> {
+    text.replace([',', '$'], "").trim().parse::<f64>().ok()
+}
+
+/// Format a date as YYYY-MM-DD
+fn parse_fact_date(text: &str) -> Option<String> {
+    parse_date(&text.replace('.', "")).map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// Number of months covered by the statement period, one when it cannot be read
+fn statement_months(pages: &[String]) -> f64 {
+    let pattern = format!(
+        r"(?i)(?:statement\s+)?period[:\s]+(?:from\s+)?({date})\s*(?:-|to|through|thru)\s*({date})",
+        date = DATE_PATTERN
+    );
+    find_pattern(&pattern, pages)
+        .and_then(|(_, captures)| {
+            let start = parse_date(captures.get(1)?.as_str())?;
+            let end = parse_date(captures.get(2)?.as_str())?;
+            let days = (end - start).num_days() as f64;
+            Some((days / 30.44).round().max(1.0))
+        })
+        .unwrap_or(1.0)
+}
+
+/// Extract facts from bank statement
+///
+/// Deposits are annualized over the statement period so they can be compared with yearly rent.
+fn extract_bank_statement_facts(document: &Document, ocr_results: &[PageOcr]) -> Vec<NewFact> {
+    let pages = page_texts(ocr_results);
+    let mut facts = Vec::new();
+
+    let balance_pattern =
+        r"(?i)average\s+(?:daily\s+|ledger\s+|collected\s+)?balance[:\s]+\$?([\d,]+\.?\d*)";
+    if let Some((page_idx, captures)) = find_pattern(balance_pattern, &pages) {
+        if let Some(balance) = captures.get(1).and_then(|m| parse_amount(m.as_str())) {
+            facts.push(pattern_fact(
+                document,
+                FactType::AverageBankBalance,
+                "Average Bank Balance",
+                balance.to_string(),
+                "USD",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.85,
+            ));
+        }
+    }
+
+    let deposits_pattern = r"(?i)(total\s+deposits(?:\s+and\s+(?:other\s+)?credits)?|deposits\s+and\s+(?:other\s+)?credits)[:\s]+\$?([\d,]+\.?\d*)";
+    if let Some((page_idx, captures)) = find_pattern(deposits_pattern, &pages) {
+        if let Some(deposits) = captures.get(2).and_then(|m| parse_amount(m.as_str())) {
+            let annualized = deposits * 12.0 / statement_months(&pages);
+            facts.push(pattern_fact(
+                document,
+                FactType::BankDeposits,
+                "Bank Deposits",
+                format!("{:.2}", annualized),
+                "USD/year",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.8,
+            ));
+        }
+    }
+
+    facts
+}
+
+/// Extract facts from insurance declarations
+fn extract_insurance_facts(document: &Document, ocr_results: &[PageOcr]) -> Vec<NewFact> {
+    let pages = page_texts(ocr_results);
+    let mut facts = Vec::new();
+
+    let coverage_pattern = r"(?i)(building\s+(?:coverage|limit)|coverage\s+limit|limit\s+of\s+insurance|total\s+insured\s+value)[:\s]+\$?([\d,]+\.?\d*)";
+    if let Some((page_idx, captures)) = find_pattern(coverage_pattern, &pages) {
+        if let Some(coverage) = captures.get(2).and_then(|m| parse_amount(m.as_str())) {
+            facts.push(pattern_fact(
+                document,
+                FactType::InsuranceCoverage,
+                "Insurance Coverage",
+                coverage.to_string(),
+                "USD",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.8,
+            ));
+        }
+    }
+
+    let premium_pattern =
+        r"(?i)((?:total\s+)?(?:annual\s+)?premium)[:\s]+\$?([\d,]+\.?\d*)";
+    if let Some((page_idx, captures)) = find_pattern(premium_pattern, &pages) {
+        if let Some(premium) = captures.get(2).and_then(|m| parse_amount(m.as_str())) {
+            facts.push(pattern_fact(
+                document,
+                FactType::InsurancePremium,
+                "Insurance Premium",
+                premium.to_string(),
+                "USD/year",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.85,
+            ));
+        }
+    }
+
+    // The end of the policy period, or an explicit expiration date
+    let period_pattern = format!(
+        r"(?i)policy\s+period[:\s]+(?:from\s+)?(?:{date})\s*(?:-|to|through|thru)\s*({date})",
+        date = DATE_PATTERN
+    );
+    let expiration_pattern = format!(
+        r"(?i)(?:expiration\s+date|policy\s+expires?(?:\s+on)?)[:\s]+({})",
+        DATE_PATTERN
+    );
+    let expiration = find_pattern(&period_pattern, &pages)
+        .or_else(|| find_pattern(&expiration_pattern, &pages));
+    if let Some((page_idx, captures)) = expiration {
+        if let Some(date) = captures.get(1).and_then(|m| parse_fact_date(m.as_str())) {
+            facts.push(pattern_fact(
+                document,
+                FactType::InsuranceExpiration,
+                "Insurance Expiration",
+                date,
+                "date",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.8,
+            ));
+        }
+    }
+
+    facts
+}
+
+/// Extract facts from lease
+fn extract_lease_facts(document: &Document, ocr_results: &[PageOcr]) -> Vec<NewFact> {
+    let pages = page_texts(ocr_results);
+    let mut facts = Vec::new();
+
+    let term_pattern = r"(?i)(?:lease\s+)?term[:\s]+(?:of\s+)?(\d+)\s*(months?|years?)";
+    if let Some((page_idx, captures)) = find_pattern(term_pattern, &pages) {
+        let length = captures.get(1).and_then(|m| m.as_str().parse::<i64>().ok());
+        let in_years = captures
+            .get(2)
+            .is_some_and(|m| m.as_str().to_lowercase().starts_with("year"));
+        if let Some(length) = length {
+            let months = if in_years { length * 12 } else { length };
+            facts.push(pattern_fact(
+                document,
+                FactType::LeaseTermMonths,
+                "Lease Term",
+                months.to_string(),
+                "months",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.8,
+            ));
+        }
+    }
+
+    // Monthly rents are annualized
+    let rent_pattern = r"(?i)((?:annual\s+|monthly\s+)?base\s+rent|(?:annual|monthly)\s+rent)[:\s]+\$?([\d,]+\.?\d*)(\s*(?:per|/)\s*(?:month|mo\b))?";
+    if let Some((page_idx, captures)) = find_pattern(rent_pattern, &pages) {
+        if let Some(rent) = captures.get(2).and_then(|m| parse_amount(m.as_str())) {
+            let monthly = captures[1].to_lowercase().contains("monthly") || captures.get(3).is_some();
+            let annual = if monthly { rent * 12.0 } else { rent };
+            facts.push(pattern_fact(
+                document,
+                FactType::LeaseBaseRent,
+                "Lease Base Rent",
+                annual.to_string(),
+                "USD/year",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.8,
+            ));
+        }
+    }
+
+    let escalation_pattern = r"(?i)((?:annual\s+)?(?:rent\s+)?(?:escalations?|increases?))[:\s]+(?:of\s+)?(\d+\.?\d*)\s*%";
+    if let Some((page_idx, captures)) = find_pattern(escalation_pattern, &pages) {
+        if let Some(rate) = captures.get(2) {
+            facts.push(pattern_fact(
+                document,
+                FactType::RentEscalation,
+                "Rent Escalation",
+                rate.as_str().to_string(),
+                "%/year",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.8,
+            ));
+        }
+    }
+
+    let expiration_pattern = format!(
+        r"(?i)(?:expiration\s+date|lease\s+expiration|termination\s+date|end\s+date)[:\s]+({})",
+        DATE_PATTERN
+    );
+    if let Some((page_idx, captures)) = find_pattern(&expiration_pattern, &pages) {
+        if let Some(date) = captures.get(1).and_then(|m| parse_fact_date(m.as_str())) {
+            facts.push(pattern_fact(
+                document,
+                FactType::LeaseExpiration,
+                "Lease Expiration",
+                date,
+                "date",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.8,
+            ));
+        }
+    }
+
+    facts
+}
+
+/// Extract facts from appraisal
+fn extract_appraisal_facts(document: &Document, ocr_results: &[PageOcr]) -> Vec<NewFact> {
+    let pages = page_texts(ocr_results);
+    let mut facts = Vec::new();
+
+    let value_pattern = r"(?i)(as[\s-]+is\s+(?:market\s+)?value|market\s+value\s+as\s+is)[:\s]+\$?([\d,]+\.?\d*)";
+    if let Some((page_idx, captures)) = find_pattern(value_pattern, &pages) {
+        if let Some(value) = captures.get(2).and_then(|m| parse_amount(m.as_str())) {
+            facts.push(pattern_fact(
+                document,
+                FactType::AppraisedValue,
+                "Appraised Value",
+                value.to_string(),
+                "USD",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.85,
+            ));
+        }
+    }
+
+    let cap_rate_pattern =
+        r"(?i)((?:overall\s+)?cap(?:italization)?\s+rate)[:\s]+(\d+\.?\d*)\s*%";
+    if let Some((page_idx, captures)) = find_pattern(cap_rate_pattern, &pages) {
+        if let Some(rate) = captures.get(2) {
+            facts.push(pattern_fact(
+                document,
+                FactType::AppraisalCapRate,
+                "Appraisal Cap Rate",
+                rate.as_str().to_string(),
+                "%",
+                cite_match(document, &ocr_results[page_idx], page_idx, &captures),
+                0.85,
+            ));
+        }
+    }
+
+    facts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::output::{BoundingBox as OutputBoundingBox, Segment, SegmentType};
+
+    fn document() -> Document {
+        Document::for_test("documents.pdf", "other")
+    }
+
+    fn words(words: &[&str]) -> Vec<OCRResult> {
+        words
+            .iter()
+            .enumerate()
+            .map(|(idx, word)| OCRResult {
+                bbox: OutputBoundingBox::new(idx as f32 * 50.0, 10.0, 40.0, 12.0),
+                text: word.to_string(),
+                confidence: Some(0.99),
+            })
+            .collect()
+    }
+
+    fn pages(texts: &[&str]) -> Vec<PageOcr> {
+        texts
+            .iter()
+            .map(|text| PageOcr {
+                results: words(&text.split(' ').collect::<Vec<_>>()),
+                width: 800.0,
+                height: 1000.0,
+            })
+            .collect()
+    }
+
+    fn value_of(facts: &[NewFact], fact_type: &str) -> Option<String> {
+        facts
+            .iter()
+            .find(|f| f.fact_type == fact_type)
+            .map(|f| f.value.clone())
+    }
+
+    #[test]
+    fn test_bank_statement_and_insurance_facts() {
+        let statement = pages(&[
+            "Statement Period: 01/01/2024 - 03/31/2024 Average Daily Balance: $84,210.55",
+            "Total Deposits and Credits: $37,500.00 Total Withdrawals: $30,100.00",
+        ]);
+        let facts = extract_bank_statement_facts(&document(), &statement);
+        assert_eq!(value_of(&facts, "average_bank_balance").as_deref(), Some("84210.55"));
+        assert_eq!(value_of(&facts, "bank_deposits").as_deref(), Some("150000.00"));
+        let citation: SourceCitation =
+            serde_json::from_value(facts[1].source_citation.clone()).unwrap();
+        assert_eq!(citation.page, 2);
+
+        let declarations = pages(&[
+            "Policy Period: March 1, 2025 to March 1, 2026 Building Limit: $4,500,000 Total Annual Premium: $18,240",
+        ]);
+        let facts = extract_insurance_facts(&document(), &declarations);
+        assert_eq!(value_of(&facts, "insurance_coverage").as_deref(), Some("4500000"));
+        assert_eq!(value_of(&facts, "insurance_premium").as_deref(), Some("18240"));
+        assert_eq!(value_of(&facts, "insurance_expiration").as_deref(), Some("2026-03-01"));
+    }
+
+    #[test]
+    fn test_lease_and_appraisal_facts() {
+        let lease = pages(&[
+            "Lease Term: 5 years Monthly Base Rent: $4,000 per month Annual Escalations: 3%",
+            "Expiration Date: 06/30/2030",
+        ]);
+        let facts = extract_lease_facts(&document(), &lease);
+        assert_eq!(value_of(&facts, "lease_term_months").as_deref(), Some("60"));
+        assert_eq!(value_of(&facts, "lease_base_rent").as_deref(), Some("48000"));
+        assert_eq!(value_of(&facts, "rent_escalation").as_deref(), Some("3"));
+        assert_eq!(value_of(&facts, "lease_expiration").as_deref(), Some("2030-06-30"));
+
+        let appraisal = pages(&["As-Is Market Value: $6,250,000 Overall Capitalization Rate: 6.25%"]);
+        let facts = extract_appraisal_facts(&document(), &appraisal);
+        assert_eq!(value_of(&facts, "appraised_value").as_deref(), Some("6250000"));
+        assert_eq!(value_of(&facts, "appraisal_cap_rate").as_deref(), Some("6.25"));
+    }
+
+    #[test]
+    fn test_citation_bounding_box() {
+        let segment = Segment::new(
+            OutputBoundingBox::new(100.0, 200.0, 500.0, 40.0),
+            None,
+            words(&["Appraisal", "Report", "As-Is", "Market", "Value:", "$6,250,000", "Effective"]),
+            1000.0,
+            800.0,
+            3,
+            SegmentType::Text,
+        );
+        let chunks = vec![Chunk {
+            chunk_id: "chunk-1".to_string(),
+            chunk_length: 0,
+            segments: vec![segment],
+            embed: None,
+        }];
+        let ocr_results = ocr_results_by_page(&chunks, 3);
+        assert!(ocr_results[0].results.is_empty());
+
+        let facts = extract_appraisal_facts(&document(), &ocr_results);
+        let citation: SourceCitation =
+            serde_json::from_value(facts[0].source_citation.clone()).unwrap();
+        assert_eq!(citation.page, 3);
+        assert_eq!(citation.line.as_deref(), Some("As-Is Market Value: $6,250,000"));
+        assert_eq!((citation.page_width, citation.page_height), (Some(800.0), Some(1000.0)));
+        let bbox = citation.bbox.unwrap();
+        assert_eq!(
+            (bbox.left, bbox.top, bbox.width, bbox.height),
+            (200.0, 210.0, 190.0, 12.0)
+        );
+    }
+}
@@ -5,4 +5,4 @@ pub mod convert_to_images;
 pub mod crop;
 pub mod fact_extraction;
 pub mod segment_processing;
-// pub mod structured_extraction;
+pub mod structured_extraction;
@@ -1,18 +1,248 @@
 use actix_multipart::form::{MultipartForm, tempfile::TempFile, text::Text};
 use actix_web::{web, HttpResponse, Result};
 use chrono::Utc;
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::models::auth::UserInfo;
-use crate::models::deal::{CreateDealRequest, Deal, DealResponse, NewDeal, UpdateDeal};
-use crate::models::document::{Document, DocumentResponse, NewDocument, UpdateDocument};
+use crate::models::chunk_processing::ChunkProcessing;
+use crate::models::deal::{
+    CreateDealRequest, Deal, DealListQuery, DealResponse, DealSort, DealStatus, NewDeal,
+    SetDealPolicyRequest, ShareDealRequest,
+};
+use crate::models::deal_access::{DealAccessError, DealRole, OrgMembership};
+use crate::models::document::{
+    ClassificationSource, Document, DocumentResponse, DocumentStatus, DocumentType, NewDocument,
+    SetDocumentTypeRequest, UpdateDocument,
+};
 use crate::models::fact::{
-    ApproveFactsRequest, Fact, FactResponse, FactType, NewFact, UpdateFact, UpdateFactValueRequest,
+    ApproveFactsRequest, Fact, FactResponse, PeriodBasis, UpdateFact, UpdateFactValueRequest,
+};
+use crate::models::fact_event::{
+    FactEvent, FactEventType, NewFactEvent, RejectFactRequest, ResetFactsRequest,
+};
+use crate::models::fact_resolution::{
+    ConflictsQuery, FactConflict, FactResolution, NewFactResolution, ResolveConflictRequest,
+};
+use crate::models::fact_value::normalize_fact_value;
+use crate::models::llm::LlmProcessing;
+use crate::models::pl_line_item::{PlLineItem, ProfitAndLossStatement};
+use crate::models::property::{
+    CreatePropertyRequest, NewProperty, Property, SetDocumentPropertyRequest,
+};
+use crate::models::rent_roll_unit::RentRollUnit;
+use crate::models::segment_processing::SegmentProcessing;
+use crate::models::task::{Configuration, Task};
+use crate::models::underwriting_policy::{PolicyRules, UnderwritingPolicy};
+use crate::models::upload::{ErrorHandlingStrategy, OcrStrategy, SegmentationStrategy};
+use crate::services::credit_memo::{load_evidence_images, memo_blocks, render_memo_pdf};
+use crate::services::deal_agent::{analyze_deal, group_by_severity};
+use crate::services::deal_export::DealExport;
+use crate::services::deal_status::{
+    current_status, sync_after_documents, sync_after_review, transition, DealStatusError,
+};
+use crate::models::underwriting_run::{
+    NewUnderwritingRun, UnderwriteQuery, UnderwriteResponse, UnderwritingRun,
+    UnderwritingRunDiffQuery,
+};
+use crate::services::portfolio::DealInput;
+use crate::services::pro_forma::{
+    project_pro_forma, ProFormaAssumptions, PRO_FORMA_METADATA_KEY,
 };
-use crate::services::underwriting::{calculate_underwriting, UnderwritingInput, UnderwritingResult};
-use crate::utils::clients::get_pg_client;
+use crate::services::reconciliation::{
+    apply_resolutions, detect_conflicts, unresolved_conflicts, DEFAULT_CONFLICT_TOLERANCE,
+};
+use crate::services::underwriting::{
+    calculate_underwriting, diff_underwriting_runs, result_snapshot, run_stress_grid,
+    StressTestGrid,
+};
+use crate::utils::clients::get_diesel_conn;
+use crate::utils::services::payload::queue_task_payload;
+
+/// Find a deal the given user has at least `role` on, as its owner or through its organization
+///
+/// A user who cannot see the deal gets `NotFound`, the same as for a deal that does not exist.
+fn find_user_deal(
+    conn: &mut PgConnection,
+    deal_id: &str,
+    user_id: &str,
+    role: DealRole,
+) -> Result<Deal, DealAccessError> {
+    use crate::data::schema::deals;
+
+    let deal = deals::table
+        .filter(deals::deal_id.eq(deal_id))
+        .first::<Deal>(conn)?;
+    match DealRole::for_user(conn, &deal, user_id)? {
+        Some(user_role) if user_role >= role => Ok(deal),
+        Some(_) => Err(DealAccessError::Forbidden(role)),
+        None => Err(DealAccessError::NotFound),
+    }
+}
+
+/// Load a deal for a route needing at least `role` on it, responding 404 when the user cannot
+/// see the deal and 403 when their role is too low
+async fn authorize_deal(deal_id: &str, user_id: &str, role: DealRole) -> Result<Deal> {
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+    let deal_id = deal_id.to_string();
+    let user_id = user_id.to_string();
+
+    web::block(move || find_user_deal(&mut client, &deal_id, &user_id, role))
+        .await
+        .map_err(|e| {
+            eprintln!("Error verifying deal: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Database error")
+        })?
+        .map_err(|e| match e {
+            DealAccessError::NotFound => actix_web::error::ErrorNotFound("Deal not found"),
+            DealAccessError::Forbidden(_) => actix_web::error::ErrorForbidden(e.to_string()),
+            DealAccessError::Database(e) => {
+                eprintln!("Database error: {:?}", e);
+                actix_web::error::ErrorInternalServerError("Database error")
+            }
+        })
+}
+
+/// Map a deal status error to a response, rejected transitions being conflicts
+fn deal_status_error(e: DealStatusError) -> actix_web::Error {
+    eprintln!("Deal status error: {:?}", e);
+    match e {
+        DealStatusError::Database(diesel::result::Error::NotFound) => {
+            actix_web::error::ErrorNotFound("Deal not found")
+        }
+        DealStatusError::Database(_) => actix_web::error::ErrorInternalServerError("Database error"),
+        e => actix_web::error::ErrorConflict(e.to_string()),
+    }
+}
+
+/// Load the approved facts of a deal that feed underwriting, with the reviewer's conflict resolutions
+fn load_underwriting_facts(
+    conn: &mut PgConnection,
+    deal_id: &str,
+) -> QueryResult<(Vec<Fact>, Vec<FactResolution>)> {
+    use crate::data::schema::facts;
+
+    let fact_list = facts::table
+        .filter(facts::deal_id.eq(deal_id))
+        .filter(facts::status.eq("approved"))
+        .filter(facts::locked.eq(true))
+        .order(facts::created_at.asc())
+        .load::<Fact>(conn)?;
+    let resolutions = FactResolution::for_deal(conn, deal_id)?;
+    Ok((fact_list, resolutions))
+}
+
+/// Load everything exported for a deal
+fn load_deal_export(conn: &mut PgConnection, deal_id: &str) -> QueryResult<DealExport> {
+    use crate::data::schema::{deals, documents, facts, pl_line_items, rent_roll_units};
+
+    let deal = deals::table.find(deal_id).first::<Deal>(conn)?;
+    let docs = documents::table
+        .filter(documents::deal_id.eq(deal_id))
+        .order(documents::created_at.asc())
+        .load::<Document>(conn)?;
+    let fact_list = facts::table
+        .filter(facts::deal_id.eq(deal_id))
+        .order(facts::created_at.asc())
+        .load::<Fact>(conn)?;
+    let resolutions = FactResolution::for_deal(conn, deal_id)?;
+    let units = rent_roll_units::table
+        .filter(rent_roll_units::deal_id.eq(deal_id))
+        .order((rent_roll_units::document_id.asc(), rent_roll_units::row_index.asc()))
+        .load::<RentRollUnit>(conn)?;
+    let line_items = pl_line_items::table
+        .filter(pl_line_items::deal_id.eq(deal_id))
+        .order((pl_line_items::document_id.asc(), pl_line_items::row_index.asc()))
+        .load::<PlLineItem>(conn)?;
+
+    let properties = Property::for_deal(conn, deal_id)?;
+    let rules = PolicyRules::for_deal(conn, &deal)?;
+
+    Ok(DealExport::new(
+        deal,
+        docs,
+        properties,
+        &fact_list,
+        &resolutions,
+        units,
+        line_items,
+        &rules,
+    ))
+}
+
+/// File name for a deal's exports, without extension
+fn export_file_name(deal: &Deal) -> String {
+    deal.deal_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Response for underwriting requests blocked by unresolved fact conflicts
+fn unresolved_conflicts_response(conflicts: Vec<FactConflict>) -> HttpResponse {
+    HttpResponse::Conflict().json(serde_json::json!({
+        "error": "Conflicting facts must be resolved before underwriting",
+        "conflicts": conflicts
+    }))
+}
+
+/// Mark a document whose upload could not be processed as failed, so its deal does not wait on it
+async fn fail_upload(document: &Document) {
+    let document_id = document.document_id.clone();
+    let deal_id = document.deal_id.clone();
+    let result = web::block(move || -> Result<(), String> {
+        let mut conn = get_diesel_conn().map_err(|e| e.to_string())?;
+        Document::update(
+            &mut conn,
+            &document_id,
+            &UpdateDocument {
+                status: Some(DocumentStatus::Failed.as_str().to_string()),
+                storage_location: None,
+                page_count: None,
+                ocr_output: None,
+                document_type: None,
+                classification_confidence: None,
+                classification_alternatives: None,
+                classification_source: None,
+            },
+        )
+        .map_err(|e| e.to_string())?;
+        sync_after_documents(&mut conn, &deal_id).map_err(|e| e.to_string())?;
+        Ok(())
+    })
+    .await;
+    if let Err(e) = result.map_err(|e| e.to_string()).and_then(|result| result) {
+        eprintln!("Failed to mark document {} as failed: {}", document.document_id, e);
+    }
+}
+
+/// Configuration for the tasks that process deal documents
+///
+/// Deal documents never expire since their facts keep citing the task output.
+fn deal_document_configuration() -> Configuration {
+    Configuration {
+        chunk_processing: ChunkProcessing::default(),
+        expires_in: None,
+        high_resolution: true,
+        input_file_url: None,
+        json_schema: None,
+        model: None,
+        ocr_strategy: OcrStrategy::default(),
+        #[cfg(feature = "azure")]
+        pipeline: None,
+        segment_processing: SegmentProcessing::default(),
+        segmentation_strategy: SegmentationStrategy::default(),
+        structured_extraction: None,
+        target_chunk_length: None,
+        error_handling: ErrorHandlingStrategy::default(),
+        llm_processing: LlmProcessing::default(),
+    }
+}
 
 // POST /api/v1/deals - Create new deal
 pub async fn create_deal_route(
@@ -28,73 +258,195 @@ pub async fn create_deal_route(
         deal_name: req.deal_name.clone(),
         status: "draft".to_string(),
         metadata: None,
+        policy_id: req.policy_id.clone(),
+        org_id: req.org_id.clone(),
     };
 
-    let mut client = get_pg_client().await.map_err(|e| {
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
     let result = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
-        
-        diesel::insert_into(deals)
+        use crate::data::schema::deals;
+
+        // Deals can only be shared with an organization the creator belongs to
+        if let Some(org_id) = &new_deal.org_id {
+            let memberships = OrgMembership::for_user(&mut client, &new_deal.user_id)?;
+            if !memberships.iter().any(|m| m.org_id == *org_id) {
+                return Ok(Err(format!("You are not a member of organization {}", org_id)));
+            }
+        }
+
+        // Only the user's own policies and those of the deal's organization can be selected
+        if let Some(policy_id) = &new_deal.policy_id {
+            UnderwritingPolicy::find_for_deal(
+                &mut client,
+                policy_id,
+                &new_deal.user_id,
+                &new_deal.user_id,
+                new_deal.org_id.as_deref(),
+            )?;
+        }
+
+        diesel::insert_into(deals::table)
             .values(&new_deal)
             .get_result::<Deal>(&mut client)
+            .map(Ok)
     })
     .await
     .map_err(|e| {
         eprintln!("Error creating deal: {:?}", e);
         actix_web::error::ErrorInternalServerError("Failed to create deal")
     })?
-    .map_err(|e| {
-        eprintln!("Database error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
+    .map_err(|e| match e {
+        diesel::result::Error::NotFound => {
+            actix_web::error::ErrorNotFound("Underwriting policy not found")
+        }
+        e => {
+            eprintln!("Database error: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Database error")
+        }
     })?;
 
-    let response: DealResponse = result.into();
+    let mut response: DealResponse = match result {
+        Ok(deal) => deal.into(),
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": message })));
+        }
+    };
+    response.role = Some(DealRole::Owner.as_str().to_string());
     Ok(HttpResponse::Ok().json(response))
 }
 
-// GET /api/v1/deals - List user's deals
-pub async fn get_deals_route(user_info: web::ReqData<UserInfo>) -> Result<HttpResponse> {
+/// Deals the user can see, as their owner or through their organizations, that match the
+/// listing's filters
+fn filtered_deals<'a>(
+    user_id: &'a str,
+    org_ids: &'a [String],
+    statuses: &'a [String],
+    query: &'a DealListQuery,
+) -> crate::data::schema::deals::BoxedQuery<'a, Pg> {
+    use crate::data::schema::deals;
+    use diesel::dsl::sql;
+    use diesel::sql_types::{Bool, Text};
+
+    let mut filtered = deals::table
+        .filter(deals::user_id.eq(user_id).or(deals::org_id.eq_any(org_ids)))
+        .into_boxed();
+    if !statuses.is_empty() {
+        filtered = filtered.filter(deals::status.eq_any(statuses));
+    }
+    if let Some(start) = query.start {
+        filtered = filtered.filter(deals::created_at.ge(start));
+    }
+    if let Some(end) = query.end {
+        filtered = filtered.filter(deals::created_at.le(end));
+    }
+    if let Some(pattern) = query.name_pattern() {
+        filtered = filtered.filter(deals::deal_name.ilike(pattern));
+    }
+    if let Some(q) = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        // Same expression as the idx_deals_search index
+        filtered = filtered.filter(
+            sql::<Bool>("to_tsvector('simple', deal_name || ' ' || coalesce(metadata::text, ''))")
+                .sql(" @@ plainto_tsquery('simple', ")
+                .bind::<Text, _>(q)
+                .sql(")"),
+        );
+    }
+    filtered
+}
+
+// GET /api/v1/deals?page=&limit=&status=&start=&end=&name=&q=&sort=&order= - List the user's deals
+pub async fn get_deals_route(
+    user_info: web::ReqData<UserInfo>,
+    query: web::Query<DealListQuery>,
+) -> Result<HttpResponse> {
     let user_id = user_info.user_id.clone();
+    let query = query.into_inner();
+    let params = query
+        .pagination()
+        .and_then(|page| Ok((page, query.statuses()?, query.sorting()?)));
+    let ((offset, limit), statuses, (sort, ascending)) = match params {
+        Ok(params) => params,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": message })));
+        }
+    };
     
-    let mut client = get_pg_client().await.map_err(|e| {
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
-    let results = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
-        use crate::data::schema::documents;
-        use crate::data::schema::facts;
+    let (total, results) = web::block(move || {
+        use crate::data::schema::{deals, documents, facts};
+        use diesel::dsl::count_star;
+        
+        let memberships = OrgMembership::for_user(&mut client, &user_id)?;
+        let org_ids: Vec<String> = memberships.iter().map(|m| m.org_id.clone()).collect();
+        
+        let total: i64 = filtered_deals(&user_id, &org_ids, &statuses, &query)
+            .count()
+            .get_result(&mut client)?;
         
-        let deal_list: Vec<Deal> = deals
-            .filter(user_id.eq(&user_id))
-            .order(created_at.desc())
+        let page = filtered_deals(&user_id, &org_ids, &statuses, &query);
+        let page = match (sort, ascending) {
+            (DealSort::CreatedAt, true) => page.order(deals::created_at.asc()),
+            (DealSort::CreatedAt, false) => page.order(deals::created_at.desc()),
+            (DealSort::UpdatedAt, true) => page.order(deals::updated_at.asc()),
+            (DealSort::UpdatedAt, false) => page.order(deals::updated_at.desc()),
+            (DealSort::DealName, true) => page.order(deals::deal_name.asc()),
+            (DealSort::DealName, false) => page.order(deals::deal_name.desc()),
+            (DealSort::Status, true) => page.order(deals::status.asc()),
+            (DealSort::Status, false) => page.order(deals::status.desc()),
+        };
+        // Break ties so that pages do not overlap
+        let deal_list: Vec<Deal> = page
+            .then_order_by(deals::deal_id.asc())
+            .offset(offset)
+            .limit(limit)
             .load::<Deal>(&mut client)?;
         
-        // Get counts for each deal
+        // Count documents and facts of the whole page with one grouped query each
+        let deal_ids: Vec<&str> = deal_list.iter().map(|d| d.deal_id.as_str()).collect();
+        let doc_counts: HashMap<String, i64> = documents::table
+            .filter(documents::deal_id.eq_any(&deal_ids))
+            .group_by(documents::deal_id)
+            .select((documents::deal_id, count_star()))
+            .load::<(String, i64)>(&mut client)?
+            .into_iter()
+            .collect();
+        let fact_counts: HashMap<String, i64> = facts::table
+            .filter(facts::deal_id.eq_any(&deal_ids))
+            .group_by(facts::deal_id)
+            .select((facts::deal_id, count_star()))
+            .load::<(String, i64)>(&mut client)?
+            .into_iter()
+            .collect();
+        
         let mut responses = Vec::new();
         for deal in deal_list {
-            let doc_count: i64 = documents::table
-                .filter(documents::deal_id.eq(&deal.deal_id))
-                .count()
-                .get_result(&mut client)?;
-            
-            let fact_count: i64 = facts::table
-                .filter(facts::deal_id.eq(&deal.deal_id))
-                .count()
-                .get_result(&mut client)?;
+            let role = if deal.user_id == user_id {
+                Some(DealRole::Owner)
+            } else {
+                memberships
+                    .iter()
+                    .find(|m| deal.org_id.as_deref() == Some(m.org_id.as_str()))
+                    .map(|m| m.role)
+            };
+            let doc_count = doc_counts.get(&deal.deal_id).copied().unwrap_or(0);
+            let fact_count = fact_counts.get(&deal.deal_id).copied().unwrap_or(0);
             
             let mut response: DealResponse = deal.into();
+            response.role = role.map(|role| role.as_str().to_string());
             response.document_count = Some(doc_count);
             response.fact_count = Some(fact_count);
             responses.push(response);
         }
         
-        Ok::<Vec<DealResponse>, diesel::result::Error>(responses)
+        Ok::<(i64, Vec<DealResponse>), diesel::result::Error>((total, responses))
     })
     .await
     .map_err(|e| {
@@ -106,7 +458,10 @@ pub async fn get_deals_route(user_info: web::ReqData<UserInfo>) -> Result<HttpRe
         actix_web::error::ErrorInternalServerError("Database error")
     })?;
 
-    Ok(HttpResponse::Ok().json(results))
+    // The page is returned as a list, with the number of matching deals in a header
+    Ok(HttpResponse::Ok()
+        .insert_header(("X-Total-Count", total.to_string()))
+        .json(results))
 }
 
 // GET /api/v1/deals/:deal_id - Get deal details
@@ -117,20 +472,18 @@ pub async fn get_deal_route(
     let deal_id = path.into_inner();
     let user_id = user_info.user_id.clone();
     
-    let mut client = get_pg_client().await.map_err(|e| {
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
     let result = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
         use crate::data::schema::documents;
         use crate::data::schema::facts;
         
-        let deal = deals
-            .filter(deal_id.eq(&deal_id))
-            .filter(user_id.eq(&user_id))
-            .first::<Deal>(&mut client)?;
+        let role = DealRole::for_user(&mut client, &deal, &user_id)?;
         
         let doc_count: i64 = documents::table
             .filter(documents::deal_id.eq(&deal.deal_id))
@@ -143,6 +496,7 @@ pub async fn get_deal_route(
             .get_result(&mut client)?;
         
         let mut response: DealResponse = deal.into();
+        response.role = role.map(|role| role.as_str().to_string());
         response.document_count = Some(doc_count);
         response.fact_count = Some(fact_count);
         
@@ -166,6 +520,8 @@ pub struct UploadDocumentsForm {
     #[multipart(limit = "1 GB")]
     pub files: Vec<TempFile>,
     pub document_type: Text<String>,
+    /// Property of a portfolio deal the documents describe
+    pub property_id: Option<Text<String>>,
 }
 
 // POST /api/v1/deals/:deal_id/documents - Upload documents to deal
@@ -177,76 +533,154 @@ pub async fn upload_deal_documents(
     let deal_id = path.into_inner();
     let user_id = user_info.user_id.clone();
     let doc_type = form.document_type.0;
-    
-    // Verify deal ownership
-    let mut client = get_pg_client().await.map_err(|e| {
+    let property_id = form.property_id.map(|id| id.0);
+
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
+
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
-    let deal_exists = web::block({
-        let deal_id_clone = deal_id.clone();
-        let user_id_clone = user_id.clone();
-        let mut client_clone = client.clone();
+    let (property_exists, mut client) = web::block({
+        let deal_id = deal_id.clone();
+        let property_id = property_id.clone();
         move || {
-            use crate::data::schema::deals::dsl::*;
-            deals
-                .filter(deal_id.eq(&deal_id_clone))
-                .filter(user_id.eq(&user_id_clone))
-                .first::<Deal>(&mut client_clone)
+            // The property must belong to the deal
+            let property = match &property_id {
+                Some(property_id) => Property::find(&mut client, &deal_id, property_id).map(|_| ()),
+                None => Ok(()),
+            };
+            (property, client)
         }
     })
     .await
     .map_err(|e| {
-        eprintln!("Error verifying deal: {:?}", e);
-        actix_web::error::ErrorNotFound("Deal not found")
+        eprintln!("Error verifying property: {:?}", e);
+        actix_web::error::ErrorNotFound("Property not found")
     })?;
 
-    if deal_exists.is_err() {
+    if property_exists.is_err() {
         return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Deal not found or access denied"
+            "error": "Property not found"
         })));
     }
+    
+    // Reject uploads to deals that cannot go back to processing before storing any file
+    let status = current_status(&deal).map_err(deal_status_error)?;
+    if status != DealStatus::ProcessingDocuments
+        && !status.can_transition_to(&DealStatus::ProcessingDocuments)
+    {
+        return Err(deal_status_error(DealStatusError::IllegalTransition {
+            from: status,
+            to: DealStatus::ProcessingDocuments,
+        }));
+    }
+
+    // A type chosen at upload is kept as is, documents of type "other" are classified
+    let classification_source = match DocumentType::from_str(&doc_type) {
+        Some(DocumentType::Other) | None => None,
+        Some(_) => Some(ClassificationSource::User.as_str().to_string()),
+    };
 
-    // Create document records for each file
+    // Create each document record, then upload its file as a task
+    let configuration = deal_document_configuration();
     let mut document_responses = Vec::new();
-    
+
     for file in form.files {
-        let document_id = Uuid::new_v4().to_string();
         let file_name = file.file_name.unwrap_or_else(|| "unknown".to_string());
-        
-        // TODO: Upload file to S3 and trigger OCR processing
-        // For now, just create the database record
-        
+
         let new_doc = NewDocument {
-            document_id: document_id.clone(),
+            document_id: Uuid::new_v4().to_string(),
             deal_id: deal_id.clone(),
             file_name: file_name.clone(),
             document_type: doc_type.clone(),
-            status: "pending".to_string(),
+            status: DocumentStatus::Pending.as_str().to_string(),
             storage_location: None,
             page_count: None,
             ocr_output: None,
+            task_id: None,
+            classification_source: classification_source.clone(),
+            property_id: property_id.clone(),
         };
 
-        let doc = web::block({
-            let mut client_clone = client.clone();
-            move || {
-                use crate::data::schema::documents::dsl::*;
-                diesel::insert_into(documents)
+        let (doc, returned_client) = web::block(move || {
+            use crate::data::schema::{deals, documents};
+            let doc = client.transaction::<_, DealStatusError, _>(|conn| {
+                let doc = diesel::insert_into(documents::table)
                     .values(&new_doc)
-                    .get_result::<Document>(&mut client_clone)
-            }
+                    .get_result::<Document>(conn)?;
+                let deal = deals::table.find(&new_doc.deal_id).first::<Deal>(conn)?;
+                transition(conn, &deal, DealStatus::ProcessingDocuments)?;
+                Ok(doc)
+            });
+            (doc, client)
         })
         .await
         .map_err(|e| {
             eprintln!("Error creating document: {:?}", e);
             actix_web::error::ErrorInternalServerError("Failed to create document")
-        })?
+        })?;
+        client = returned_client;
+        let doc = doc.map_err(deal_status_error)?;
+
+        let task = match Task::new(
+            &user_id,
+            user_info.api_key.clone(),
+            &configuration,
+            &file.file,
+            Some(file_name),
+        )
+        .await
+        {
+            Ok(task) => task,
+            Err(e) => {
+                eprintln!("Error creating task: {:?}", e);
+                fail_upload(&doc).await;
+                return Err(actix_web::error::ErrorInternalServerError(
+                    "Failed to upload document",
+                ));
+            }
+        };
+
+        let (updated, returned_client) = web::block({
+            let document_id = doc.document_id.clone();
+            let task_id = task.task_id.clone();
+            let input_location = task.input_location.clone();
+            move || {
+                let updated =
+                    Document::set_task(&mut client, &document_id, &task_id, &input_location);
+                (updated, client)
+            }
+        })
+        .await
         .map_err(|e| {
-            eprintln!("Database error: {:?}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
+            eprintln!("Error updating document: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Failed to create document")
         })?;
+        client = returned_client;
+        let doc = match updated {
+            Ok(updated) => updated,
+            Err(e) => {
+                eprintln!("Error updating document: {:?}", e);
+                fail_upload(&doc).await;
+                return Err(actix_web::error::ErrorInternalServerError(
+                    "Failed to create document",
+                ));
+            }
+        };
+
+        // Queue only once the document points at its task so the worker can find it
+        if let Err(e) =
+            queue_task_payload(task.to_task_payload(None, None, None, None, &user_info)).await
+        {
+            eprintln!("Error queuing task: {:?}", e);
+            fail_upload(&doc).await;
+            return Err(actix_web::error::ErrorInternalServerError(
+                "Failed to queue document processing",
+            ));
+        }
 
         document_responses.push(DocumentResponse::from(doc));
     }
@@ -262,22 +696,18 @@ pub async fn get_deal_documents(
     let deal_id = path.into_inner();
     let user_id = user_info.user_id.clone();
     
-    let mut client = get_pg_client().await.map_err(|e| {
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
     let results = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
         use crate::data::schema::documents;
         use crate::data::schema::facts;
         
-        // Verify deal ownership
-        deals
-            .filter(deal_id.eq(&deal_id))
-            .filter(user_id.eq(&user_id))
-            .first::<Deal>(&mut client)?;
-        
         // Get documents
         let docs: Vec<Document> = documents::table
             .filter(documents::deal_id.eq(&deal_id))
@@ -312,347 +742,1584 @@ pub async fn get_deal_documents(
     Ok(HttpResponse::Ok().json(results))
 }
 
-// GET /api/v1/deals/:deal_id/facts - Get extracted facts
-pub async fn get_deal_facts(
+// PUT /api/v1/deals/:deal_id/documents/:document_id/type - Override the classified document type
+//
+// The type is used the next time facts are extracted from the document.
+pub async fn set_document_type_route(
     user_info: web::ReqData<UserInfo>,
-    path: web::Path<String>,
+    path: web::Path<(String, String)>,
+    req: web::Json<SetDocumentTypeRequest>,
 ) -> Result<HttpResponse> {
-    let deal_id = path.into_inner();
+    let (deal_id, document_id) = path.into_inner();
     let user_id = user_info.user_id.clone();
+    let document_type = match DocumentType::from_str(&req.document_type) {
+        Some(document_type) => document_type,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Unknown document type: {}", req.document_type)
+            })));
+        }
+    };
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
     
-    let mut client = get_pg_client().await.map_err(|e| {
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
-    let results = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
-        use crate::data::schema::facts;
-        
-        // Verify deal ownership
-        deals
-            .filter(deal_id.eq(&deal_id))
-            .filter(user_id.eq(&user_id))
-            .first::<Deal>(&mut client)?;
-        
-        // Get facts
-        let fact_list: Vec<Fact> = facts::table
-            .filter(facts::deal_id.eq(&deal_id))
-            .order(facts::created_at.asc())
-            .load::<Fact>(&mut client)?;
+    let document = web::block(move || {
+        use crate::data::schema::documents;
         
-        // Convert to responses
-        let responses: Result<Vec<FactResponse>, serde_json::Error> = fact_list
-            .into_iter()
-            .map(|f| f.to_response())
-            .collect();
+        // Verify the document belongs to the deal
+        let document = documents::table
+            .filter(documents::document_id.eq(&document_id))
+            .filter(documents::deal_id.eq(&deal_id))
+            .first::<Document>(&mut client)?;
         
-        responses.map_err(|_| diesel::result::Error::DeserializationError(Box::new(
-            std::io::Error::new(std::io::ErrorKind::Other, "Failed to deserialize facts")
-        )))
+        // The classifier's confidence and alternatives are kept for reference
+        Document::update(
+            &mut client,
+            &document.document_id,
+            &UpdateDocument {
+                status: None,
+                storage_location: None,
+                page_count: None,
+                ocr_output: None,
+                document_type: Some(document_type.as_str().to_string()),
+                classification_confidence: None,
+                classification_alternatives: None,
+                classification_source: Some(ClassificationSource::User.as_str().to_string()),
+            },
+        )
     })
     .await
     .map_err(|e| {
-        eprintln!("Error fetching facts: {:?}", e);
-        actix_web::error::ErrorNotFound("Facts not found")
+        eprintln!("Error updating document type: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot update document type")
     })?
     .map_err(|e| {
         eprintln!("Database error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
+        match e {
+            diesel::result::Error::NotFound => {
+                actix_web::error::ErrorNotFound("Document not found")
+            }
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
     })?;
 
-    Ok(HttpResponse::Ok().json(results))
+    Ok(HttpResponse::Ok().json(DocumentResponse::from(document)))
 }
 
-// PATCH /api/v1/deals/:deal_id/facts/:fact_id - Update fact value
-pub async fn update_fact_route(
+// PUT /api/v1/deals/:deal_id/documents/:document_id/property - Assign a document and its facts to a property
+pub async fn set_document_property_route(
     user_info: web::ReqData<UserInfo>,
     path: web::Path<(String, String)>,
-    req: web::Json<UpdateFactValueRequest>,
+    req: web::Json<SetDocumentPropertyRequest>,
 ) -> Result<HttpResponse> {
-    let (deal_id, fact_id) = path.into_inner();
+    let (deal_id, document_id) = path.into_inner();
     let user_id = user_info.user_id.clone();
+    let property_id = req.into_inner().property_id;
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
     
-    let mut client = get_pg_client().await.map_err(|e| {
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
-    let result = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
-        use crate::data::schema::facts;
+    let document = web::block(move || {
+        use crate::data::schema::{documents, facts};
         
-        // Verify deal ownership
-        deals
-            .filter(deal_id.eq(&deal_id))
-            .filter(user_id.eq(&user_id))
-            .first::<Deal>(&mut client)?;
-        
-        // Check if fact is locked
-        let fact: Fact = facts::table
-            .filter(facts::fact_id.eq(&fact_id))
-            .filter(facts::deal_id.eq(&deal_id))
-            .first::<Fact>(&mut client)?;
-        
-        if fact.locked {
-            return Err(diesel::result::Error::DatabaseError(
-                diesel::result::DatabaseErrorKind::Unknown,
-                Box::new("Cannot update locked fact".to_string())
-            ));
+        // The property must belong to the deal
+        if let Some(property_id) = &property_id {
+            Property::find(&mut client, &deal_id, property_id)?;
         }
         
-        // Update fact
-        let update = UpdateFact {
-            value: Some(req.value.clone()),
-            unit: req.unit.clone(),
-            status: None,
-            approved_at: None,
-            approved_by: None,
-            locked: None,
-        };
-        
-        diesel::update(facts::table.filter(facts::fact_id.eq(&fact_id)))
-            .set(&update)
-            .get_result::<Fact>(&mut client)
+        client.transaction::<_, diesel::result::Error, _>(|conn| {
+            let document = diesel::update(
+                documents::table
+                    .filter(documents::document_id.eq(&document_id))
+                    .filter(documents::deal_id.eq(&deal_id)),
+            )
+            .set(documents::property_id.eq(&property_id))
+            .get_result::<Document>(conn)?;
+            
+            // Facts describe the same property as their document
+            diesel::update(facts::table.filter(facts::document_id.eq(&document_id)))
+                .set(facts::property_id.eq(&property_id))
+                .execute(conn)?;
+            Ok(document)
+        })
     })
     .await
     .map_err(|e| {
-        eprintln!("Error updating fact: {:?}", e);
-        actix_web::error::ErrorBadRequest("Cannot update fact")
+        eprintln!("Error updating document property: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot update document property")
     })?
     .map_err(|e| {
         eprintln!("Database error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?;
-
-    let response = result.to_response().map_err(|e| {
-        eprintln!("Serialization error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Serialization error")
+        match e {
+            diesel::result::Error::NotFound => {
+                actix_web::error::ErrorNotFound("Document or property not found")
+            }
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
     })?;
 
-    Ok(HttpResponse::Ok().json(response))
+    Ok(HttpResponse::Ok().json(DocumentResponse::from(document)))
 }
 
-// POST /api/v1/deals/:deal_id/facts/approve - Approve facts (lock them)
-pub async fn approve_facts_route(
+// POST /api/v1/deals/:deal_id/properties - Add a property to a portfolio deal
+pub async fn create_property_route(
     user_info: web::ReqData<UserInfo>,
     path: web::Path<String>,
-    req: web::Json<ApproveFactsRequest>,
+    req: web::Json<CreatePropertyRequest>,
 ) -> Result<HttpResponse> {
     let deal_id = path.into_inner();
     let user_id = user_info.user_id.clone();
-    let fact_ids = req.fact_ids.clone();
+    let CreatePropertyRequest { name, address } = req.into_inner();
+    if name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Property name must not be empty"
+        })));
+    }
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
     
-    let mut client = get_pg_client().await.map_err(|e| {
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
-    let results = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
-        use crate::data::schema::facts;
+    let property = web::block(move || {
+        use crate::data::schema::properties;
         
-        // Verify deal ownership
-        deals
-            .filter(deal_id.eq(&deal_id))
-            .filter(user_id.eq(&user_id))
-            .first::<Deal>(&mut client)?;
-        
-        // Approve and lock facts
-        let update = UpdateFact {
-            value: None,
-            unit: None,
-            status: Some("approved".to_string()),
-            approved_at: Some(Utc::now()),
-            approved_by: Some(user_id.clone()),
-            locked: Some(true),
+        let new_property = NewProperty {
+            property_id: Uuid::new_v4().to_string(),
+            deal_id: deal_id.clone(),
+            name: name.trim().to_string(),
+            address,
         };
-        
-        diesel::update(
-            facts::table
-                .filter(facts::fact_id.eq_any(&fact_ids))
-                .filter(facts::deal_id.eq(&deal_id))
-        )
-        .set(&update)
-        .execute(&mut client)
+        diesel::insert_into(properties::table)
+            .values(&new_property)
+            .get_result::<Property>(&mut client)
     })
     .await
     .map_err(|e| {
-        eprintln!("Error approving facts: {:?}", e);
-        actix_web::error::ErrorBadRequest("Cannot approve facts")
+        eprintln!("Error creating property: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Failed to create property")
     })?
     .map_err(|e| {
         eprintln!("Database error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
+        match e {
+            diesel::result::Error::NotFound => actix_web::error::ErrorNotFound("Deal not found"),
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
     })?;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "message": "Facts approved successfully",
-        "count": results
-    })))
+    Ok(HttpResponse::Ok().json(property))
 }
 
-// POST /api/v1/deals/:deal_id/facts/reset - Reset all facts to editable
-pub async fn reset_facts_route(
+// GET /api/v1/deals/:deal_id/properties - List the properties of a deal
+pub async fn get_properties_route(
     user_info: web::ReqData<UserInfo>,
     path: web::Path<String>,
 ) -> Result<HttpResponse> {
     let deal_id = path.into_inner();
     let user_id = user_info.user_id.clone();
     
-    let mut client = get_pg_client().await.map_err(|e| {
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
-    let results = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
-        use crate::data::schema::facts;
+    let properties = web::block(move || Property::for_deal(&mut client, &deal_id))
+        .await
+        .map_err(|e| {
+            eprintln!("Error fetching properties: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Cannot fetch properties")
+        })?
+        .map_err(|e| {
+            eprintln!("Database error: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(HttpResponse::Ok().json(properties))
+}
+
+// GET /api/v1/deals/:deal_id/documents/:document_id/rent-roll - Get units parsed from a rent roll
+pub async fn get_rent_roll_units_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (deal_id, document_id) = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let units = web::block(move || {
+        use crate::data::schema::{documents, rent_roll_units};
         
-        // Verify deal ownership
-        deals
-            .filter(deal_id.eq(&deal_id))
-            .filter(user_id.eq(&user_id))
-            .first::<Deal>(&mut client)?;
+        // Verify the document belongs to the deal
+        documents::table
+            .filter(documents::document_id.eq(&document_id))
+            .filter(documents::deal_id.eq(&deal_id))
+            .first::<Document>(&mut client)?;
         
-        // Unlock and reset facts
-        let update = UpdateFact {
-            value: None,
-            unit: None,
-            status: Some("pending_approval".to_string()),
-            approved_at: None,
-            approved_by: None,
-            locked: Some(false),
-        };
+        rent_roll_units::table
+            .filter(rent_roll_units::document_id.eq(&document_id))
+            .order(rent_roll_units::row_index.asc())
+            .load::<RentRollUnit>(&mut client)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error fetching rent roll units: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot fetch rent roll units")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        match e {
+            diesel::result::Error::NotFound => {
+                actix_web::error::ErrorNotFound("Document not found")
+            }
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(units))
+}
+
+// GET /api/v1/deals/:deal_id/documents/:document_id/profit-and-loss - Get the period x line item matrix of a P&L
+pub async fn get_profit_and_loss_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (deal_id, document_id) = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let statement = web::block(move || {
+        use crate::data::schema::{documents, pl_line_items};
         
-        diesel::update(facts::table.filter(facts::deal_id.eq(&deal_id)))
-            .set(&update)
-            .execute(&mut client)
+        // Verify the document belongs to the deal
+        documents::table
+            .filter(documents::document_id.eq(&document_id))
+            .filter(documents::deal_id.eq(&deal_id))
+            .first::<Document>(&mut client)?;
+        
+        let line_items = pl_line_items::table
+            .filter(pl_line_items::document_id.eq(&document_id))
+            .order(pl_line_items::row_index.asc())
+            .load::<PlLineItem>(&mut client)?;
+        
+        Ok::<_, diesel::result::Error>(ProfitAndLossStatement {
+            document_id,
+            periods: line_items.first().map(|l| l.periods.clone()).unwrap_or_default(),
+            line_items,
+        })
     })
     .await
     .map_err(|e| {
-        eprintln!("Error resetting facts: {:?}", e);
-        actix_web::error::ErrorBadRequest("Cannot reset facts")
+        eprintln!("Error fetching P&L line items: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot fetch P&L line items")
     })?
     .map_err(|e| {
         eprintln!("Database error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
+        match e {
+            diesel::result::Error::NotFound => {
+                actix_web::error::ErrorNotFound("Document not found")
+            }
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
     })?;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "message": "Facts reset successfully",
-        "count": results
-    })))
+    Ok(HttpResponse::Ok().json(statement))
 }
 
-// POST /api/v1/deals/:deal_id/underwrite - Run underwriting calculations
-pub async fn calculate_underwriting_route(
+// GET /api/v1/deals/:deal_id/facts - Get extracted facts
+pub async fn get_deal_facts(
     user_info: web::ReqData<UserInfo>,
     path: web::Path<String>,
 ) -> Result<HttpResponse> {
     let deal_id = path.into_inner();
     let user_id = user_info.user_id.clone();
     
-    let mut client = get_pg_client().await.map_err(|e| {
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
         eprintln!("Database connection error: {:?}", e);
         actix_web::error::ErrorInternalServerError("Database connection failed")
     })?;
 
-    let result = web::block(move || {
-        use crate::data::schema::deals::dsl::*;
+    let results = web::block(move || {
         use crate::data::schema::facts;
         
-        // Verify deal ownership
-        deals
-            .filter(deal_id.eq(&deal_id))
-            .filter(user_id.eq(&user_id))
-            .first::<Deal>(&mut client)?;
-        
-        // Get approved facts for this deal
+        // Get facts
         let fact_list: Vec<Fact> = facts::table
             .filter(facts::deal_id.eq(&deal_id))
-            .filter(facts::status.eq("approved"))
-            .filter(facts::locked.eq(true))
+            .order(facts::created_at.asc())
             .load::<Fact>(&mut client)?;
         
-        // Extract values from facts by type
-        let mut unit_count: Option<i32> = None;
-        let mut occupancy_rate: Option<f64> = None;
-        let mut gross_scheduled_rent: Option<f64> = None;
-        let mut collected_rent: Option<f64> = None;
-        let mut operating_expenses: Option<f64> = None;
-        let mut debt_service: Option<f64> = None;
-        let mut property_value: Option<f64> = None;
-        let mut mortgage_balance: Option<f64> = None;
-        let mut interest_rate: Option<f64> = None;
-        
-        for fact in fact_list {
-            let parsed_value = fact.value.parse::<f64>().ok();
+        // Convert to responses
+        let responses: Result<Vec<FactResponse>, serde_json::Error> = fact_list
+            .into_iter()
+            .map(|f| f.to_response())
+            .collect();
+        
+        responses.map_err(|_| diesel::result::Error::DeserializationError(Box::new(
+            std::io::Error::other("Failed to deserialize facts")
+        )))
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error fetching facts: {:?}", e);
+        actix_web::error::ErrorNotFound("Facts not found")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+// PATCH /api/v1/deals/:deal_id/facts/:fact_id - Update fact value
+pub async fn update_fact_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<(String, String)>,
+    req: web::Json<UpdateFactValueRequest>,
+) -> Result<HttpResponse> {
+    let (deal_id, fact_id) = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let result = web::block(move || {
+        use crate::data::schema::facts;
+        
+        client.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Check if fact is locked
+            let fact: Fact = facts::table
+                .filter(facts::fact_id.eq(&fact_id))
+                .filter(facts::deal_id.eq(&deal_id))
+                .first::<Fact>(conn)?;
             
-            match fact.fact_type.as_str() {
-                "unit_count" => {
-                    unit_count = fact.value.parse::<i32>().ok();
-                },
-                "occupancy_rate" => {
-                    occupancy_rate = parsed_value;
-                },
-                "gross_scheduled_rent" => {
-                    gross_scheduled_rent = parsed_value;
-                },
-                "collected_rent" => {
-                    collected_rent = parsed_value;
-                },
-                "operating_expenses" => {
-                    operating_expenses = parsed_value;
-                },
-                "debt_service" => {
-                    debt_service = parsed_value;
-                },
-                "property_value" => {
-                    property_value = parsed_value;
-                },
-                "mortgage_balance" => {
-                    mortgage_balance = parsed_value;
-                },
-                "interest_rate" => {
-                    interest_rate = parsed_value;
-                },
-                _ => {}
+            if fact.locked {
+                return Err(diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::Unknown,
+                    Box::new("Cannot update locked fact".to_string())
+                ));
             }
+            
+            // Edits must be readable as the fact's kind of value
+            let unit = req.unit.as_deref().or(fact.unit.as_deref());
+            let (value, unit) = match normalize_fact_value(&fact.fact_type, &req.value, unit) {
+                Ok(normalized) => normalized,
+                Err(message) => return Ok(Err(message)),
+            };
+            
+            // Update fact
+            let update = UpdateFact {
+                value: Some(value),
+                unit,
+                status: None,
+                approved_at: None,
+                approved_by: None,
+                locked: None,
+            };
+            
+            let updated = diesel::update(facts::table.filter(facts::fact_id.eq(&fact_id)))
+                .set(&update)
+                .get_result::<Fact>(conn)?;
+            
+            let event = NewFactEvent::edited(
+                &fact,
+                &updated,
+                FactEventType::Edited,
+                Some(&user_id),
+                req.reason.clone(),
+            );
+            FactEvent::record(conn, &[event])?;
+            Ok(Ok(updated))
+        })
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error updating fact: {:?}", e);
+        actix_web::error::ErrorBadRequest("Cannot update fact")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    let result = match result {
+        Ok(fact) => fact,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": message })));
         }
+    };
+
+    let response = result.to_response().map_err(|e| {
+        eprintln!("Serialization error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Serialization error")
+    })?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// POST /api/v1/deals/:deal_id/facts/approve - Approve facts (lock them)
+pub async fn approve_facts_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    req: web::Json<ApproveFactsRequest>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let fact_ids = req.fact_ids.clone();
+    let reason = req.reason.clone();
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Approver).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let results = web::block(move || {
+        use crate::data::schema::facts;
         
-        // Validate required fields
-        if collected_rent.is_none() || operating_expenses.is_none() {
-            return Err(diesel::result::Error::DatabaseError(
-                diesel::result::DatabaseErrorKind::Unknown,
-                Box::new("Missing required facts: collected_rent and operating_expenses".to_string())
-            ));
+        // Approve and lock facts
+        let update = UpdateFact {
+            value: None,
+            unit: None,
+            status: Some("approved".to_string()),
+            approved_at: Some(Utc::now()),
+            approved_by: Some(user_id.clone()),
+            locked: Some(true),
+        };
+        
+        client.transaction::<_, DealStatusError, _>(|conn| {
+            // Four-eyes on shared deals: a fact cannot be approved by the user who last edited
+            // it. Locking the fact rows keeps an edit from landing between the check and the
+            // approval.
+            if deal.org_id.is_some() {
+                facts::table
+                    .filter(facts::fact_id.eq_any(&fact_ids))
+                    .filter(facts::deal_id.eq(&deal_id))
+                    .select(facts::fact_id)
+                    .for_update()
+                    .load::<String>(conn)?;
+                let last_editors = FactEvent::last_editors(conn, &deal_id, &fact_ids)?;
+                let own_edits: Vec<String> = fact_ids
+                    .iter()
+                    .filter(|fact_id| last_editors.get(*fact_id) == Some(&user_id))
+                    .cloned()
+                    .collect();
+                if !own_edits.is_empty() {
+                    return Ok(Err(own_edits));
+                }
+            }
+            
+            let approved = diesel::update(
+                facts::table
+                    .filter(facts::fact_id.eq_any(&fact_ids))
+                    .filter(facts::deal_id.eq(&deal_id))
+            )
+            .set(&update)
+            .get_results::<Fact>(conn)?;
+            
+            let events: Vec<NewFactEvent> = approved
+                .iter()
+                .map(|fact| {
+                    NewFactEvent::for_fact(
+                        fact,
+                        FactEventType::Approved,
+                        Some(&user_id),
+                        reason.clone(),
+                    )
+                })
+                .collect();
+            FactEvent::record(conn, &events)?;
+            let deal = sync_after_review(conn, &deal)?;
+            Ok(Ok((approved.len(), deal.status)))
+        })
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error approving facts: {:?}", e);
+        actix_web::error::ErrorBadRequest("Cannot approve facts")
+    })?
+    .map_err(deal_status_error)?;
+
+    let results = match results {
+        Ok(results) => results,
+        Err(fact_ids) => {
+            return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+                "error": "Facts you last edited must be approved by another user",
+                "fact_ids": fact_ids
+            })));
         }
+    };
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Facts approved successfully",
+        "count": results.0,
+        "deal_status": results.1
+    })))
+}
+
+// POST /api/v1/deals/:deal_id/facts/reset - Reset all facts to editable
+pub async fn reset_facts_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    req: web::Json<ResetFactsRequest>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let reason = req.into_inner().reason;
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Approver).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let results = web::block(move || {
+        use crate::data::schema::facts;
         
-        let input = UnderwritingInput {
-            unit_count,
-            occupancy_rate,
-            gross_scheduled_rent,
-            collected_rent: collected_rent.unwrap(),
-            operating_expenses: operating_expenses.unwrap(),
-            debt_service,
-            property_value,
-            mortgage_balance,
-            interest_rate,
+        // Unlock and reset facts
+        let update = UpdateFact {
+            value: None,
+            unit: None,
+            status: Some("pending_approval".to_string()),
+            approved_at: None,
+            approved_by: None,
+            locked: Some(false),
         };
         
-        Ok::<UnderwritingResult, diesel::result::Error>(calculate_underwriting(input))
+        client.transaction::<_, DealStatusError, _>(|conn| {
+            // Only facts that were reviewed get a reset event
+            let reviewed = facts::table
+                .filter(facts::deal_id.eq(&deal_id))
+                .filter(
+                    facts::locked
+                        .eq(true)
+                        .or(facts::status.ne("pending_approval")),
+                )
+                .load::<Fact>(conn)?;
+            
+            let count = diesel::update(facts::table.filter(facts::deal_id.eq(&deal_id)))
+                .set(&update)
+                .execute(conn)?;
+            
+            let events: Vec<NewFactEvent> = reviewed
+                .iter()
+                .map(|fact| {
+                    NewFactEvent::for_fact(
+                        fact,
+                        FactEventType::Reset,
+                        Some(&user_id),
+                        reason.clone(),
+                    )
+                })
+                .collect();
+            FactEvent::record(conn, &events)?;
+            // A complete deal cannot go back to review, which rolls the reset back
+            let deal = sync_after_review(conn, &deal)?;
+            Ok((count, deal.status))
+        })
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error resetting facts: {:?}", e);
+        actix_web::error::ErrorBadRequest("Cannot reset facts")
+    })?
+    .map_err(deal_status_error)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Facts reset successfully",
+        "count": results.0,
+        "deal_status": results.1
+    })))
+}
+
+// POST /api/v1/deals/:deal_id/facts/:fact_id/reject - Reject a fact
+pub async fn reject_fact_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<(String, String)>,
+    req: web::Json<RejectFactRequest>,
+) -> Result<HttpResponse> {
+    let (deal_id, fact_id) = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let reason = req.into_inner().reason;
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let result = web::block(move || {
+        use crate::data::schema::facts;
+        
+        client.transaction::<_, diesel::result::Error, _>(|conn| {
+            let fact: Fact = facts::table
+                .filter(facts::fact_id.eq(&fact_id))
+                .filter(facts::deal_id.eq(&deal_id))
+                .first::<Fact>(conn)?;
+            
+            if fact.locked {
+                return Err(diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::Unknown,
+                    Box::new("Cannot reject locked fact".to_string())
+                ));
+            }
+            
+            let update = UpdateFact {
+                value: None,
+                unit: None,
+                status: Some("rejected".to_string()),
+                approved_at: None,
+                approved_by: None,
+                locked: None,
+            };
+            
+            let rejected = diesel::update(facts::table.filter(facts::fact_id.eq(&fact_id)))
+                .set(&update)
+                .get_result::<Fact>(conn)?;
+            
+            let event = NewFactEvent::for_fact(
+                &rejected,
+                FactEventType::Rejected,
+                Some(&user_id),
+                reason,
+            );
+            FactEvent::record(conn, &[event])?;
+            Ok(rejected)
+        })
     })
     .await
     .map_err(|e| {
-        eprintln!("Error calculating underwriting: {:?}", e);
-        actix_web::error::ErrorBadRequest("Cannot calculate underwriting")
+        eprintln!("Error rejecting fact: {:?}", e);
+        actix_web::error::ErrorBadRequest("Cannot reject fact")
     })?
     .map_err(|e| {
         eprintln!("Database error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
+        match e {
+            diesel::result::Error::NotFound => actix_web::error::ErrorNotFound("Fact not found"),
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
     })?;
 
-    Ok(HttpResponse::Ok().json(result))
+    let response = result.to_response().map_err(|e| {
+        eprintln!("Serialization error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Serialization error")
+    })?;
+
+    Ok(HttpResponse::Ok().json(response))
 }
 
+// GET /api/v1/deals/:deal_id/facts/:fact_id/history - Audit log of a fact, oldest first
+pub async fn get_fact_history_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (deal_id, fact_id) = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let events = web::block(move || {
+        
+        FactEvent::history(&mut client, &deal_id, &fact_id)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error fetching fact history: {:?}", e);
+        actix_web::error::ErrorNotFound("Fact history not found")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        match e {
+            diesel::result::Error::NotFound => actix_web::error::ErrorNotFound("Deal not found"),
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(events))
+}
+
+// POST /api/v1/deals/:deal_id/underwrite?period_basis=:basis - Run underwriting calculations
+pub async fn calculate_underwriting_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    query: web::Query<UnderwriteQuery>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let period_basis = match query.into_inner().period_basis {
+        Some(basis) => match PeriodBasis::from_str(&basis) {
+            Some(basis) => basis,
+            None => {
+                return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": format!("Invalid period basis: {}", basis)
+                })));
+            }
+        },
+        None => PeriodBasis::default(),
+    };
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let result = web::block(move || {
+        use crate::data::schema::underwriting_runs;
+        
+        let rules = PolicyRules::for_deal(&mut client, &deal)?;
+        
+        // Get approved facts for this deal
+        let (fact_list, resolutions) = load_underwriting_facts(&mut client, &deal_id)?;
+        let properties = Property::for_deal(&mut client, &deal_id)?;
+        
+        // Documents that disagree must be reconciled by a reviewer first
+        let conflicts = unresolved_conflicts(&fact_list, &resolutions, &period_basis);
+        if !conflicts.is_empty() {
+            return Ok(Err(conflicts));
+        }
+        let fact_list = apply_resolutions(&fact_list, &resolutions);
+        
+        // Validate required fields, for each property of a portfolio
+        let deal_input = DealInput::from_facts(properties, &fact_list, &period_basis, &rules);
+        let deal_input = match deal_input {
+            Ok(deal_input) => deal_input,
+            Err(e) => return Ok(Ok(Err(e))),
+        };
+        let input = deal_input.input.clone();
+        let result = calculate_underwriting(input.clone(), &rules);
+        let property_results = deal_input.underwrite_properties(&rules);
+        
+        let serialization_error = |e: serde_json::Error| {
+            diesel::result::Error::SerializationError(Box::new(e))
+        };
+        let inputs = serde_json::to_value(&input).map_err(serialization_error)?;
+        let result_json = result_snapshot(&result).map_err(serialization_error)?;
+        let audit_trail = serde_json::to_value(&result.audit_trail).map_err(serialization_error)?;
+        let property_results_json =
+            serde_json::to_value(&property_results).map_err(serialization_error)?;
+        
+        // Store the run under the deal's next version
+        let run = client.transaction::<_, diesel::result::Error, _>(|conn| {
+            let new_run = NewUnderwritingRun {
+                run_id: Uuid::new_v4().to_string(),
+                deal_id: deal_id.clone(),
+                version: UnderwritingRun::next_version(conn, &deal_id)?,
+                input_fact_ids: deal_input.source_fact_ids(),
+                inputs,
+                result: result_json,
+                audit_trail,
+                warnings: result.warnings.clone(),
+                run_by: user_id.clone(),
+                property_results: property_results_json,
+            };
+            
+            diesel::insert_into(underwriting_runs::table)
+                .values(&new_run)
+                .get_result::<UnderwritingRun>(conn)
+        })?;
+        
+        Ok::<_, diesel::result::Error>(Ok(Ok(UnderwriteResponse {
+            run_id: run.run_id,
+            version: run.version,
+            result,
+            properties: property_results,
+        })))
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error calculating underwriting: {:?}", e);
+        actix_web::error::ErrorBadRequest("Cannot calculate underwriting")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    match result {
+        Ok(Ok(response)) => Ok(HttpResponse::Ok().json(response)),
+        Ok(Err(e)) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))),
+        Err(conflicts) => Ok(unresolved_conflicts_response(conflicts)),
+    }
+}
+
+
+// GET /api/v1/deals/:deal_id/recommendations - Get deal review recommendations
+pub async fn get_deal_recommendations_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let (fact_list, resolutions, properties, rules) = web::block(move || {
+        use crate::data::schema::facts;
+        
+        let rules = PolicyRules::for_deal(&mut client, &deal)?;
+        
+        let fact_list = facts::table
+            .filter(facts::deal_id.eq(&deal_id))
+            .order(facts::created_at.asc())
+            .load::<Fact>(&mut client)?;
+        let resolutions = FactResolution::for_deal(&mut client, &deal_id)?;
+        let properties = Property::for_deal(&mut client, &deal_id)?;
+        Ok::<_, diesel::result::Error>((fact_list, resolutions, properties, rules))
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error fetching facts: {:?}", e);
+        actix_web::error::ErrorNotFound("Facts not found")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    // Underwriting only considers approved facts, same as the underwrite endpoint
+    let approved_facts: Vec<Fact> = fact_list
+        .iter()
+        .filter(|f| f.status == "approved" && f.locked)
+        .cloned()
+        .collect();
+    let approved_facts = apply_resolutions(&approved_facts, &resolutions);
+    let underwriting =
+        DealInput::from_facts(properties, &approved_facts, &PeriodBasis::default(), &rules)
+            .ok()
+            .map(|deal_input| calculate_underwriting(deal_input.input, &rules));
+
+    let recommendations = analyze_deal(&fact_list, underwriting.as_ref(), &rules);
+    Ok(HttpResponse::Ok().json(group_by_severity(recommendations)))
+}
+
+// POST /api/v1/deals/:deal_id/stress-test - Run a grid of stress scenarios
+pub async fn stress_test_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    req: web::Json<StressTestGrid>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let grid = req.into_inner();
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let (input, rules) = web::block(move || {
+        let rules = PolicyRules::for_deal(&mut client, &deal)?;
+        
+        // Get approved facts for this deal
+        let (fact_list, resolutions) = load_underwriting_facts(&mut client, &deal_id)?;
+        let properties = Property::for_deal(&mut client, &deal_id)?;
+        
+        let conflicts = unresolved_conflicts(&fact_list, &resolutions, &PeriodBasis::default());
+        if !conflicts.is_empty() {
+            return Ok((Err(conflicts), rules));
+        }
+        let fact_list = apply_resolutions(&fact_list, &resolutions);
+        
+        Ok::<_, diesel::result::Error>((
+            Ok(DealInput::from_facts(properties, &fact_list, &PeriodBasis::default(), &rules)),
+            rules,
+        ))
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error loading facts: {:?}", e);
+        actix_web::error::ErrorNotFound("Deal not found")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    let input = match input {
+        Ok(input) => input,
+        Err(conflicts) => return Ok(unresolved_conflicts_response(conflicts)),
+    };
+    let input = match input {
+        Ok(deal_input) => deal_input.input,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))),
+    };
+
+    match run_stress_grid(input, &grid, &rules) {
+        Ok(matrix) => Ok(HttpResponse::Ok().json(matrix)),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))),
+    }
+}
+
+// PUT /api/v1/deals/:deal_id/pro-forma/assumptions - Store the deal's pro forma assumptions
+pub async fn set_pro_forma_assumptions_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    req: web::Json<ProFormaAssumptions>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let assumptions = req.into_inner();
+    if let Err(e) = assumptions.validate() {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e })));
+    }
+    let assumptions_json = serde_json::to_value(&assumptions)?;
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    web::block(move || {
+        use crate::data::schema::deals;
+        
+        let mut metadata = match deal.metadata {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        metadata.insert(PRO_FORMA_METADATA_KEY.to_string(), assumptions_json);
+        diesel::update(deals::table.find(&deal.deal_id))
+            .set((
+                deals::metadata.eq(serde_json::Value::Object(metadata)),
+                deals::updated_at.eq(Utc::now()),
+            ))
+            .execute(&mut client)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error storing pro forma assumptions: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot store pro forma assumptions")
+    })?
+    .map_err(|e| match e {
+        diesel::result::Error::NotFound => actix_web::error::ErrorNotFound("Deal not found"),
+        e => {
+            eprintln!("Database error: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Database error")
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(assumptions))
+}
+
+// GET /api/v1/deals/:deal_id/pro-forma - Project the deal's cash flows over the hold period
+pub async fn get_pro_forma_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let (input, rules, assumptions) = web::block(move || {
+        let rules = PolicyRules::for_deal(&mut client, &deal)?;
+        let assumptions = ProFormaAssumptions::from_metadata(&deal.metadata);
+        
+        // Get approved facts for this deal
+        let (fact_list, resolutions) = load_underwriting_facts(&mut client, &deal_id)?;
+        let properties = Property::for_deal(&mut client, &deal_id)?;
+        
+        let conflicts = unresolved_conflicts(&fact_list, &resolutions, &PeriodBasis::default());
+        if !conflicts.is_empty() {
+            return Ok((Err(conflicts), rules, assumptions));
+        }
+        let fact_list = apply_resolutions(&fact_list, &resolutions);
+        
+        Ok::<_, diesel::result::Error>((
+            Ok(DealInput::from_facts(properties, &fact_list, &PeriodBasis::default(), &rules)),
+            rules,
+            assumptions,
+        ))
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error loading facts: {:?}", e);
+        actix_web::error::ErrorNotFound("Deal not found")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    let input = match input {
+        Ok(input) => input,
+        Err(conflicts) => return Ok(unresolved_conflicts_response(conflicts)),
+    };
+    let input = match input {
+        Ok(deal_input) => deal_input.input,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))),
+    };
+
+    match project_pro_forma(&input, &rules, &assumptions) {
+        Ok(pro_forma) => Ok(HttpResponse::Ok().json(pro_forma)),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))),
+    }
+}
+
+// GET /api/v1/deals/:deal_id/conflicts?tolerance=:tolerance - List facts that disagree across documents
+pub async fn get_fact_conflicts_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    query: web::Query<ConflictsQuery>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let tolerance = query.into_inner().tolerance.unwrap_or(DEFAULT_CONFLICT_TOLERANCE);
+    if !tolerance.is_finite() || tolerance < 0.0 {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "tolerance must be a non-negative number"
+        })));
+    }
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let conflicts = web::block(move || {
+        use crate::data::schema::facts;
+        
+        let fact_list = facts::table
+            .filter(facts::deal_id.eq(&deal_id))
+            .order(facts::created_at.asc())
+            .load::<Fact>(&mut client)?;
+        let resolutions = FactResolution::for_deal(&mut client, &deal_id)?;
+        
+        Ok::<_, diesel::result::Error>(detect_conflicts(
+            &fact_list,
+            &resolutions,
+            &PeriodBasis::default(),
+            tolerance,
+        ))
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error detecting fact conflicts: {:?}", e);
+        actix_web::error::ErrorNotFound("Deal not found")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(HttpResponse::Ok().json(conflicts))
+}
+
+// POST /api/v1/deals/:deal_id/conflicts/:fact_type/resolve - Pick the fact to use for a fact type
+pub async fn resolve_fact_conflict_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<(String, String)>,
+    req: web::Json<ResolveConflictRequest>,
+) -> Result<HttpResponse> {
+    let (deal_id, fact_type) = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let ResolveConflictRequest { fact_id, reason } = req.into_inner();
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Analyst).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let resolution = web::block(move || {
+        use crate::data::schema::{fact_resolutions, facts};
+        
+        // The picked fact must be one of the deal's facts of that type
+        let fact = facts::table
+            .filter(facts::fact_id.eq(&fact_id))
+            .filter(facts::deal_id.eq(&deal_id))
+            .filter(facts::fact_type.eq(&fact_type))
+            .first::<Fact>(&mut client)?;
+        // Underwriting only uses approved facts, so a resolution to any other would not apply
+        if fact.status != "approved" || !fact.locked {
+            return Ok(Err("Only an approved fact can resolve a conflict".to_string()));
+        }
+        
+        let resolution = NewFactResolution {
+            resolution_id: Uuid::new_v4().to_string(),
+            deal_id: deal_id.clone(),
+            fact_type: fact_type.clone(),
+            fact_id: fact_id.clone(),
+            resolved_by: user_id.clone(),
+            resolved_at: Utc::now(),
+        };
+        client.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Replace the resolution of this fact type for the picked fact's property only, so
+            // each property of a portfolio keeps its own
+            let property_fact_ids: Vec<String> = facts::table
+                .filter(facts::deal_id.eq(&deal_id))
+                .filter(facts::fact_type.eq(&fact_type))
+                .filter(facts::property_id.is_not_distinct_from(&fact.property_id))
+                .select(facts::fact_id)
+                .load(conn)?;
+            diesel::delete(
+                fact_resolutions::table
+                    .filter(fact_resolutions::deal_id.eq(&deal_id))
+                    .filter(fact_resolutions::fact_type.eq(&fact_type))
+                    .filter(fact_resolutions::fact_id.eq_any(&property_fact_ids)),
+            )
+            .execute(conn)?;
+            let resolution = diesel::insert_into(fact_resolutions::table)
+                .values(&resolution)
+                .get_result::<FactResolution>(conn)?;
+            
+            let event = NewFactEvent::for_fact(
+                &fact,
+                FactEventType::Resolved,
+                Some(&user_id),
+                reason,
+            );
+            FactEvent::record(conn, &[event])?;
+            Ok(resolution)
+        })
+        .map(Ok)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error resolving fact conflict: {:?}", e);
+        actix_web::error::ErrorBadRequest("Cannot resolve fact conflict")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        match e {
+            diesel::result::Error::NotFound => {
+                actix_web::error::ErrorNotFound("Fact not found for this deal and fact type")
+            }
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
+    })?;
+
+    match resolution {
+        Ok(resolution) => Ok(HttpResponse::Ok().json(resolution)),
+        Err(message) => {
+            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": message })))
+        }
+    }
+}
+
+// GET /api/v1/deals/:deal_id/underwriting-runs - List underwriting runs, newest first
+pub async fn get_underwriting_runs_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let results = web::block(move || {
+        use crate::data::schema::underwriting_runs;
+        
+        underwriting_runs::table
+            .filter(underwriting_runs::deal_id.eq(&deal_id))
+            .order(underwriting_runs::version.desc())
+            .load::<UnderwritingRun>(&mut client)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error fetching underwriting runs: {:?}", e);
+        actix_web::error::ErrorNotFound("Underwriting runs not found")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+// GET /api/v1/deals/:deal_id/underwriting-runs/diff?from=:run_id&to=:run_id - Compare two runs
+pub async fn diff_underwriting_runs_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    query: web::Query<UnderwritingRunDiffQuery>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let query = query.into_inner();
+    
+    // Verify deal access
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let result = web::block(move || {
+        
+        let from = UnderwritingRun::find(&mut client, &deal_id, &query.from)?;
+        let to = UnderwritingRun::find(&mut client, &deal_id, &query.to)?;
+        
+        Ok::<_, diesel::result::Error>(diff_underwriting_runs(&from, &to))
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error comparing underwriting runs: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot compare underwriting runs")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        match e {
+            diesel::result::Error::NotFound => {
+                actix_web::error::ErrorNotFound("Underwriting run not found")
+            }
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// POST /api/v1/deals/:deal_id/underwriting-runs/:run_id/finalize - Accept a run and complete the deal
+pub async fn finalize_underwriting_run_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (deal_id, run_id) = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Approver).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let run = web::block(move || {
+        use crate::data::schema::underwriting_runs;
+        
+        client.transaction::<_, DealStatusError, _>(|conn| {
+            let run = UnderwritingRun::find(conn, &deal_id, &run_id)?;
+            // Only one run can be final
+            if current_status(&deal)? == DealStatus::Complete {
+                return Err(DealStatusError::AlreadyComplete);
+            }
+            transition(conn, &deal, DealStatus::Complete)?;
+            
+            Ok(diesel::update(underwriting_runs::table.find(&run.run_id))
+                .set((
+                    underwriting_runs::finalized_at.eq(Utc::now()),
+                    underwriting_runs::finalized_by.eq(&user_id),
+                ))
+                .get_result::<UnderwritingRun>(conn)?)
+        })
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error finalizing underwriting run: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot finalize underwriting run")
+    })?
+    .map_err(|e| match e {
+        DealStatusError::Database(diesel::result::Error::NotFound) => {
+            actix_web::error::ErrorNotFound("Underwriting run not found")
+        }
+        e => deal_status_error(e),
+    })?;
+
+    Ok(HttpResponse::Ok().json(run))
+}
+
+// PUT /api/v1/deals/:deal_id/policy - Select the underwriting policy the deal is checked against
+pub async fn set_deal_policy_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    req: web::Json<SetDealPolicyRequest>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let policy_id = req.into_inner().policy_id;
+    
+    // Verify deal access
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Approver).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let deal = web::block(move || {
+        use crate::data::schema::deals;
+        
+        if let Some(policy_id) = &policy_id {
+            let policy = UnderwritingPolicy::find_for_deal(
+                &mut client,
+                policy_id,
+                &user_id,
+                &deal.user_id,
+                deal.org_id.as_deref(),
+            );
+            match policy {
+                Ok(_) => {}
+                Err(diesel::result::Error::NotFound) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        
+        // The new policy may require different facts, which can move the deal back to review
+        client
+            .transaction::<_, DealStatusError, _>(|conn| {
+                let deal = diesel::update(deals::table.find(&deal.deal_id))
+                    .set((
+                        deals::policy_id.eq(&policy_id),
+                        deals::updated_at.eq(Utc::now()),
+                    ))
+                    .get_result::<Deal>(conn)?;
+                sync_after_review(conn, &deal)
+            })
+            .map(Some)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error selecting underwriting policy: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot select underwriting policy")
+    })?
+    .map_err(deal_status_error)?;
+
+    match deal {
+        Some(deal) => Ok(HttpResponse::Ok().json(DealResponse::from(deal))),
+        None => Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Underwriting policy not found"
+        }))),
+    }
+}
+
+// PUT /api/v1/deals/:deal_id/org - Share the deal with an organization, or stop sharing it
+pub async fn share_deal_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    req: web::Json<ShareDealRequest>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let org_id = req.into_inner().org_id;
+    
+    // Only the deal's creator decides who it is shared with
+    let deal = authorize_deal(&deal_id, &user_id, DealRole::Owner).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let result = web::block(move || {
+        use crate::data::schema::deals;
+        
+        if let Some(org_id) = &org_id {
+            let memberships = OrgMembership::for_user(&mut client, &user_id)?;
+            if !memberships.iter().any(|m| m.org_id == *org_id) {
+                return Ok(Err(format!("You are not a member of organization {}", org_id)));
+            }
+        }
+        
+        diesel::update(deals::table.find(&deal.deal_id))
+            .set((deals::org_id.eq(&org_id), deals::updated_at.eq(Utc::now())))
+            .get_result::<Deal>(&mut client)
+            .map(Ok)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error sharing deal: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot share deal")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        match e {
+            diesel::result::Error::NotFound => {
+                actix_web::error::ErrorNotFound("Deal not found or access denied")
+            }
+            _ => actix_web::error::ErrorInternalServerError("Database error"),
+        }
+    })?;
+
+    match result {
+        Ok(deal) => {
+            let mut response = DealResponse::from(deal);
+            response.role = Some(DealRole::Owner.as_str().to_string());
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(message) => {
+            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": message })))
+        }
+    }
+}
+
+// GET /api/v1/deals/:deal_id/export.xlsx - Download the deal's underwriting workbook
+pub async fn export_deal_workbook_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let export = web::block(move || load_deal_export(&mut client, &deal_id))
+        .await
+        .map_err(|e| {
+            eprintln!("Error exporting deal: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Cannot export deal")
+        })?
+        .map_err(|e| {
+            eprintln!("Database error: {:?}", e);
+            match e {
+                diesel::result::Error::NotFound => {
+                    actix_web::error::ErrorNotFound("Deal not found")
+                }
+                _ => actix_web::error::ErrorInternalServerError("Database error"),
+            }
+        })?;
+
+    let bytes = export.to_workbook().to_bytes().map_err(|e| {
+        eprintln!("Error writing workbook: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Cannot write workbook")
+    })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+        .insert_header((
+            actix_web::http::header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.xlsx\"", export_file_name(&export.deal)),
+        ))
+        .body(bytes))
+}
+
+// GET /api/v1/deals/:deal_id/memo.pdf - Download the deal's credit memo with cropped source evidence
+pub async fn export_credit_memo_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let deal_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    
+    authorize_deal(&deal_id, &user_id, DealRole::Viewer).await?;
+    
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let export = web::block(move || load_deal_export(&mut client, &deal_id))
+        .await
+        .map_err(|e| {
+            eprintln!("Error exporting deal: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Cannot export deal")
+        })?
+        .map_err(|e| {
+            eprintln!("Database error: {:?}", e);
+            match e {
+                diesel::result::Error::NotFound => {
+                    actix_web::error::ErrorNotFound("Deal not found")
+                }
+                _ => actix_web::error::ErrorInternalServerError("Database error"),
+            }
+        })?;
+
+    let evidence = load_evidence_images(&export).await;
+    let blocks = memo_blocks(&export, Utc::now());
+    let bytes = web::block(move || render_memo_pdf(&blocks, &evidence).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| {
+            eprintln!("Error rendering credit memo: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Cannot render credit memo")
+        })?
+        .map_err(|e| {
+            eprintln!("Error rendering credit memo: {}", e);
+            actix_web::error::ErrorInternalServerError("Cannot render credit memo")
+        })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header((
+            actix_web::http::header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.pdf\"", export_file_name(&export.deal)),
+        ))
+        .body(bytes))
+}
@@ -5,5 +5,6 @@ pub mod llm;
 pub mod stripe;
 pub mod task;
 pub mod tasks;
+pub mod underwriting_policy;
 pub mod user;
-// pub mod structured_extraction;
+pub mod structured_extraction;
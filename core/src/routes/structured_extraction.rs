@@ -1,8 +1,11 @@
+use crate::configs::otel_config;
+use crate::models::llm::LlmProcessing;
 use crate::models::structured_extraction::{
     StructuredExtractionRequest, StructuredExtractionResponse,
 };
 use crate::utils::services::structured_extraction::perform_structured_extraction;
 use actix_web::{web, Error, HttpResponse};
+use opentelemetry::global;
 
 /// Extract structured data from a document
 ///
@@ -26,7 +29,14 @@ pub async fn handle_structured_extraction_route(
     req: web::Json<StructuredExtractionRequest>,
 ) -> Result<HttpResponse, Error> {
     let structured_extraction_request = req.into_inner();
-    match perform_structured_extraction(structured_extraction_request).await {
+    let tracer = global::tracer(otel_config::ServiceName::Server.to_string());
+    match perform_structured_extraction(
+        structured_extraction_request,
+        LlmProcessing::default(),
+        &tracer,
+    )
+    .await
+    {
         Ok(response) => Ok(HttpResponse::Ok().json(response)),
         Err(e) => Ok(HttpResponse::InternalServerError().json(format!("Error: {}", e))),
     }
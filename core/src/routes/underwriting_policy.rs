@@ -0,0 +1,317 @@
+use actix_web::{web, HttpResponse, Result};
+use chrono::Utc;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::models::auth::UserInfo;
+use crate::models::deal::{Deal, DealStatus};
+use crate::models::deal_access::OrgMembership;
+use crate::models::fact::FactType;
+use crate::models::underwriting_policy::{
+    CreateUnderwritingPolicyRequest, NewUnderwritingPolicy, PolicyRules, PolicyThresholds,
+    UnderwritingPolicy, UnderwritingPolicyResponse, UpdateUnderwritingPolicy,
+    UpdateUnderwritingPolicyRequest,
+};
+use crate::services::deal_status::{current_status, sync_after_review, DealStatusError};
+use crate::services::underwriting::INPUT_FACT_TYPES;
+use crate::utils::clients::get_diesel_conn;
+
+/// Check a policy's name, thresholds and required fact types, returning the first problem found
+fn validate_policy(
+    name: Option<&str>,
+    thresholds: Option<&PolicyThresholds>,
+    required_fact_types: Option<&[String]>,
+) -> Option<String> {
+    if name.is_some_and(|n| n.trim().is_empty()) {
+        return Some("Policy name cannot be empty".to_string());
+    }
+    if let Some(Err(e)) = thresholds.map(|t| t.validate()) {
+        return Some(e);
+    }
+    let required_fact_types = required_fact_types.unwrap_or_default();
+    if required_fact_types.iter().any(|t| t.trim().is_empty()) {
+        return Some("Required fact types cannot be empty".to_string());
+    }
+    // Facts of an unknown type are never extracted, so deals could never be underwritten
+    if let Some(unknown) = required_fact_types
+        .iter()
+        .find(|t| FactType::from_str(t).is_none() && !INPUT_FACT_TYPES.contains(&t.as_str()))
+    {
+        return Some(format!("Unknown fact type: {}", unknown));
+    }
+    None
+}
+
+/// Re-check the status of every deal using a policy, since its required facts may have changed
+///
+/// Complete deals are left as they are, their underwriting having been accepted under the policy
+/// as it was.
+fn sync_policy_deals(
+    conn: &mut PgConnection,
+    deals_using_policy: &[Deal],
+) -> Result<(), DealStatusError> {
+    use crate::data::schema::deals;
+
+    for deal in deals_using_policy {
+        let deal = deals::table.find(&deal.deal_id).first::<Deal>(conn)?;
+        if current_status(&deal)? == DealStatus::Complete {
+            continue;
+        }
+        sync_after_review(conn, &deal)?;
+    }
+    Ok(())
+}
+
+/// Load the deals that use a policy
+fn policy_deals(conn: &mut PgConnection, policy_id: &str) -> QueryResult<Vec<Deal>> {
+    use crate::data::schema::deals;
+
+    deals::table
+        .filter(deals::policy_id.eq(policy_id))
+        .load::<Deal>(conn)
+}
+
+/// Map a failed policy change to a response, deals that cannot move back to review being conflicts
+fn policy_change_error(e: DealStatusError) -> actix_web::Error {
+    match e {
+        DealStatusError::Database(diesel::result::Error::NotFound) => {
+            actix_web::error::ErrorNotFound("Underwriting policy not found")
+        }
+        DealStatusError::Database(e) => {
+            eprintln!("Database error: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Database error")
+        }
+        e => actix_web::error::ErrorConflict(e.to_string()),
+    }
+}
+
+// POST /api/v1/underwriting-policies - Create an underwriting policy
+pub async fn create_underwriting_policy_route(
+    user_info: web::ReqData<UserInfo>,
+    req: web::Json<CreateUnderwritingPolicyRequest>,
+) -> Result<HttpResponse> {
+    let user_id = user_info.user_id.clone();
+    let req = req.into_inner();
+    let required_fact_types = req
+        .required_fact_types
+        .unwrap_or_else(|| PolicyRules::default().required_fact_types);
+    if let Some(error) = validate_policy(
+        Some(&req.name),
+        Some(&req.thresholds),
+        Some(&required_fact_types),
+    ) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": error })));
+    }
+
+    let new_policy = NewUnderwritingPolicy {
+        policy_id: Uuid::new_v4().to_string(),
+        user_id,
+        name: req.name,
+        thresholds: serde_json::to_value(&req.thresholds)?,
+        required_fact_types,
+        severities: serde_json::to_value(&req.severities)?,
+        org_id: req.org_id,
+    };
+
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let result = web::block(move || {
+        use crate::data::schema::underwriting_policies;
+
+        // Policies can only be shared with an organization the creator belongs to
+        if let Some(org_id) = &new_policy.org_id {
+            let memberships = OrgMembership::for_user(&mut client, &new_policy.user_id)?;
+            if !memberships.iter().any(|m| m.org_id == *org_id) {
+                return Ok(Err(format!("You are not a member of organization {}", org_id)));
+            }
+        }
+
+        diesel::insert_into(underwriting_policies::table)
+            .values(&new_policy)
+            .get_result::<UnderwritingPolicy>(&mut client)
+            .map(Ok)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error creating underwriting policy: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Failed to create underwriting policy")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    match result {
+        Ok(policy) => Ok(HttpResponse::Ok().json(UnderwritingPolicyResponse::from(policy))),
+        Err(message) => {
+            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": message })))
+        }
+    }
+}
+
+// GET /api/v1/underwriting-policies - List the user's underwriting policies and those of their
+// organizations
+pub async fn get_underwriting_policies_route(
+    user_info: web::ReqData<UserInfo>,
+) -> Result<HttpResponse> {
+    let user_id = user_info.user_id.clone();
+
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let policies = web::block(move || {
+        use crate::data::schema::underwriting_policies;
+
+        let org_ids: Vec<String> = OrgMembership::for_user(&mut client, &user_id)?
+            .into_iter()
+            .map(|membership| membership.org_id)
+            .collect();
+        underwriting_policies::table
+            .filter(
+                underwriting_policies::user_id
+                    .eq(&user_id)
+                    .or(underwriting_policies::org_id.eq_any(&org_ids)),
+            )
+            .order(underwriting_policies::created_at.asc())
+            .load::<UnderwritingPolicy>(&mut client)
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error fetching underwriting policies: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Failed to fetch underwriting policies")
+    })?
+    .map_err(|e| {
+        eprintln!("Database error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database error")
+    })?;
+
+    let response: Vec<UnderwritingPolicyResponse> = policies.into_iter().map(Into::into).collect();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// GET /api/v1/underwriting-policies/:policy_id - Get an underwriting policy
+pub async fn get_underwriting_policy_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let policy_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let policy = web::block(move || UnderwritingPolicy::find(&mut client, &policy_id, &user_id))
+        .await
+        .map_err(|e| {
+            eprintln!("Error fetching underwriting policy: {:?}", e);
+            actix_web::error::ErrorInternalServerError("Failed to fetch underwriting policy")
+        })?
+        .map_err(|e| match e {
+            diesel::result::Error::NotFound => {
+                actix_web::error::ErrorNotFound("Underwriting policy not found")
+            }
+            e => {
+                eprintln!("Database error: {:?}", e);
+                actix_web::error::ErrorInternalServerError("Database error")
+            }
+        })?;
+
+    Ok(HttpResponse::Ok().json(UnderwritingPolicyResponse::from(policy)))
+}
+
+// PATCH /api/v1/underwriting-policies/:policy_id - Update an underwriting policy
+pub async fn update_underwriting_policy_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+    req: web::Json<UpdateUnderwritingPolicyRequest>,
+) -> Result<HttpResponse> {
+    let policy_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+    let req = req.into_inner();
+    if let Some(error) = validate_policy(
+        req.name.as_deref(),
+        req.thresholds.as_ref(),
+        req.required_fact_types.as_deref(),
+    ) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": error })));
+    }
+
+    let changes = UpdateUnderwritingPolicy {
+        name: req.name,
+        thresholds: req.thresholds.as_ref().map(serde_json::to_value).transpose()?,
+        required_fact_types: req.required_fact_types,
+        severities: req.severities.as_ref().map(serde_json::to_value).transpose()?,
+        updated_at: Some(Utc::now()),
+    };
+
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    let policy = web::block(move || {
+        use crate::data::schema::underwriting_policies;
+
+        client.transaction::<_, DealStatusError, _>(|conn| {
+            let policy = UnderwritingPolicy::find(conn, &policy_id, &user_id)?;
+            let policy = diesel::update(underwriting_policies::table.find(&policy.policy_id))
+                .set(&changes)
+                .get_result::<UnderwritingPolicy>(conn)?;
+
+            let deals_using_policy = policy_deals(conn, &policy.policy_id)?;
+            sync_policy_deals(conn, &deals_using_policy)?;
+            Ok(policy)
+        })
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error updating underwriting policy: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Failed to update underwriting policy")
+    })?
+    .map_err(policy_change_error)?;
+
+    Ok(HttpResponse::Ok().json(UnderwritingPolicyResponse::from(policy)))
+}
+
+// DELETE /api/v1/underwriting-policies/:policy_id - Delete an underwriting policy
+//
+// Deals using the policy fall back to the standard thresholds.
+pub async fn delete_underwriting_policy_route(
+    user_info: web::ReqData<UserInfo>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let policy_id = path.into_inner();
+    let user_id = user_info.user_id.clone();
+
+    let mut client = get_diesel_conn().map_err(|e| {
+        eprintln!("Database connection error: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Database connection failed")
+    })?;
+
+    web::block(move || {
+        use crate::data::schema::underwriting_policies;
+
+        client.transaction::<_, DealStatusError, _>(|conn| {
+            let policy = UnderwritingPolicy::find(conn, &policy_id, &user_id)?;
+            let deals_using_policy = policy_deals(conn, &policy.policy_id)?;
+            diesel::delete(underwriting_policies::table.find(&policy.policy_id)).execute(conn)?;
+
+            sync_policy_deals(conn, &deals_using_policy)
+        })
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Error deleting underwriting policy: {:?}", e);
+        actix_web::error::ErrorInternalServerError("Failed to delete underwriting policy")
+    })?
+    .map_err(policy_change_error)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
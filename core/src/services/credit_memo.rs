@@ -0,0 +1,477 @@
+use crate::configs::pdfium_config::Config as PdfiumConfig;
+use crate::models::fact::SourceCitation;
+use crate::models::output::BoundingBox;
+use crate::models::task::Task;
+use crate::services::deal_export::DealExport;
+use crate::utils::services::images::crop_image;
+use crate::utils::storage::services::download_to_tempfile;
+use chrono::{DateTime, Utc};
+use image::{DynamicImage, ImageReader};
+use pdfium_render::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use tempfile::NamedTempFile;
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+const TITLE_SIZE: f32 = 18.0;
+const HEADING_SIZE: f32 = 13.0;
+const BODY_SIZE: f32 = 10.0;
+const LINE_SPACING: f32 = 1.4;
+/// Tallest a source crop is drawn, in points
+const MAX_EVIDENCE_HEIGHT: f32 = 140.0;
+
+/// A piece of the credit memo, laid out top to bottom
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoBlock {
+    Title(String),
+    Heading(String),
+    Paragraph(String),
+    Metric { label: String, value: String },
+    Bullet(String),
+    /// A fact followed by the crop of its source region, if one could be made
+    Evidence {
+        fact_id: String,
+        label: String,
+        value: String,
+        citation: String,
+    },
+}
+
+/// Lay out the memo of a deal: underwriting metrics, warnings, recommendations and the evidence
+/// for every approved fact
+pub fn memo_blocks(export: &DealExport, generated_at: DateTime<Utc>) -> Vec<MemoBlock> {
+    let mut blocks = vec![
+        MemoBlock::Title(format!("Credit Memo: {}", export.deal.deal_name)),
+        MemoBlock::Paragraph(format!(
+            "Deal {} - status {} - generated {}",
+            export.deal.deal_id,
+            export.deal.status,
+            generated_at.format("%Y-%m-%d %H:%M UTC")
+        )),
+        MemoBlock::Heading("Summary Metrics".to_string()),
+    ];
+
+    let metric = |label: &str, value: Option<String>| MemoBlock::Metric {
+        label: label.to_string(),
+        value: value.unwrap_or_else(|| "n/a".to_string()),
+    };
+    match &export.underwriting {
+        Some(result) => {
+            blocks.push(metric("NOI", Some(format!("${:.2}", result.noi))));
+            blocks.push(metric("DSCR", result.dscr.map(|v| format!("{:.2}x", v))));
+            blocks.push(metric(
+                "Cash Flow After Debt",
+                result.cash_flow_after_debt.map(|v| format!("${:.2}", v)),
+            ));
+            blocks.push(metric("Cap Rate", result.cap_rate.map(|v| format!("{:.2}%", v))));
+            blocks.push(metric("LTV", result.ltv.map(|v| format!("{:.2}%", v))));
+            blocks.push(metric(
+                "Gross Rent Multiplier",
+                result.gross_rent_multiplier.map(|v| format!("{:.2}", v)),
+            ));
+            blocks.push(metric("Debt Yield", result.debt_yield.map(|v| format!("{:.2}%", v))));
+            blocks.push(metric(
+                "Expense Ratio",
+                result.expense_ratio.map(|v| format!("{:.2}%", v)),
+            ));
+            blocks.push(metric(
+                "Break-even Occupancy",
+                result.break_even_occupancy.map(|v| format!("{:.2}%", v)),
+            ));
+            blocks.push(metric(
+                "NOI per Unit",
+                result.noi_per_unit.map(|v| format!("${:.2}", v)),
+            ));
+            blocks.push(metric(
+                "Max Loan Amount",
+                result.loan_sizing.as_ref().map(|sizing| {
+                    format!("${:.2} ({} constrained)", sizing.max_loan_amount, sizing.binding_constraint)
+                }),
+            ));
+        }
+        None => blocks.push(MemoBlock::Paragraph(format!(
+            "Underwriting not calculated: {}",
+            export
+                .underwriting_note
+                .as_deref()
+                .unwrap_or("no approved facts")
+        ))),
+    }
+
+    blocks.push(MemoBlock::Heading("Warnings".to_string()));
+    let warnings = export
+        .underwriting
+        .as_ref()
+        .map(|result| result.warnings.as_slice())
+        .unwrap_or_default();
+    if warnings.is_empty() {
+        blocks.push(MemoBlock::Paragraph("No warnings.".to_string()));
+    }
+    blocks.extend(warnings.iter().map(|w| MemoBlock::Bullet(w.clone())));
+
+    blocks.push(MemoBlock::Heading("Recommendations".to_string()));
+    if export.recommendations.is_empty() {
+        blocks.push(MemoBlock::Paragraph("No recommendations.".to_string()));
+    }
+    for recommendation in &export.recommendations {
+        let mut text = format!(
+            "[{:?}] {}: {}",
+            recommendation.severity, recommendation.category, recommendation.message
+        );
+        if let Some(action) = &recommendation.recommended_action {
+            text.push_str(&format!(" Action: {}", action));
+        }
+        blocks.push(MemoBlock::Bullet(text));
+    }
+
+    blocks.push(MemoBlock::Heading("Facts and Evidence".to_string()));
+    if export.facts.is_empty() {
+        blocks.push(MemoBlock::Paragraph("No approved facts.".to_string()));
+    }
+    for fact in &export.facts {
+        let citation = serde_json::from_value::<SourceCitation>(fact.source_citation.clone())
+            .map(|c| format!("{}, page {}", c.document, c.page))
+            .unwrap_or_else(|_| "No citation".to_string());
+        let value = match &fact.unit {
+            Some(unit) => format!("{} {}", fact.value, unit),
+            None => fact.value.clone(),
+        };
+        blocks.push(MemoBlock::Evidence {
+            fact_id: fact.fact_id.clone(),
+            label: fact.label.clone(),
+            value,
+            citation,
+        });
+    }
+    blocks
+}
+
+/// Crop the source region of every approved fact from its document's page images, keyed by
+/// fact id
+///
+/// Facts without a bounding box, or whose page image cannot be loaded, get no image.
+pub async fn load_evidence_images(export: &DealExport) -> HashMap<String, DynamicImage> {
+    let mut tasks: HashMap<String, Option<Task>> = HashMap::new();
+    let mut pages: HashMap<(String, i32), Option<NamedTempFile>> = HashMap::new();
+    let mut images = HashMap::new();
+
+    for fact in &export.facts {
+        let Ok(citation) = serde_json::from_value::<SourceCitation>(fact.source_citation.clone())
+        else {
+            continue;
+        };
+        let (Some(bbox), true) = (citation.bbox, citation.page >= 1) else {
+            continue;
+        };
+        let Some(task_id) = export
+            .documents
+            .iter()
+            .find(|d| d.document_id == fact.document_id)
+            .and_then(|d| d.task_id.clone())
+        else {
+            continue;
+        };
+
+        if !tasks.contains_key(&task_id) {
+            let task = Task::get(&task_id, &export.deal.user_id)
+                .await
+                .map_err(|e| println!("Failed to load task {}: {}", task_id, e))
+                .ok();
+            tasks.insert(task_id.clone(), task);
+        }
+        let Some(task) = tasks.get(&task_id).and_then(Option::as_ref) else {
+            continue;
+        };
+
+        let page_key = (task_id.clone(), citation.page);
+        if !pages.contains_key(&page_key) {
+            // Page images are stored zero-based, citations count pages from one
+            let location = format!(
+                "{}/pages/page_{}.jpg",
+                task.image_folder_location,
+                citation.page - 1
+            );
+            let page_image = download_to_tempfile(&location, None, "image/jpeg")
+                .await
+                .map_err(|e| println!("Failed to download page image {}: {}", location, e))
+                .ok();
+            pages.insert(page_key.clone(), page_image);
+        }
+        let Some(page_image) = pages.get(&page_key).and_then(Option::as_ref) else {
+            continue;
+        };
+
+        let region = BoundingBox {
+            left: bbox.left as f32,
+            top: bbox.top as f32,
+            width: bbox.width as f32,
+            height: bbox.height as f32,
+        };
+        let cropped = crop_image(page_image, &region).and_then(|file| {
+            Ok(ImageReader::open(file.path())?
+                .with_guessed_format()?
+                .decode()?)
+        });
+        match cropped {
+            Ok(image) => {
+                images.insert(fact.fact_id.clone(), image);
+            }
+            Err(e) => println!("Failed to crop evidence of fact {}: {}", fact.fact_id, e),
+        }
+    }
+    images
+}
+
+/// Split text into lines that fit the given width, approximating Helvetica's average glyph width
+pub fn wrap_text(text: &str, font_size: f32, width: f32) -> Vec<String> {
+    let max_chars = ((width / (font_size * 0.5)).floor() as usize).max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        // Break words longer than a line
+        while word.len() > max_chars {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(word.drain(..max_chars).collect());
+        }
+        let word: String = word.into_iter().collect();
+        if line.is_empty() {
+            line = word;
+        } else if line.chars().count() + 1 + word.chars().count() <= max_chars {
+            line.push(' ');
+            line.push_str(&word);
+        } else {
+            lines.push(std::mem::replace(&mut line, word));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Render the memo to an A4 PDF, drawing each fact's evidence image below it
+pub fn render_memo_pdf(
+    blocks: &[MemoBlock],
+    evidence: &HashMap<String, DynamicImage>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let pdfium = PdfiumConfig::from_env()?.get_pdfium()?;
+    let mut document = pdfium.create_new_pdf()?;
+    let regular = document.fonts_mut().helvetica();
+    let bold = document.fonts_mut().helvetica_bold();
+    let mut page = document
+        .pages_mut()
+        .create_page_at_end(PdfPagePaperSize::a4())?;
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    for block in blocks {
+        let (lines, font, size, indent, space_before) = match block {
+            MemoBlock::Title(text) => (
+                wrap_text(text, TITLE_SIZE, CONTENT_WIDTH),
+                bold,
+                TITLE_SIZE,
+                0.0,
+                0.0,
+            ),
+            MemoBlock::Heading(text) => (
+                wrap_text(text, HEADING_SIZE, CONTENT_WIDTH),
+                bold,
+                HEADING_SIZE,
+                0.0,
+                HEADING_SIZE,
+            ),
+            MemoBlock::Paragraph(text) => (
+                wrap_text(text, BODY_SIZE, CONTENT_WIDTH),
+                regular,
+                BODY_SIZE,
+                0.0,
+                0.0,
+            ),
+            MemoBlock::Metric { label, value } => (
+                vec![format!("{}: {}", label, value)],
+                regular,
+                BODY_SIZE,
+                0.0,
+                0.0,
+            ),
+            MemoBlock::Bullet(text) => {
+                let mut lines = wrap_text(text, BODY_SIZE, CONTENT_WIDTH - 12.0);
+                if let Some(first) = lines.first_mut() {
+                    *first = format!("- {}", first);
+                }
+                (lines, regular, BODY_SIZE, 12.0, 0.0)
+            }
+            MemoBlock::Evidence {
+                label,
+                value,
+                citation,
+                ..
+            } => (
+                wrap_text(
+                    &format!("{}: {} ({})", label, value, citation),
+                    BODY_SIZE,
+                    CONTENT_WIDTH,
+                ),
+                bold,
+                BODY_SIZE,
+                0.0,
+                BODY_SIZE * 0.5,
+            ),
+        };
+
+        y -= space_before;
+        let line_height = size * LINE_SPACING;
+        for (idx, line) in lines.iter().enumerate() {
+            ensure_space(&mut document, &mut page, &mut y, line_height)?;
+            y -= line_height;
+            // Continuation lines of a bullet are indented past the dash
+            let x = MARGIN + if idx == 0 { 0.0 } else { indent };
+            page.objects_mut().create_text_object(
+                PdfPoints::new(x),
+                PdfPoints::new(y),
+                line,
+                font,
+                PdfPoints::new(size),
+            )?;
+        }
+
+        if let MemoBlock::Evidence { fact_id, .. } = block {
+            match evidence.get(fact_id) {
+                Some(image) if image.width() > 0 && image.height() > 0 => {
+                    let scale = (CONTENT_WIDTH / image.width() as f32)
+                        .min(MAX_EVIDENCE_HEIGHT / image.height() as f32);
+                    let (width, height) =
+                        (image.width() as f32 * scale, image.height() as f32 * scale);
+                    ensure_space(&mut document, &mut page, &mut y, height + 4.0)?;
+                    y -= height + 4.0;
+                    page.objects_mut().create_image_object(
+                        PdfPoints::new(MARGIN),
+                        PdfPoints::new(y),
+                        image,
+                        Some(PdfPoints::new(width)),
+                        Some(PdfPoints::new(height)),
+                    )?;
+                }
+                _ => {
+                    let line_height = BODY_SIZE * LINE_SPACING;
+                    ensure_space(&mut document, &mut page, &mut y, line_height)?;
+                    y -= line_height;
+                    page.objects_mut().create_text_object(
+                        PdfPoints::new(MARGIN),
+                        PdfPoints::new(y),
+                        "No source image available",
+                        regular,
+                        PdfPoints::new(BODY_SIZE),
+                    )?;
+                }
+            }
+        }
+    }
+
+    drop(page);
+    Ok(document.save_to_bytes()?)
+}
+
+/// Start a new page when less than `needed` points are left above the bottom margin
+fn ensure_space<'a>(
+    document: &mut PdfDocument<'a>,
+    page: &mut PdfPage<'a>,
+    y: &mut f32,
+    needed: f32,
+) -> Result<(), PdfiumError> {
+    if *y - needed < MARGIN {
+        *page = document
+            .pages_mut()
+            .create_page_at_end(PdfPagePaperSize::a4())?;
+        *y = PAGE_HEIGHT - MARGIN;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::deal::Deal;
+    use crate::models::fact::Fact;
+    use crate::models::underwriting_policy::PolicyRules;
+
+    #[test]
+    fn test_wrap_text() {
+        // 10pt text in 100pt fits 20 characters per line
+        let lines = wrap_text("Debt service coverage is below the threshold", 10.0, 100.0);
+        assert_eq!(lines, vec!["Debt service", "coverage is below", "the threshold"]);
+        assert!(lines.iter().all(|l| l.len() <= 20));
+
+        let lines = wrap_text("see 0123456789012345678901234", 10.0, 100.0);
+        assert_eq!(lines, vec!["see", "01234567890123456789", "01234"]);
+        assert!(wrap_text("   ", 10.0, 100.0).is_empty());
+    }
+
+    #[test]
+    fn test_memo_blocks() {
+        let deal = Deal {
+            deal_id: "deal-1".to_string(),
+            user_id: "user-1".to_string(),
+            deal_name: "Maple Court".to_string(),
+            status: "ready_for_underwriting".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+            policy_id: None,
+            org_id: None,
+        };
+        let fact = |fact_id: &str, fact_type: &str, value: &str| Fact {
+            unit: Some("USD".to_string()),
+            source_citation: serde_json::json!({
+                "document": "t12.pdf",
+                "page": 3,
+                "bbox": { "left": 10.0, "top": 20.0, "width": 200.0, "height": 30.0 }
+            }),
+            ..Fact::for_test(fact_id, fact_type, value)
+        };
+        let facts = vec![
+            fact("f-rent", "collected_rent", "120000"),
+            fact("f-opex", "operating_expenses", "45000"),
+        ];
+        let export = DealExport::new(
+            deal,
+            vec![],
+            vec![],
+            &facts,
+            &[],
+            vec![],
+            vec![],
+            &PolicyRules::default(),
+        );
+
+        let blocks = memo_blocks(&export, Utc::now());
+        assert_eq!(
+            blocks[0],
+            MemoBlock::Title("Credit Memo: Maple Court".to_string())
+        );
+        assert!(blocks.contains(&MemoBlock::Metric {
+            label: "NOI".to_string(),
+            value: "$75000.00".to_string(),
+        }));
+        assert!(blocks.contains(&MemoBlock::Metric {
+            label: "DSCR".to_string(),
+            value: "n/a".to_string(),
+        }));
+        assert!(blocks.contains(&MemoBlock::Evidence {
+            fact_id: "f-rent".to_string(),
+            label: "collected_rent".to_string(),
+            value: "120000 USD".to_string(),
+            citation: "t12.pdf, page 3".to_string(),
+        }));
+        let warnings_at = blocks
+            .iter()
+            .position(|b| *b == MemoBlock::Heading("Warnings".to_string()))
+            .unwrap();
+        assert!(matches!(blocks[warnings_at + 1], MemoBlock::Bullet(_)));
+    }
+}
@@ -1,15 +1,28 @@
 use crate::models::fact::Fact;
+use crate::models::underwriting_policy::PolicyRules;
 use crate::services::underwriting::UnderwritingResult;
+use chrono::{Duration, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
 pub enum Severity {
     Info,
     Warning,
     Critical,
 }
 
+impl Severity {
+    /// Prefix of underwriting warnings reported at this severity
+    pub fn warning_prefix(&self) -> &str {
+        match self {
+            Severity::Info => "Note",
+            Severity::Warning => "Warning",
+            Severity::Critical => "Critical",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AgentRecommendation {
     pub severity: Severity,
@@ -19,131 +32,172 @@ pub struct AgentRecommendation {
     pub details: Option<String>,
 }
 
-/// Analyze a deal and provide recommendations
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RecommendationsBySeverity {
+    pub critical: Vec<AgentRecommendation>,
+    pub warning: Vec<AgentRecommendation>,
+    pub info: Vec<AgentRecommendation>,
+}
+
+/// Group recommendations by severity, keeping their original order within each group
+pub fn group_by_severity(recommendations: Vec<AgentRecommendation>) -> RecommendationsBySeverity {
+    let mut grouped = RecommendationsBySeverity::default();
+    for recommendation in recommendations {
+        match recommendation.severity {
+            Severity::Critical => grouped.critical.push(recommendation),
+            Severity::Warning => grouped.warning.push(recommendation),
+            Severity::Info => grouped.info.push(recommendation),
+        }
+    }
+    grouped
+}
+
+/// Analyze a deal against its underwriting policy and provide recommendations
 pub fn analyze_deal(
     facts: &[Fact],
     underwriting: Option<&UnderwritingResult>,
+    rules: &PolicyRules,
 ) -> Vec<AgentRecommendation> {
     let mut recommendations = Vec::new();
 
     // Check for missing critical documents
-    recommendations.extend(check_missing_documents(facts));
+    recommendations.extend(check_missing_documents(facts, rules));
 
     // Analyze underwriting metrics if available
     if let Some(uw) = underwriting {
-        recommendations.extend(analyze_underwriting_metrics(uw));
-        recommendations.extend(analyze_leverage_ratios(uw));
+        recommendations.extend(analyze_underwriting_metrics(uw, rules));
+        recommendations.extend(analyze_leverage_ratios(uw, rules));
+        recommendations.extend(analyze_supporting_documents(uw, rules));
     }
 
+    // Check insurance and leases expiring soon
+    recommendations.extend(check_expirations(facts, rules, Utc::now().date_naive()));
+
     // Check fact quality and completeness
-    recommendations.extend(check_fact_quality(facts));
+    recommendations.extend(check_fact_quality(facts, rules));
 
     recommendations
 }
 
-fn check_missing_documents(facts: &[Fact]) -> Vec<AgentRecommendation> {
-    let mut recommendations = Vec::new();
-
-    // Check for critical fact types
-    let has_collected_rent = facts.iter().any(|f| f.fact_type == "collected_rent");
-    let has_operating_expenses = facts.iter().any(|f| f.fact_type == "operating_expenses");
-    let has_mortgage = facts.iter().any(|f| {
-        f.fact_type == "mortgage_balance" || f.fact_type == "debt_service"
-    });
-    let has_property_value = facts.iter().any(|f| f.fact_type == "property_value");
-    let has_unit_count = facts.iter().any(|f| f.fact_type == "unit_count");
-    let has_occupancy = facts.iter().any(|f| f.fact_type == "occupancy_rate");
-
-    if !has_collected_rent {
-        recommendations.push(AgentRecommendation {
-            severity: Severity::Critical,
-            category: "Missing Data".to_string(),
-            message: "No rental income data found".to_string(),
-            recommended_action: Some(
-                "Upload rent roll or P&L statement showing collected rent".to_string(),
-            ),
-            details: Some(
-                "Collected rent is essential for calculating NOI and evaluating property performance"
-                    .to_string(),
-            ),
-        });
-    }
-
-    if !has_operating_expenses {
-        recommendations.push(AgentRecommendation {
-            severity: Severity::Critical,
-            category: "Missing Data".to_string(),
-            message: "No operating expenses data found".to_string(),
-            recommended_action: Some("Upload P&L statement with operating expenses breakdown".to_string()),
-            details: Some(
-                "Operating expenses are required to calculate NOI and understand property profitability"
-                    .to_string(),
-            ),
-        });
-    }
-
-    if !has_mortgage {
-        recommendations.push(AgentRecommendation {
-            severity: Severity::Warning,
-            category: "Missing Data".to_string(),
-            message: "No mortgage or debt service information found".to_string(),
-            recommended_action: Some(
-                "Upload mortgage statement to enable DSCR calculation".to_string(),
-            ),
-            details: Some(
-                "Debt service coverage ratio (DSCR) cannot be calculated without mortgage information"
-                    .to_string(),
-            ),
-        });
-    }
+/// Data the agent looks for, with the severity reported when it is missing and not required by
+/// the policy
+struct ExpectedData {
+    fact_types: &'static [&'static str],
+    severity: Severity,
+    message: &'static str,
+    recommended_action: &'static str,
+    details: &'static str,
+}
 
-    if !has_property_value {
-        recommendations.push(AgentRecommendation {
-            severity: Severity::Info,
-            category: "Missing Data".to_string(),
-            message: "No property value found".to_string(),
-            recommended_action: Some("Upload tax assessment or appraisal document".to_string()),
-            details: Some("Property value enables calculation of Cap Rate and LTV".to_string()),
-        });
-    }
+const EXPECTED_DATA: &[ExpectedData] = &[
+    ExpectedData {
+        fact_types: &["collected_rent"],
+        severity: Severity::Critical,
+        message: "No rental income data found",
+        recommended_action: "Upload rent roll or P&L statement showing collected rent",
+        details: "Collected rent is essential for calculating NOI and evaluating property performance",
+    },
+    ExpectedData {
+        fact_types: &["operating_expenses"],
+        severity: Severity::Critical,
+        message: "No operating expenses data found",
+        recommended_action: "Upload P&L statement with operating expenses breakdown",
+        details: "Operating expenses are required to calculate NOI and understand property profitability",
+    },
+    ExpectedData {
+        fact_types: &["mortgage_balance", "debt_service"],
+        severity: Severity::Warning,
+        message: "No mortgage or debt service information found",
+        recommended_action: "Upload mortgage statement to enable DSCR calculation",
+        details: "Debt service coverage ratio (DSCR) cannot be calculated without mortgage information",
+    },
+    ExpectedData {
+        fact_types: &["property_value", "appraised_value"],
+        severity: Severity::Info,
+        message: "No property value found",
+        recommended_action: "Upload tax assessment or appraisal document",
+        details: "Property value enables calculation of Cap Rate and LTV",
+    },
+    ExpectedData {
+        fact_types: &["unit_count"],
+        severity: Severity::Info,
+        message: "No unit count information found",
+        recommended_action: "Ensure rent roll includes total unit count",
+        details: "Unit count helps assess property size and per-unit economics",
+    },
+    ExpectedData {
+        fact_types: &["occupancy_rate"],
+        severity: Severity::Info,
+        message: "No occupancy rate found",
+        recommended_action: "Include occupancy percentage in rent roll",
+        details: "Occupancy rate is important for understanding property performance and risk",
+    },
+];
+
+fn check_missing_documents(facts: &[Fact], rules: &PolicyRules) -> Vec<AgentRecommendation> {
+    let mut recommendations = Vec::new();
+    let has_fact = |fact_type: &str| facts.iter().any(|f| f.fact_type == fact_type);
+    let is_required = |fact_type: &str| rules.required_fact_types.iter().any(|t| t == fact_type);
 
-    if !has_unit_count {
+    for expected in EXPECTED_DATA {
+        if expected.fact_types.iter().any(|t| has_fact(t)) {
+            continue;
+        }
+        // Data the policy requires is reported at the policy's severity
+        let severity = if expected.fact_types.iter().any(|t| is_required(t)) {
+            rules.severities.missing_required_fact
+        } else {
+            expected.severity
+        };
         recommendations.push(AgentRecommendation {
-            severity: Severity::Info,
+            severity,
             category: "Missing Data".to_string(),
-            message: "No unit count information found".to_string(),
-            recommended_action: Some("Ensure rent roll includes total unit count".to_string()),
-            details: Some("Unit count helps assess property size and per-unit economics".to_string()),
+            message: expected.message.to_string(),
+            recommended_action: Some(expected.recommended_action.to_string()),
+            details: Some(expected.details.to_string()),
         });
     }
 
-    if !has_occupancy {
+    // Required fact types the agent has no specific guidance for
+    for fact_type in &rules.required_fact_types {
+        let known = EXPECTED_DATA
+            .iter()
+            .any(|e| e.fact_types.contains(&fact_type.as_str()));
+        if known || has_fact(fact_type) {
+            continue;
+        }
         recommendations.push(AgentRecommendation {
-            severity: Severity::Info,
+            severity: rules.severities.missing_required_fact,
             category: "Missing Data".to_string(),
-            message: "No occupancy rate found".to_string(),
-            recommended_action: Some("Include occupancy percentage in rent roll".to_string()),
-            details: Some(
-                "Occupancy rate is important for understanding property performance and risk".to_string(),
-            ),
+            message: format!("No {} found", fact_type.replace('_', " ")),
+            recommended_action: Some(format!(
+                "Upload a document showing {}",
+                fact_type.replace('_', " ")
+            )),
+            details: Some("The underwriting policy requires this fact".to_string()),
         });
     }
 
     recommendations
 }
 
-fn analyze_underwriting_metrics(uw: &UnderwritingResult) -> Vec<AgentRecommendation> {
+fn analyze_underwriting_metrics(
+    uw: &UnderwritingResult,
+    rules: &PolicyRules,
+) -> Vec<AgentRecommendation> {
     let mut recommendations = Vec::new();
+    let thresholds = &rules.thresholds;
+    let severities = &rules.severities;
 
     // Analyze DSCR
     if let Some(dscr) = uw.dscr {
-        if dscr < 1.0 {
+        if dscr < thresholds.critical_dscr {
             recommendations.push(AgentRecommendation {
-                severity: Severity::Critical,
+                severity: severities.dscr_below_critical,
                 category: "Debt Coverage".to_string(),
                 message: format!(
-                    "DSCR of {:.2} is below 1.0 - property cannot cover debt service",
-                    dscr
+                    "DSCR of {:.2} is below critical {:.2} threshold",
+                    dscr, thresholds.critical_dscr
                 ),
                 recommended_action: Some(
                     "Consider higher coupon rate, lower leverage, or refinancing options".to_string(),
@@ -153,20 +207,23 @@ fn analyze_underwriting_metrics(uw: &UnderwritingResult) -> Vec<AgentRecommendat
                         .to_string(),
                 ),
             });
-        } else if dscr < 1.25 {
+        } else if dscr < thresholds.min_dscr {
             recommendations.push(AgentRecommendation {
-                severity: Severity::Warning,
+                severity: severities.dscr_below_minimum,
                 category: "Debt Coverage".to_string(),
-                message: format!("DSCR of {:.2} is below recommended 1.25 threshold", dscr),
+                message: format!(
+                    "DSCR of {:.2} is below required {:.2} threshold",
+                    dscr, thresholds.min_dscr
+                ),
                 recommended_action: Some(
                     "Consider increasing down payment or negotiating better loan terms".to_string(),
                 ),
-                details: Some(
-                    "Most lenders require DSCR of 1.25 or higher for comfortable debt service coverage"
-                        .to_string(),
-                ),
+                details: Some(format!(
+                    "The lending program requires DSCR of {:.2} or higher for comfortable debt service coverage",
+                    thresholds.min_dscr
+                )),
             });
-        } else if dscr >= 1.5 {
+        } else if dscr >= thresholds.strong_dscr {
             recommendations.push(AgentRecommendation {
                 severity: Severity::Info,
                 category: "Debt Coverage".to_string(),
@@ -183,7 +240,7 @@ fn analyze_underwriting_metrics(uw: &UnderwritingResult) -> Vec<AgentRecommendat
     // Analyze NOI
     if uw.noi < 0.0 {
         recommendations.push(AgentRecommendation {
-            severity: Severity::Critical,
+            severity: severities.negative_noi,
             category: "Operating Performance".to_string(),
             message: format!("Negative NOI of ${:.2} indicates operating loss", uw.noi.abs()),
             recommended_action: Some(
@@ -199,7 +256,7 @@ fn analyze_underwriting_metrics(uw: &UnderwritingResult) -> Vec<AgentRecommendat
     if let Some(cf) = uw.cash_flow_after_debt {
         if cf < 0.0 {
             recommendations.push(AgentRecommendation {
-                severity: Severity::Critical,
+                severity: severities.negative_cash_flow,
                 category: "Cash Flow".to_string(),
                 message: format!("Negative cash flow of ${:.2} per year", cf.abs()),
                 recommended_action: Some(
@@ -211,9 +268,9 @@ fn analyze_underwriting_metrics(uw: &UnderwritingResult) -> Vec<AgentRecommendat
                         .to_string(),
                 ),
             });
-        } else if cf < 5000.0 {
+        } else if cf < thresholds.min_cash_flow {
             recommendations.push(AgentRecommendation {
-                severity: Severity::Warning,
+                severity: severities.low_cash_flow,
                 category: "Cash Flow".to_string(),
                 message: "Low cash flow provides minimal safety margin".to_string(),
                 recommended_action: Some(
@@ -228,28 +285,29 @@ fn analyze_underwriting_metrics(uw: &UnderwritingResult) -> Vec<AgentRecommendat
 
     // Analyze Cap Rate
     if let Some(cap_rate) = uw.cap_rate {
-        if cap_rate < 3.0 {
+        if cap_rate < thresholds.min_cap_rate {
             recommendations.push(AgentRecommendation {
-                severity: Severity::Warning,
+                severity: severities.cap_rate_out_of_range,
                 category: "Returns".to_string(),
                 message: format!("Low cap rate of {:.2}% suggests limited income potential", cap_rate),
                 recommended_action: Some(
                     "Verify property value is accurate and consider if appreciation potential justifies low yield"
                         .to_string(),
                 ),
-                details: Some("Cap rates below 3% are typically seen in premium locations with strong appreciation potential".to_string()),
+                details: Some(format!("Cap rates below {:.2}% are typically seen in premium locations with strong appreciation potential", thresholds.min_cap_rate)),
             });
-        } else if cap_rate > 10.0 {
+        } else if cap_rate > thresholds.max_cap_rate {
             recommendations.push(AgentRecommendation {
-                severity: Severity::Warning,
+                severity: severities.cap_rate_out_of_range,
                 category: "Returns".to_string(),
                 message: format!("High cap rate of {:.2}% may indicate higher risk", cap_rate),
                 recommended_action: Some(
                     "Investigate property condition, location, and tenant quality".to_string(),
                 ),
-                details: Some(
-                    "Cap rates above 10% often reflect higher risk properties or markets".to_string(),
-                ),
+                details: Some(format!(
+                    "Cap rates above {:.2}% often reflect higher risk properties or markets",
+                    thresholds.max_cap_rate
+                )),
             });
         }
     }
@@ -257,16 +315,17 @@ fn analyze_underwriting_metrics(uw: &UnderwritingResult) -> Vec<AgentRecommendat
     recommendations
 }
 
-fn analyze_leverage_ratios(uw: &UnderwritingResult) -> Vec<AgentRecommendation> {
+fn analyze_leverage_ratios(uw: &UnderwritingResult, rules: &PolicyRules) -> Vec<AgentRecommendation> {
     let mut recommendations = Vec::new();
+    let thresholds = &rules.thresholds;
 
     // Analyze LTV
     if let Some(ltv) = uw.ltv {
-        if ltv > 85.0 {
+        if ltv > thresholds.max_ltv {
             recommendations.push(AgentRecommendation {
-                severity: Severity::Warning,
+                severity: rules.severities.ltv_above_maximum,
                 category: "Leverage".to_string(),
-                message: format!("LTV of {:.2}% exceeds 85% threshold", ltv),
+                message: format!("LTV of {:.2}% exceeds {:.2}% threshold", ltv, thresholds.max_ltv),
                 recommended_action: Some(
                     "Consider increasing equity contribution to reduce leverage risk".to_string(),
                 ),
@@ -275,15 +334,7 @@ fn analyze_leverage_ratios(uw: &UnderwritingResult) -> Vec<AgentRecommendation>
                         .to_string(),
                 ),
             });
-        } else if ltv > 80.0 {
-            recommendations.push(AgentRecommendation {
-                severity: Severity::Info,
-                category: "Leverage".to_string(),
-                message: format!("LTV of {:.2}% is above 80%", ltv),
-                recommended_action: Some("Monitor property value and maintain cash reserves".to_string()),
-                details: Some("LTV above 80% may require PMI or higher interest rates".to_string()),
-            });
-        } else if ltv < 60.0 {
+        } else if ltv < thresholds.conservative_ltv {
             recommendations.push(AgentRecommendation {
                 severity: Severity::Info,
                 category: "Leverage".to_string(),
@@ -296,17 +347,156 @@ fn analyze_leverage_ratios(uw: &UnderwritingResult) -> Vec<AgentRecommendation>
         }
     }
 
+    // Analyze debt yield
+    if let Some(debt_yield) = uw.debt_yield.filter(|dy| *dy < thresholds.min_debt_yield) {
+        recommendations.push(AgentRecommendation {
+            severity: rules.severities.debt_yield_below_minimum,
+            category: "Leverage".to_string(),
+            message: format!(
+                "Debt yield of {:.2}% is below required {:.2}%",
+                debt_yield, thresholds.min_debt_yield
+            ),
+            recommended_action: uw.max_loan_amount.map(|amount| {
+                format!("Reduce loan amount to at most ${:.2}", amount)
+            }),
+            details: Some(
+                "Debt yield measures the lender's return on the loan regardless of rate or amortization"
+                    .to_string(),
+            ),
+        });
+    }
+
+    recommendations
+}
+
+/// Check reported rent against bank deposits and the property value against its insurance
+fn analyze_supporting_documents(
+    uw: &UnderwritingResult,
+    rules: &PolicyRules,
+) -> Vec<AgentRecommendation> {
+    let mut recommendations = Vec::new();
+    let thresholds = &rules.thresholds;
+
+    if let Some(coverage) = uw
+        .deposit_coverage
+        .filter(|c| *c < thresholds.min_deposit_coverage)
+    {
+        recommendations.push(AgentRecommendation {
+            severity: rules.severities.deposits_below_rent,
+            category: "Income Verification".to_string(),
+            message: format!("Bank deposits cover only {:.1}% of reported rent", coverage),
+            recommended_action: Some(
+                "Reconcile reported rent with bank statements and request additional months of statements"
+                    .to_string(),
+            ),
+            details: Some(
+                "Deposits below reported rent may indicate overstated income or rent collected elsewhere"
+                    .to_string(),
+            ),
+        });
+    }
+
+    if let Some(ratio) = uw
+        .insurance_coverage_ratio
+        .filter(|r| *r < thresholds.min_insurance_coverage)
+    {
+        recommendations.push(AgentRecommendation {
+            severity: rules.severities.insufficient_insurance,
+            category: "Insurance".to_string(),
+            message: format!(
+                "Insurance coverage is {:.1}% of the property value, below {:.1}%",
+                ratio, thresholds.min_insurance_coverage
+            ),
+            recommended_action: Some(
+                "Request increased building coverage or a replacement cost estimate".to_string(),
+            ),
+            details: Some(
+                "A loss exceeding the coverage would impair the collateral securing the loan".to_string(),
+            ),
+        });
+    }
+
+    recommendations
+}
+
+/// Dates of the facts of the given type, skipping values that are not ISO dates
+fn fact_dates(facts: &[Fact], fact_type: &str) -> Vec<NaiveDate> {
+    facts
+        .iter()
+        .filter(|f| f.fact_type == fact_type)
+        .filter_map(|f| NaiveDate::parse_from_str(&f.value, "%Y-%m-%d").ok())
+        .collect()
+}
+
+/// Check for an insurance policy due for renewal and leases rolling over during the loan term
+fn check_expirations(
+    facts: &[Fact],
+    rules: &PolicyRules,
+    today: NaiveDate,
+) -> Vec<AgentRecommendation> {
+    let mut recommendations = Vec::new();
+    let thresholds = &rules.thresholds;
+
+    // The latest policy on file is the one in force
+    let renewal_window = Duration::days(thresholds.insurance_renewal_days.round() as i64);
+    if let Some(expiration) = fact_dates(facts, "insurance_expiration")
+        .into_iter()
+        .max()
+        .filter(|date| *date <= today + renewal_window)
+    {
+        let message = if expiration < today {
+            format!("Insurance policy expired on {}", expiration)
+        } else {
+            format!("Insurance policy expires on {}", expiration)
+        };
+        recommendations.push(AgentRecommendation {
+            severity: rules.severities.insurance_expiring,
+            category: "Insurance".to_string(),
+            message,
+            recommended_action: Some(
+                "Obtain evidence of the renewed policy before closing".to_string(),
+            ),
+            details: Some("The collateral must remain insured for the life of the loan".to_string()),
+        });
+    }
+
+    let maturity = today + Duration::days((thresholds.loan_term_years * 365.25).round() as i64);
+    let mut rolling: Vec<NaiveDate> = fact_dates(facts, "lease_expiration")
+        .into_iter()
+        .filter(|date| *date < maturity)
+        .collect();
+    rolling.sort();
+    if let Some(first) = rolling.first() {
+        recommendations.push(AgentRecommendation {
+            severity: rules.severities.lease_rollover,
+            category: "Lease Rollover".to_string(),
+            message: format!(
+                "{} lease(s) expire before the loan matures, the first on {}",
+                rolling.len(),
+                first
+            ),
+            recommended_action: Some(
+                "Review renewal options and re-leasing costs for the expiring tenants".to_string(),
+            ),
+            details: Some(
+                "Tenants leaving during the loan term reduce the income covering debt service"
+                    .to_string(),
+            ),
+        });
+    }
+
     recommendations
 }
 
-fn check_fact_quality(facts: &[Fact]) -> Vec<AgentRecommendation> {
+fn check_fact_quality(facts: &[Fact], rules: &PolicyRules) -> Vec<AgentRecommendation> {
+    let min_confidence = rules.thresholds.min_confidence;
     let mut recommendations = Vec::new();
 
     // Count unlocked facts
     let unlocked_count = facts.iter().filter(|f| !f.locked).count();
     if unlocked_count > 0 {
         recommendations.push(AgentRecommendation {
-            severity: Severity::Warning,
+            severity: rules.severities.pending_facts,
             category: "Data Quality".to_string(),
             message: format!("{} facts pending approval", unlocked_count),
             recommended_action: Some(
@@ -321,20 +511,21 @@ fn check_fact_quality(facts: &[Fact]) -> Vec<AgentRecommendation> {
     // Check for low confidence facts
     let low_confidence: Vec<&Fact> = facts
         .iter()
-        .filter(|f| f.confidence_score.map_or(false, |s| s < 0.7))
+        .filter(|f| f.confidence_score.is_some_and(|s| s < min_confidence))
         .collect();
 
     if !low_confidence.is_empty() {
         recommendations.push(AgentRecommendation {
-            severity: Severity::Info,
+            severity: rules.severities.low_confidence,
             category: "Data Quality".to_string(),
             message: format!(
                 "{} facts have low confidence scores",
                 low_confidence.len()
             ),
-            recommended_action: Some(
-                "Manually verify facts with confidence below 70%".to_string(),
-            ),
+            recommended_action: Some(format!(
+                "Manually verify facts with confidence below {:.0}%",
+                min_confidence * 100.0
+            )),
             details: Some(
                 "Low confidence scores may indicate OCR errors or ambiguous source data".to_string(),
             ),
@@ -347,7 +538,6 @@ fn check_fact_quality(facts: &[Fact]) -> Vec<AgentRecommendation> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::services::underwriting::CalculationStep;
 
     #[test]
     fn test_low_dscr_warning() {
@@ -358,11 +548,24 @@ mod tests {
             cap_rate: Some(6.0),
             ltv: Some(75.0),
             gross_rent_multiplier: None,
+            annual_debt_service: None,
+            amortization_schedule: None,
+            debt_yield: None,
+            break_even_occupancy: None,
+            expense_ratio: None,
+            rent_per_unit: None,
+            expenses_per_unit: None,
+            noi_per_unit: None,
+            value_per_unit: None,
+            max_loan_amount: None,
+            loan_sizing: None,
+            deposit_coverage: None,
+            insurance_coverage_ratio: None,
             audit_trail: vec![],
             warnings: vec![],
         };
 
-        let recommendations = analyze_underwriting_metrics(&uw);
+        let recommendations = analyze_underwriting_metrics(&uw, &PolicyRules::default());
 
         assert!(recommendations
             .iter()
@@ -379,15 +582,99 @@ mod tests {
             cap_rate: None,
             ltv: None,
             gross_rent_multiplier: None,
+            annual_debt_service: None,
+            amortization_schedule: None,
+            debt_yield: None,
+            break_even_occupancy: None,
+            expense_ratio: None,
+            rent_per_unit: None,
+            expenses_per_unit: None,
+            noi_per_unit: None,
+            value_per_unit: None,
+            max_loan_amount: None,
+            loan_sizing: None,
+            deposit_coverage: None,
+            insurance_coverage_ratio: None,
             audit_trail: vec![],
             warnings: vec![],
         };
 
-        let recommendations = analyze_underwriting_metrics(&uw);
+        let recommendations = analyze_underwriting_metrics(&uw, &PolicyRules::default());
 
         assert!(recommendations
             .iter()
             .any(|r| matches!(r.severity, Severity::Critical) && r.category == "Cash Flow"));
     }
+
+    #[test]
+    fn test_policy_required_facts() {
+        let mut rules = PolicyRules::default();
+        rules.required_fact_types.push("property_value".to_string());
+        rules.required_fact_types.push("insurance_premium".to_string());
+        rules.severities.missing_required_fact = Severity::Warning;
+
+        let recommendations = analyze_deal(&[], None, &rules);
+        let severity_of = |message: &str| {
+            recommendations
+                .iter()
+                .find(|r| r.message == message)
+                .map(|r| r.severity)
+        };
+
+        assert_eq!(severity_of("No rental income data found"), Some(Severity::Warning));
+        assert_eq!(severity_of("No property value found"), Some(Severity::Warning));
+        assert_eq!(severity_of("No insurance premium found"), Some(Severity::Warning));
+        assert_eq!(severity_of("No unit count information found"), Some(Severity::Info));
+    }
+
+    #[test]
+    fn test_expirations() {
+        let fact = |fact_type: &str, value: &str| Fact {
+            unit: Some("date".to_string()),
+            source_citation: serde_json::json!({}),
+            confidence_score: None,
+            ..Fact::for_test(&format!("{}-{}", fact_type, value), fact_type, value)
+        };
+        let facts = vec![
+            fact("insurance_expiration", "2025-01-15"),
+            fact("insurance_expiration", "2025-04-01"),
+            fact("lease_expiration", "2031-06-30"),
+            fact("lease_expiration", "2028-12-31"),
+            fact("lease_expiration", "2040-01-31"),
+        ];
+        let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+
+        let recommendations = check_expirations(&facts, &PolicyRules::default(), today);
+        assert_eq!(recommendations.len(), 2);
+        assert_eq!(recommendations[0].message, "Insurance policy expires on 2025-04-01");
+        assert_eq!(recommendations[0].severity, Severity::Warning);
+        assert_eq!(
+            recommendations[1].message,
+            "2 lease(s) expire before the loan matures, the first on 2028-12-31"
+        );
+
+        let later = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        assert_eq!(check_expirations(&facts, &PolicyRules::default(), later).len(), 1);
+    }
+
+    #[test]
+    fn test_group_by_severity() {
+        let recommendations = analyze_deal(&[], None, &PolicyRules::default());
+        let total = recommendations.len();
+        let grouped = group_by_severity(recommendations);
+
+        assert_eq!(
+            grouped.critical.len() + grouped.warning.len() + grouped.info.len(),
+            total
+        );
+        assert!(grouped
+            .critical
+            .iter()
+            .all(|r| matches!(r.severity, Severity::Critical)));
+        assert!(grouped
+            .critical
+            .iter()
+            .any(|r| r.message == "No rental income data found"));
+    }
 }
 
@@ -0,0 +1,540 @@
+use crate::models::deal::Deal;
+use crate::models::document::Document;
+use crate::models::fact::{Fact, FactStatus, PeriodBasis, SourceCitation};
+use crate::models::fact_resolution::FactResolution;
+use crate::models::fact_value::FactValue;
+use crate::models::pl_line_item::PlLineItem;
+use crate::models::property::Property;
+use crate::models::rent_roll_unit::RentRollUnit;
+use crate::models::underwriting_policy::PolicyRules;
+use crate::services::deal_agent::{analyze_deal, AgentRecommendation};
+use crate::services::portfolio::DealInput;
+use crate::services::reconciliation::{apply_resolutions, unresolved_conflicts};
+use crate::services::underwriting::{
+    calculate_underwriting, run_stress_grid, StressTestGrid, StressTestMatrix, UnderwritingResult,
+};
+use crate::utils::services::xlsx::{Cell, Workbook, Worksheet};
+use std::collections::HashMap;
+
+/// Everything that goes into a deal's underwriting workbook
+#[derive(Debug, Clone)]
+pub struct DealExport {
+    pub deal: Deal,
+    pub documents: Vec<Document>,
+    /// Approved facts, after applying conflict resolutions
+    pub facts: Vec<Fact>,
+    pub rent_roll_units: Vec<RentRollUnit>,
+    pub pl_line_items: Vec<PlLineItem>,
+    pub underwriting: Option<UnderwritingResult>,
+    pub stress_test: Option<StressTestMatrix>,
+    pub recommendations: Vec<AgentRecommendation>,
+    /// Why underwriting could not be calculated, if it could not
+    pub underwriting_note: Option<String>,
+}
+
+/// Shocks exported on the stress test sheet
+pub fn export_stress_grid() -> StressTestGrid {
+    StressTestGrid {
+        rent_adjustments: vec![-10.0, -5.0, 0.0],
+        expense_adjustments: vec![0.0, 5.0, 10.0],
+        occupancy_adjustments: vec![-5.0, 0.0],
+        interest_rate_adjustments: vec![0.0, 100.0],
+    }
+}
+
+impl DealExport {
+    /// Run underwriting, stress tests and recommendations over a deal's facts, the same way the
+    /// underwrite, stress test and recommendations endpoints do
+    ///
+    /// Underwriting is left out when documents disagree on a fact the reviewer has not resolved,
+    /// or when required facts are missing. Portfolio deals are exported with the roll-up of their
+    /// properties.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        deal: Deal,
+        documents: Vec<Document>,
+        properties: Vec<Property>,
+        facts: &[Fact],
+        resolutions: &[FactResolution],
+        rent_roll_units: Vec<RentRollUnit>,
+        pl_line_items: Vec<PlLineItem>,
+        rules: &PolicyRules,
+    ) -> Self {
+        let period_basis = PeriodBasis::default();
+        let approved: Vec<Fact> = facts
+            .iter()
+            .filter(|f| f.status == FactStatus::Approved.as_str() && f.locked)
+            .cloned()
+            .collect();
+
+        let conflicts = unresolved_conflicts(&approved, resolutions, &period_basis);
+        let approved = apply_resolutions(&approved, resolutions);
+        let (underwriting, stress_test, underwriting_note) = if !conflicts.is_empty() {
+            let fact_types: Vec<&str> = conflicts.iter().map(|c| c.fact_type.as_str()).collect();
+            (
+                None,
+                None,
+                Some(format!("Unresolved fact conflicts: {}", fact_types.join(", "))),
+            )
+        } else {
+            match DealInput::from_facts(properties, &approved, &period_basis, rules) {
+                Ok(DealInput { input, .. }) => (
+                    Some(calculate_underwriting(input.clone(), rules)),
+                    run_stress_grid(input, &export_stress_grid(), rules).ok(),
+                    None,
+                ),
+                Err(error) => (None, None, Some(error)),
+            }
+        };
+        let recommendations = analyze_deal(facts, underwriting.as_ref(), rules);
+
+        DealExport {
+            deal,
+            documents,
+            facts: approved,
+            rent_roll_units,
+            pl_line_items,
+            underwriting,
+            stress_test,
+            recommendations,
+            underwriting_note,
+        }
+    }
+
+    pub fn to_workbook(&self) -> Workbook {
+        let mut workbook = Workbook::new();
+        workbook.add_sheet(self.summary_sheet());
+        workbook.add_sheet(self.facts_sheet());
+        workbook.add_sheet(self.rent_roll_sheet());
+        workbook.add_sheet(self.profit_and_loss_sheet());
+        workbook.add_sheet(self.underwriting_sheet());
+        workbook.add_sheet(self.audit_trail_sheet());
+        workbook.add_sheet(self.stress_test_sheet());
+        workbook.add_sheet(self.recommendations_sheet());
+        workbook
+    }
+
+    fn document_name(&self, document_id: &str) -> String {
+        self.documents
+            .iter()
+            .find(|d| d.document_id == document_id)
+            .map(|d| d.file_name.clone())
+            .unwrap_or_else(|| document_id.to_string())
+    }
+
+    fn summary_sheet(&self) -> Worksheet {
+        let mut sheet = Worksheet::new("Summary", &["Field", "Value"]);
+        sheet.push_row(vec!["Deal".into(), self.deal.deal_name.clone().into()]);
+        sheet.push_row(vec!["Deal ID".into(), self.deal.deal_id.clone().into()]);
+        sheet.push_row(vec!["Status".into(), self.deal.status.clone().into()]);
+        sheet.push_row(vec![
+            "Documents".into(),
+            Cell::Number(self.documents.len() as f64),
+        ]);
+        sheet.push_row(vec![
+            "Approved facts".into(),
+            Cell::Number(self.facts.len() as f64),
+        ]);
+        if let Some(note) = &self.underwriting_note {
+            sheet.push_row(vec!["Underwriting".into(), note.clone().into()]);
+        }
+        sheet
+    }
+
+    fn facts_sheet(&self) -> Worksheet {
+        let mut sheet = Worksheet::new(
+            "Facts",
+            &[
+                "Fact Type",
+                "Label",
+                "Value",
+                "Unit",
+                "Period Basis",
+                "Confidence",
+                "Approved By",
+                "Approved At",
+                "Document",
+                "Page",
+                "Source Text",
+                "Bounding Box",
+            ],
+        );
+        for fact in &self.facts {
+            let citation: Option<SourceCitation> =
+                serde_json::from_value(fact.source_citation.clone()).ok();
+            let value = match fact.typed_value() {
+                Ok(FactValue::Amount { amount, .. }) => Cell::Number(amount),
+                _ => Cell::Text(fact.value.clone()),
+            };
+            sheet.push_row(vec![
+                fact.fact_type.clone().into(),
+                fact.label.clone().into(),
+                value,
+                fact.unit.clone().into(),
+                fact.period_basis.clone().into(),
+                fact.confidence_score.into(),
+                fact.approved_by.clone().into(),
+                fact.approved_at.map(|at| at.to_rfc3339()).into(),
+                citation
+                    .as_ref()
+                    .map(|c| c.document.clone())
+                    .unwrap_or_else(|| self.document_name(&fact.document_id))
+                    .into(),
+                citation.as_ref().map(|c| c.page as f64).into(),
+                citation.as_ref().and_then(|c| c.line.clone()).into(),
+                citation
+                    .as_ref()
+                    .and_then(|c| c.bbox.as_ref())
+                    .map(|b| format!("{}, {}, {}, {}", b.left, b.top, b.width, b.height))
+                    .into(),
+            ]);
+        }
+        sheet
+    }
+
+    fn rent_roll_sheet(&self) -> Worksheet {
+        let mut sheet = Worksheet::new(
+            "Rent Roll",
+            &[
+                "Document",
+                "Unit",
+                "Tenant",
+                "Square Feet",
+                "Lease Start",
+                "Lease End",
+                "Contract Rent",
+                "Market Rent",
+                "Status",
+            ],
+        );
+        for unit in &self.rent_roll_units {
+            sheet.push_row(vec![
+                self.document_name(&unit.document_id).into(),
+                unit.unit_id.clone().into(),
+                unit.tenant.clone().into(),
+                unit.square_feet.into(),
+                unit.lease_start.map(|d| d.to_string()).into(),
+                unit.lease_end.map(|d| d.to_string()).into(),
+                unit.contract_rent.into(),
+                unit.market_rent.into(),
+                unit.status.clone().into(),
+            ]);
+        }
+        sheet
+    }
+
+    /// Line items × periods, with a column for every period found in any P&L document
+    fn profit_and_loss_sheet(&self) -> Worksheet {
+        let mut periods: Vec<&str> = self
+            .pl_line_items
+            .iter()
+            .flat_map(|item| item.periods.iter().map(String::as_str))
+            .collect();
+        periods.sort();
+        periods.dedup();
+
+        let mut header = vec!["Document", "Category", "Label"];
+        header.extend(periods.iter().copied());
+        let mut sheet = Worksheet::new("P&L", &header);
+        for item in &self.pl_line_items {
+            let amounts: HashMap<&str, Option<f64>> = item
+                .periods
+                .iter()
+                .map(String::as_str)
+                .zip(item.amounts.iter().copied())
+                .collect();
+            let mut row: Vec<Cell> = vec![
+                self.document_name(&item.document_id).into(),
+                item.category.clone().into(),
+                item.label.clone().into(),
+            ];
+            row.extend(
+                periods
+                    .iter()
+                    .map(|period| amounts.get(period).copied().flatten().into()),
+            );
+            sheet.push_row(row);
+        }
+        sheet
+    }
+
+    fn underwriting_sheet(&self) -> Worksheet {
+        let mut sheet = Worksheet::new("Underwriting", &["Metric", "Value"]);
+        let Some(result) = &self.underwriting else {
+            if let Some(note) = &self.underwriting_note {
+                sheet.push_row(vec!["Not calculated".into(), note.clone().into()]);
+            }
+            return sheet;
+        };
+
+        sheet.push_row(vec!["NOI".into(), result.noi.into()]);
+        sheet.push_row(vec![
+            "Annual Debt Service".into(),
+            result.annual_debt_service.into(),
+        ]);
+        sheet.push_row(vec!["DSCR".into(), result.dscr.into()]);
+        sheet.push_row(vec![
+            "Cash Flow After Debt".into(),
+            result.cash_flow_after_debt.into(),
+        ]);
+        sheet.push_row(vec!["Cap Rate (%)".into(), result.cap_rate.into()]);
+        sheet.push_row(vec!["LTV (%)".into(), result.ltv.into()]);
+        sheet.push_row(vec![
+            "Gross Rent Multiplier".into(),
+            result.gross_rent_multiplier.into(),
+        ]);
+        sheet.push_row(vec!["Debt Yield (%)".into(), result.debt_yield.into()]);
+        sheet.push_row(vec!["Expense Ratio (%)".into(), result.expense_ratio.into()]);
+        sheet.push_row(vec![
+            "Break-even Occupancy (%)".into(),
+            result.break_even_occupancy.into(),
+        ]);
+        sheet.push_row(vec!["Rent per Unit".into(), result.rent_per_unit.into()]);
+        sheet.push_row(vec!["Expenses per Unit".into(), result.expenses_per_unit.into()]);
+        sheet.push_row(vec!["NOI per Unit".into(), result.noi_per_unit.into()]);
+        sheet.push_row(vec!["Value per Unit".into(), result.value_per_unit.into()]);
+        sheet.push_row(vec![
+            "Deposit Coverage of Rent (%)".into(),
+            result.deposit_coverage.into(),
+        ]);
+        sheet.push_row(vec![
+            "Insurance Coverage of Value (%)".into(),
+            result.insurance_coverage_ratio.into(),
+        ]);
+        if let Some(sizing) = &result.loan_sizing {
+            sheet.push_row(vec!["Max Loan Amount".into(), sizing.max_loan_amount.into()]);
+            sheet.push_row(vec![
+                "Max Loan Binding Constraint".into(),
+                sizing.binding_constraint.clone().into(),
+            ]);
+            sheet.push_row(vec!["Max Loan at Min DSCR".into(), sizing.dscr_max_loan.into()]);
+            sheet.push_row(vec!["Max Loan at Max LTV".into(), sizing.ltv_max_loan.into()]);
+            sheet.push_row(vec![
+                "Max Loan at Min Debt Yield".into(),
+                sizing.debt_yield_max_loan.into(),
+            ]);
+            sheet.push_row(vec![
+                "Max Loan Annual Debt Service".into(),
+                sizing.annual_debt_service.into(),
+            ]);
+            sheet.push_row(vec![
+                format!("Balloon Balance at {} Years", sizing.term_years).into(),
+                sizing.balloon_balance.into(),
+            ]);
+        }
+        if let Some(stress_test) = &self.stress_test {
+            let breakevens = &stress_test.breakevens;
+            sheet.push_row(vec![
+                "Break-even Rent Change (%)".into(),
+                breakevens.rent_change_pct.into(),
+            ]);
+            sheet.push_row(vec![
+                "Break-even Expense Change (%)".into(),
+                breakevens.expense_change_pct.into(),
+            ]);
+            sheet.push_row(vec![
+                "Stress Break-even Occupancy (%)".into(),
+                breakevens.occupancy_rate.into(),
+            ]);
+            sheet.push_row(vec![
+                "Break-even Interest Rate Change (bps)".into(),
+                breakevens.interest_rate_change_bps.into(),
+            ]);
+        }
+        for warning in &result.warnings {
+            sheet.push_row(vec!["Warning".into(), warning.clone().into()]);
+        }
+        sheet
+    }
+
+    fn audit_trail_sheet(&self) -> Worksheet {
+        let mut sheet = Worksheet::new(
+            "Audit Trail",
+            &["Metric", "Formula", "Inputs", "Result", "Source Facts", "Citations"],
+        );
+        let steps = self
+            .underwriting
+            .as_ref()
+            .map(|result| result.audit_trail.as_slice())
+            .unwrap_or_default();
+        for step in steps {
+            let inputs: Vec<String> = step
+                .inputs
+                .iter()
+                .map(|(name, value)| format!("{} = {}", name, value))
+                .collect();
+            let citations: Vec<String> = step
+                .citations
+                .iter()
+                .map(|c| format!("{} p.{}", c.document, c.page))
+                .collect();
+            sheet.push_row(vec![
+                step.metric.clone().into(),
+                step.formula.clone().into(),
+                inputs.join("; ").into(),
+                step.result.into(),
+                step.sources.join(", ").into(),
+                citations.join("; ").into(),
+            ]);
+        }
+        sheet
+    }
+
+    fn stress_test_sheet(&self) -> Worksheet {
+        let mut sheet = Worksheet::new(
+            "Stress Test",
+            &[
+                "Rent Change (%)",
+                "Expense Change (%)",
+                "Occupancy Change (pts)",
+                "Interest Rate Change (bps)",
+                "Stressed NOI",
+                "Stressed DSCR",
+                "Stressed Cash Flow",
+                "NOI Change",
+                "NOI Change (%)",
+                "DSCR Change",
+                "Cash Flow Change",
+            ],
+        );
+        let scenarios = self
+            .stress_test
+            .as_ref()
+            .map(|matrix| matrix.scenarios.as_slice())
+            .unwrap_or_default();
+        for scenario in scenarios {
+            let result = &scenario.result;
+            sheet.push_row(vec![
+                scenario.rent_adjustment.into(),
+                scenario.expense_adjustment.into(),
+                scenario.occupancy_adjustment.into(),
+                scenario.interest_rate_adjustment.into(),
+                result.stressed_noi.into(),
+                result.stressed_dscr.into(),
+                result.stressed_cash_flow.into(),
+                result.comparison.noi_change.into(),
+                result.comparison.noi_change_pct.into(),
+                result.comparison.dscr_change.into(),
+                result.comparison.cash_flow_change.into(),
+            ]);
+        }
+        sheet
+    }
+
+    fn recommendations_sheet(&self) -> Worksheet {
+        let mut sheet = Worksheet::new(
+            "Recommendations",
+            &["Severity", "Category", "Message", "Recommended Action", "Details"],
+        );
+        for recommendation in &self.recommendations {
+            sheet.push_row(vec![
+                format!("{:?}", recommendation.severity).into(),
+                recommendation.category.clone().into(),
+                recommendation.message.clone().into(),
+                recommendation.recommended_action.clone().into(),
+                recommendation.details.clone().into(),
+            ]);
+        }
+        sheet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn fact(fact_id: &str, fact_type: &str, value: &str) -> Fact {
+        Fact {
+            document_id: "doc-pl".to_string(),
+            unit: Some("USD".to_string()),
+            source_citation: serde_json::json!({ "document": "pl.pdf", "page": 2 }),
+            approved_by: Some("user-1".to_string()),
+            ..Fact::for_test(fact_id, fact_type, value)
+        }
+    }
+
+    #[test]
+    fn test_deal_export_workbook() {
+        let deal = Deal {
+            deal_id: "deal-1".to_string(),
+            user_id: "user-1".to_string(),
+            deal_name: "Maple Court".to_string(),
+            status: "ready_for_underwriting".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+            policy_id: None,
+            org_id: None,
+        };
+        let line_item = |label: &str, periods: &[&str], amounts: &[f64]| PlLineItem {
+            pl_line_item_id: format!("pl-{}", label),
+            document_id: "doc-pl".to_string(),
+            deal_id: "deal-1".to_string(),
+            row_index: 0,
+            category: "rental_income".to_string(),
+            label: label.to_string(),
+            periods: periods.iter().map(|p| p.to_string()).collect(),
+            amounts: amounts.iter().map(|a| Some(*a)).collect(),
+            source_citation: serde_json::json!({}),
+            created_at: Utc::now(),
+        };
+        let mut pending = fact("f-units", "unit_count", "24");
+        pending.status = "pending_approval".to_string();
+        pending.locked = false;
+        let facts = vec![
+            fact("f-rent", "collected_rent", "120000"),
+            fact("f-opex", "operating_expenses", "45000"),
+            fact("f-debt", "debt_service", "50000"),
+            pending,
+        ];
+
+        let export = DealExport::new(
+            deal,
+            vec![],
+            vec![],
+            &facts,
+            &[],
+            vec![],
+            vec![
+                line_item("Rent", &["2024-01", "2024-02"], &[10000.0, 10100.0]),
+                line_item("Parking", &["2024-02", "2024-03"], &[300.0, 320.0]),
+            ],
+            &PolicyRules::default(),
+        );
+        assert_eq!(export.facts.len(), 3);
+        assert!(export.underwriting_note.is_none());
+        assert_eq!(export.underwriting.as_ref().unwrap().noi, 75000.0);
+
+        let workbook = export.to_workbook();
+        let names: Vec<&str> = workbook.sheets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Summary",
+                "Facts",
+                "Rent Roll",
+                "P&L",
+                "Underwriting",
+                "Audit Trail",
+                "Stress Test",
+                "Recommendations"
+            ]
+        );
+
+        let pl = &workbook.sheets[3];
+        assert_eq!(
+            pl.header,
+            vec!["Document", "Category", "Label", "2024-01", "2024-02", "2024-03"]
+        );
+        assert_eq!(pl.rows[1][3], Cell::Empty);
+        assert_eq!(pl.rows[1][5], Cell::Number(320.0));
+
+        let facts_sheet = &workbook.sheets[1];
+        assert_eq!(facts_sheet.rows[0][2], Cell::Number(120000.0));
+        assert_eq!(facts_sheet.rows[0][8], Cell::Text("pl.pdf".to_string()));
+        assert_eq!(workbook.sheets[6].rows.len(), 36);
+        assert!(!workbook.sheets[5].rows.is_empty());
+        assert!(workbook.to_bytes().is_ok());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::models::deal::{Deal, DealStatus};
+use crate::models::document::DocumentStatus;
+use crate::models::fact::{Fact, FactStatus};
+use crate::models::underwriting_policy::PolicyRules;
+use chrono::Utc;
+use diesel::prelude::*;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DealStatusError {
+    #[error("Deal has unknown status '{0}'")]
+    UnknownStatus(String),
+
+    #[error("Cannot move deal from {} to {}", .from.as_str(), .to.as_str())]
+    IllegalTransition { from: DealStatus, to: DealStatus },
+
+    #[error("Deal is already complete")]
+    AlreadyComplete,
+
+    #[error(transparent)]
+    Database(#[from] diesel::result::Error),
+}
+
+pub fn current_status(deal: &Deal) -> Result<DealStatus, DealStatusError> {
+    DealStatus::from_str(&deal.status)
+        .ok_or_else(|| DealStatusError::UnknownStatus(deal.status.clone()))
+}
+
+/// Whether every fact type the deal's policy requires has an approved, locked fact
+pub fn required_facts_approved(facts: &[Fact], required_fact_types: &[String]) -> bool {
+    required_fact_types.iter().all(|fact_type| {
+        facts.iter().any(|f| {
+            f.fact_type == *fact_type && f.status == FactStatus::Approved.as_str() && f.locked
+        })
+    })
+}
+
+/// Move a deal to `next`, rejecting transitions the state machine does not allow
+///
+/// Moving a deal to the status it already has is a no-op.
+pub fn transition(
+    conn: &mut PgConnection,
+    deal: &Deal,
+    next: DealStatus,
+) -> Result<Deal, DealStatusError> {
+    use crate::data::schema::deals;
+
+    let from = current_status(deal)?;
+    if from == next {
+        return Ok(deal.clone());
+    }
+    if !from.can_transition_to(&next) {
+        return Err(DealStatusError::IllegalTransition { from, to: next });
+    }
+
+    Ok(diesel::update(deals::table.filter(deals::deal_id.eq(&deal.deal_id)))
+        .set((
+            deals::status.eq(next.as_str()),
+            deals::updated_at.eq(Utc::now()),
+        ))
+        .get_result::<Deal>(conn)?)
+}
+
+/// Move a deal whose documents have all finished processing to fact review
+///
+/// Failed documents count as finished. Deals in any other status are left untouched.
+pub fn sync_after_documents(
+    conn: &mut PgConnection,
+    deal_id: &str,
+) -> Result<Deal, DealStatusError> {
+    use crate::data::schema::{deals, documents};
+
+    let deal = deals::table
+        .filter(deals::deal_id.eq(deal_id))
+        .first::<Deal>(conn)?;
+    if current_status(&deal)? != DealStatus::ProcessingDocuments {
+        return Ok(deal);
+    }
+
+    let unfinished: i64 = documents::table
+        .filter(documents::deal_id.eq(deal_id))
+        .filter(documents::status.eq_any([
+            DocumentStatus::Pending.as_str(),
+            DocumentStatus::Processing.as_str(),
+        ]))
+        .count()
+        .get_result(conn)?;
+    if unfinished > 0 {
+        return Ok(deal);
+    }
+
+    let deal = transition(conn, &deal, DealStatus::FactReview)?;
+    sync_after_review(conn, &deal)
+}
+
+/// Move a deal between fact review and ready for underwriting as the facts its policy requires are
+/// approved or reset
+///
+/// Fails when the facts of a complete deal are no longer approved.
+pub fn sync_after_review(conn: &mut PgConnection, deal: &Deal) -> Result<Deal, DealStatusError> {
+    use crate::data::schema::facts;
+
+    let status = current_status(deal)?;
+    if !matches!(
+        status,
+        DealStatus::FactReview | DealStatus::ReadyForUnderwriting | DealStatus::Complete
+    ) {
+        return Ok(deal.clone());
+    }
+
+    let fact_list = facts::table
+        .filter(facts::deal_id.eq(&deal.deal_id))
+        .load::<Fact>(conn)?;
+    let rules = PolicyRules::for_deal(conn, deal)?;
+    match (status, required_facts_approved(&fact_list, &rules.required_fact_types)) {
+        (DealStatus::FactReview, true) => {
+            transition(conn, deal, DealStatus::ReadyForUnderwriting)
+        }
+        (DealStatus::ReadyForUnderwriting, false) | (DealStatus::Complete, false) => {
+            transition(conn, deal, DealStatus::FactReview)
+        }
+        _ => Ok(deal.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fact(fact_type: &str, status: FactStatus, locked: bool) -> Fact {
+        Fact {
+            unit: Some("USD".to_string()),
+            status: status.as_str().to_string(),
+            locked,
+            ..Fact::for_test(&format!("f-{}", fact_type), fact_type, "100000")
+        }
+    }
+
+    #[test]
+    fn test_transitions() {
+        use DealStatus::*;
+
+        assert!(Draft.can_transition_to(&ProcessingDocuments));
+        assert!(ProcessingDocuments.can_transition_to(&FactReview));
+        assert!(FactReview.can_transition_to(&ReadyForUnderwriting));
+        assert!(ReadyForUnderwriting.can_transition_to(&Complete));
+        assert!(ReadyForUnderwriting.can_transition_to(&FactReview));
+        assert!(!Draft.can_transition_to(&Complete));
+        assert!(!FactReview.can_transition_to(&Complete));
+        assert!(!Complete.can_transition_to(&ProcessingDocuments));
+        assert!(!Complete.can_transition_to(&FactReview));
+
+        let error = DealStatusError::IllegalTransition { from: Complete, to: FactReview };
+        assert_eq!(error.to_string(), "Cannot move deal from complete to fact_review");
+    }
+
+    #[test]
+    fn test_required_facts_approved() {
+        let mut facts = vec![
+            fact("collected_rent", FactStatus::Approved, true),
+            fact("operating_expenses", FactStatus::PendingApproval, false),
+            fact("unit_count", FactStatus::PendingApproval, false),
+        ];
+        let required = PolicyRules::default().required_fact_types;
+        assert!(!required_facts_approved(&facts, &required));
+
+        facts[1] = fact("operating_expenses", FactStatus::Approved, true);
+        assert!(required_facts_approved(&facts, &required));
+
+        let mut required = required;
+        required.push("unit_count".to_string());
+        assert!(!required_facts_approved(&facts, &required));
+    }
+}
@@ -0,0 +1,345 @@
+use crate::models::document::{
+    ClassificationAlternative, ClassificationSource, Document, DocumentType,
+};
+use crate::models::output::{Chunk, Segment, SegmentType};
+
+/// Only the first pages are classified, where titles and headers identify a document
+pub const CLASSIFICATION_PAGES: u32 = 3;
+
+/// Layout classifications below this confidence are sent to the LLM
+pub const LLM_FALLBACK_CONFIDENCE: f64 = 0.6;
+
+/// Number of alternative types kept besides the chosen one
+pub const MAX_ALTERNATIVES: usize = 3;
+
+/// Weight of a file name match, lower than any segment as servicers and banks reuse names
+/// like "statement.pdf"
+const FILE_NAME_WEIGHT: f64 = 0.5;
+
+/// Score at which the layout evidence for a type counts for half of its confidence
+const EVIDENCE_HALF_SCORE: f64 = 4.0;
+
+/// Phrases that identify a document type when found in its segments
+struct TypeSignals {
+    document_type: DocumentType,
+    phrases: &'static [&'static str],
+}
+
+const TYPE_SIGNALS: &[TypeSignals] = &[
+    TypeSignals {
+        document_type: DocumentType::RentRoll,
+        phrases: &[
+            "rent roll",
+            "tenant",
+            "lease start",
+            "lease end",
+            "lease expiration",
+            "move-in",
+            "move in",
+            "market rent",
+            "unit mix",
+            "vacant",
+        ],
+    },
+    TypeSignals {
+        document_type: DocumentType::ProfitAndLoss,
+        phrases: &[
+            "profit and loss",
+            "profit & loss",
+            "income statement",
+            "operating statement",
+            "statement of operations",
+            "net operating income",
+            "total operating expenses",
+            "total income",
+            "trailing 12",
+            "t-12",
+        ],
+    },
+    TypeSignals {
+        document_type: DocumentType::MortgageStatement,
+        phrases: &[
+            "mortgage statement",
+            "mortgage",
+            "loan number",
+            "principal balance",
+            "escrow",
+            "servicer",
+            "amount due",
+            "payment due date",
+            "maturity date",
+            "interest rate",
+        ],
+    },
+    TypeSignals {
+        document_type: DocumentType::TaxDocument,
+        phrases: &[
+            "property tax",
+            "tax bill",
+            "tax statement",
+            "assessed value",
+            "assessment",
+            "tax year",
+            "form 1099",
+            "form 1098",
+            "parcel number",
+            "millage",
+        ],
+    },
+    TypeSignals {
+        document_type: DocumentType::BankStatement,
+        phrases: &[
+            "bank statement",
+            "account summary",
+            "beginning balance",
+            "ending balance",
+            "deposits",
+            "withdrawals",
+            "checking account",
+            "savings account",
+            "routing number",
+            "available balance",
+        ],
+    },
+    TypeSignals {
+        document_type: DocumentType::PropertyDeed,
+        phrases: &[
+            "warranty deed",
+            "quitclaim",
+            "deed of trust",
+            "grantor",
+            "grantee",
+            "legal description",
+            "recorder",
+            "conveys",
+        ],
+    },
+    TypeSignals {
+        document_type: DocumentType::InsurancePolicy,
+        phrases: &[
+            "insurance policy",
+            "declarations",
+            "policy number",
+            "named insured",
+            "policy period",
+            "premium",
+            "coverage",
+            "deductible",
+        ],
+    },
+    TypeSignals {
+        document_type: DocumentType::Lease,
+        phrases: &[
+            "lease agreement",
+            "landlord",
+            "premises",
+            "base rent",
+            "commencement date",
+            "security deposit",
+            "renewal option",
+            "escalation",
+        ],
+    },
+    TypeSignals {
+        document_type: DocumentType::Appraisal,
+        phrases: &[
+            "appraisal report",
+            "as is value",
+            "as-is value",
+            "appraiser",
+            "sales comparison approach",
+            "income capitalization approach",
+            "highest and best use",
+            "effective date of value",
+        ],
+    },
+];
+
+/// The type of a document with its confidence and the other types considered
+#[derive(Debug, Clone)]
+pub struct DocumentClassification {
+    pub document_type: DocumentType,
+    /// Between 0 and 1
+    pub confidence: f64,
+    pub alternatives: Vec<ClassificationAlternative>,
+    pub source: ClassificationSource,
+}
+
+impl DocumentClassification {
+    /// The type chosen by the user, which is never reclassified
+    pub fn user(document_type: DocumentType) -> Self {
+        DocumentClassification {
+            document_type,
+            confidence: 1.0,
+            alternatives: vec![],
+            source: ClassificationSource::User,
+        }
+    }
+}
+
+/// How much a match in a segment counts towards its document type
+fn segment_weight(segment_type: &SegmentType) -> f64 {
+    match segment_type {
+        SegmentType::Title => 4.0,
+        SegmentType::SectionHeader => 3.0,
+        SegmentType::PageHeader | SegmentType::Table => 2.0,
+        SegmentType::Text | SegmentType::ListItem | SegmentType::Caption | SegmentType::Page => {
+            1.0
+        }
+        SegmentType::PageFooter
+        | SegmentType::Footnote
+        | SegmentType::Formula
+        | SegmentType::Picture => 0.5,
+    }
+}
+
+/// Number of distinct phrases of each type found in the text
+fn phrase_matches(text: &str) -> Vec<usize> {
+    let text = text.to_lowercase();
+    TYPE_SIGNALS
+        .iter()
+        .map(|signals| signals.phrases.iter().filter(|p| text.contains(*p)).count())
+        .collect()
+}
+
+/// Text of a segment, falling back to its OCR results when the content was not generated
+fn segment_text(segment: &Segment) -> String {
+    if !segment.content.trim().is_empty() {
+        return segment.content.clone();
+    }
+    segment
+        .ocr
+        .iter()
+        .flatten()
+        .map(|r| r.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Classify a document from the titles, section headers and tables of its first pages
+///
+/// Every phrase found in a segment adds the weight of the segment type to its document type.
+/// The confidence of a type is its share of the total score, scaled down when there is little
+/// evidence overall. Documents without any matching phrase are `Other` with no confidence.
+pub fn classify_segments(document: &Document, chunks: &[Chunk]) -> DocumentClassification {
+    let mut scores = vec![0.0; TYPE_SIGNALS.len()];
+    let segments = chunks
+        .iter()
+        .flat_map(|chunk| chunk.segments.iter())
+        .filter(|segment| segment.page_number <= CLASSIFICATION_PAGES);
+    for segment in segments {
+        let weight = segment_weight(&segment.segment_type);
+        for (score, matches) in scores.iter_mut().zip(phrase_matches(&segment_text(segment))) {
+            *score += weight * matches as f64;
+        }
+    }
+    let file_name = document.file_name.replace(['_', '-', '.'], " ");
+    for (score, matches) in scores.iter_mut().zip(phrase_matches(&file_name)) {
+        *score += FILE_NAME_WEIGHT * matches as f64;
+    }
+
+    let total: f64 = scores.iter().sum();
+    if total <= 0.0 {
+        return DocumentClassification {
+            document_type: DocumentType::Other,
+            confidence: 0.0,
+            alternatives: vec![],
+            source: ClassificationSource::Layout,
+        };
+    }
+    let top = scores.iter().cloned().fold(0.0, f64::max);
+    let evidence = top / (top + EVIDENCE_HALF_SCORE);
+    let mut ranked: Vec<(usize, f64)> = scores
+        .iter()
+        .enumerate()
+        .filter(|(_, score)| **score > 0.0)
+        .map(|(i, score)| (i, score / total * evidence))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let (best, confidence) = ranked[0];
+    DocumentClassification {
+        document_type: TYPE_SIGNALS[best].document_type.clone(),
+        confidence,
+        alternatives: ranked
+            .iter()
+            .skip(1)
+            .take(MAX_ALTERNATIVES)
+            .map(|(i, confidence)| ClassificationAlternative {
+                document_type: TYPE_SIGNALS[*i].document_type.as_str().to_string(),
+                confidence: *confidence,
+            })
+            .collect(),
+        source: ClassificationSource::Layout,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::output::BoundingBox;
+
+    fn document(file_name: &str) -> Document {
+        Document {
+            page_count: Some(2),
+            ..Document::for_test(file_name, "other")
+        }
+    }
+
+    fn chunks(segments: &[(SegmentType, u32, &str)]) -> Vec<Chunk> {
+        let segments = segments
+            .iter()
+            .map(|(segment_type, page, content)| {
+                let mut segment = Segment::new(
+                    BoundingBox::new(0.0, 0.0, 100.0, 20.0),
+                    None,
+                    vec![],
+                    1000.0,
+                    800.0,
+                    *page,
+                    segment_type.clone(),
+                );
+                segment.content = content.to_string();
+                segment
+            })
+            .collect();
+        vec![Chunk {
+            chunk_id: "chunk-1".to_string(),
+            chunk_length: 0,
+            segments,
+            embed: None,
+        }]
+    }
+
+    #[test]
+    fn test_mortgage_statement_named_statement() {
+        let chunks = chunks(&[
+            (SegmentType::Title, 1, "Mortgage Statement"),
+            (SegmentType::Text, 1, "Loan Number 00123 Payment Due Date 03/01/2025"),
+            (SegmentType::Table, 1, "Principal Balance $1,250,000 Escrow Balance $4,200"),
+            (SegmentType::Text, 2, "Deposits to escrow are made by your servicer"),
+            (SegmentType::Title, 5, "Bank Statement Account Summary"),
+        ]);
+        let classification = classify_segments(&document("statement.pdf"), &chunks);
+
+        assert_eq!(classification.document_type.as_str(), "mortgage_statement");
+        assert_eq!(classification.source, ClassificationSource::Layout);
+        assert!(classification.confidence > LLM_FALLBACK_CONFIDENCE);
+        assert_eq!(classification.alternatives.len(), 1);
+        assert_eq!(classification.alternatives[0].document_type, "bank_statement");
+        assert!(classification.alternatives[0].confidence < 0.1);
+    }
+
+    #[test]
+    fn test_inconclusive_layout() {
+        let empty = classify_segments(&document("scan.pdf"), &chunks(&[]));
+        assert_eq!(empty.document_type.as_str(), "other");
+        assert_eq!(empty.confidence, 0.0);
+        assert!(empty.alternatives.is_empty());
+
+        // A file name alone is weak evidence
+        let named = classify_segments(&document("rent_roll.pdf"), &chunks(&[]));
+        assert_eq!(named.document_type.as_str(), "rent_roll");
+        assert!(named.confidence < LLM_FALLBACK_CONFIDENCE);
+    }
+}
@@ -1,3 +1,12 @@
+pub mod credit_memo;
 pub mod deal_agent;
+pub mod deal_export;
+pub mod deal_status;
+pub mod document_classifier;
+pub mod portfolio;
+pub mod pro_forma;
+pub mod profit_and_loss;
+pub mod reconciliation;
+pub mod rent_roll;
 pub mod underwriting;
 
@@ -0,0 +1,314 @@
+use crate::models::fact::{Fact, PeriodBasis};
+use crate::models::property::Property;
+use crate::models::underwriting_policy::PolicyRules;
+use crate::services::underwriting::{
+    base_debt_service, calculate_underwriting, input_values, UnderwritingInput,
+    UnderwritingResult,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// Loan fields that facts without a property, such as a blanket mortgage, give for the portfolio
+const PORTFOLIO_LOAN_FIELDS: &[&str] = &[
+    "debt_service",
+    "mortgage_balance",
+    "interest_rate",
+    "amortization_years",
+    "interest_only_years",
+    "loan_term_years",
+];
+
+/// Input of one property of a portfolio deal, read from the facts assigned to it
+#[derive(Debug, Clone)]
+pub struct PropertyInput {
+    pub property: Property,
+    pub input: UnderwritingInput,
+}
+
+/// Underwriting of one property of a portfolio deal
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PropertyUnderwriting {
+    pub property_id: String,
+    pub name: String,
+    pub result: UnderwritingResult,
+}
+
+/// The input a deal is underwritten with, and the input of each property of a portfolio
+#[derive(Debug, Clone)]
+pub struct DealInput {
+    pub input: UnderwritingInput,
+    /// Empty for deals without properties
+    pub properties: Vec<PropertyInput>,
+}
+
+impl DealInput {
+    /// Read the input of a deal from its approved facts
+    ///
+    /// Deals without properties are underwritten from all of their facts. Each property of a
+    /// portfolio is read from the facts assigned to it and the portfolio combines them. Fails
+    /// when collected rent or operating expenses are missing for the deal or any property.
+    pub fn from_facts(
+        properties: Vec<Property>,
+        facts: &[Fact],
+        period_basis: &PeriodBasis,
+        rules: &PolicyRules,
+    ) -> Result<Self, String> {
+        if properties.is_empty() {
+            let input = UnderwritingInput::from_facts(facts, period_basis).ok_or_else(|| {
+                "Missing required facts: collected_rent and operating_expenses".to_string()
+            })?;
+            return Ok(DealInput {
+                input,
+                properties: vec![],
+            });
+        }
+
+        let properties = properties
+            .into_iter()
+            .map(|property| {
+                let property_facts: Vec<Fact> = facts
+                    .iter()
+                    .filter(|f| f.property_id.as_deref() == Some(property.property_id.as_str()))
+                    .cloned()
+                    .collect();
+                match UnderwritingInput::from_facts(&property_facts, period_basis) {
+                    Some(input) => Ok(PropertyInput { property, input }),
+                    None => Err(format!(
+                        "Missing required facts for {}: collected_rent and operating_expenses",
+                        property.name
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let portfolio_facts: Vec<Fact> = facts
+            .iter()
+            .filter(|f| f.property_id.is_none())
+            .cloned()
+            .collect();
+
+        Ok(DealInput {
+            input: combine_inputs(&properties, &portfolio_facts, period_basis, rules),
+            properties,
+        })
+    }
+
+    /// IDs of the facts the deal and its properties were read from
+    pub fn source_fact_ids(&self) -> Vec<String> {
+        let mut ids = self.input.source_fact_ids();
+        for property in &self.properties {
+            ids.extend(property.input.source_fact_ids());
+        }
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Underwrite each property of a portfolio on its own
+    pub fn underwrite_properties(&self, rules: &PolicyRules) -> Vec<PropertyUnderwriting> {
+        self.properties
+            .iter()
+            .map(|property| PropertyUnderwriting {
+                property_id: property.property.property_id.clone(),
+                name: property.property.name.clone(),
+                result: calculate_underwriting(property.input.clone(), rules),
+            })
+            .collect()
+    }
+}
+
+/// Sum of a field over every property, `None` unless each property has it
+fn total(inputs: &[&UnderwritingInput], field: impl Fn(&UnderwritingInput) -> Option<f64>) -> Option<f64> {
+    inputs.iter().map(|input| field(input)).sum()
+}
+
+/// Sum of a field over the properties that have it, `None` when none has it
+fn partial_total(
+    inputs: &[&UnderwritingInput],
+    field: impl Fn(&UnderwritingInput) -> Option<f64>,
+) -> Option<f64> {
+    let values: Vec<f64> = inputs.iter().filter_map(|input| field(input)).collect();
+    (!values.is_empty()).then(|| values.iter().sum())
+}
+
+/// Average of a field weighted by another, over the properties that have both
+fn weighted_average(
+    inputs: &[&UnderwritingInput],
+    field: impl Fn(&UnderwritingInput) -> Option<f64>,
+    weight: impl Fn(&UnderwritingInput) -> Option<f64>,
+) -> Option<f64> {
+    let (sum, weights) = inputs
+        .iter()
+        .filter_map(|input| Some((field(input)?, weight(input)?)))
+        .fold((0.0, 0.0), |(sum, weights), (value, weight)| {
+            (sum + value * weight, weights + weight)
+        });
+    (weights > 0.0).then(|| sum / weights)
+}
+
+/// Combine the inputs of a portfolio's properties into the input of the portfolio
+///
+/// Rent, expenses and deposits add up. Units, potential rent, values and insurance only add up
+/// when every property has them, so that LTV is not understated by a property without a value.
+/// Occupancy is averaged by units. A loan given by facts without a property takes precedence
+/// over the properties' loans, whose debt service and balances add up and whose rates are
+/// averaged by balance. Each property's debt service is the one its own underwriting uses, so a
+/// loan given only by its balance and rate still counts.
+fn combine_inputs(
+    properties: &[PropertyInput],
+    portfolio_facts: &[Fact],
+    period_basis: &PeriodBasis,
+    rules: &PolicyRules,
+) -> UnderwritingInput {
+    let inputs: Vec<&UnderwritingInput> = properties.iter().map(|p| &p.input).collect();
+    let units = |input: &UnderwritingInput| input.unit_count.map(|count| count as f64);
+    let (loan_values, loan_sources) = input_values(portfolio_facts, period_basis);
+    let loan = |field: &str| {
+        PORTFOLIO_LOAN_FIELDS
+            .contains(&field)
+            .then(|| loan_values.get(field).copied())
+            .flatten()
+    };
+    let sources: BTreeMap<_, _> = loan_sources
+        .into_iter()
+        .filter(|(field, _)| PORTFOLIO_LOAN_FIELDS.contains(&field.as_str()))
+        .collect();
+
+    let unit_count = total(&inputs, units);
+    UnderwritingInput {
+        unit_count: unit_count.map(|count| count.round() as i32),
+        occupancy_rate: if inputs.iter().all(|i| i.unit_count.is_some() && i.occupancy_rate.is_some()) {
+            weighted_average(&inputs, |i| i.occupancy_rate, units)
+        } else {
+            None
+        },
+        gross_scheduled_rent: total(&inputs, |i| i.gross_scheduled_rent),
+        collected_rent: inputs.iter().map(|i| i.collected_rent).sum(),
+        operating_expenses: inputs.iter().map(|i| i.operating_expenses).sum(),
+        debt_service: loan("debt_service")
+            .or_else(|| partial_total(&inputs, |i| base_debt_service(i, rules))),
+        property_value: total(&inputs, |i| i.property_value),
+        mortgage_balance: loan("mortgage_balance")
+            .or_else(|| partial_total(&inputs, |i| i.mortgage_balance)),
+        interest_rate: loan("interest_rate")
+            .or_else(|| weighted_average(&inputs, |i| i.interest_rate, |i| i.mortgage_balance)),
+        amortization_years: loan("amortization_years"),
+        interest_only_years: loan("interest_only_years"),
+        loan_term_years: loan("loan_term_years"),
+        appraisal_cap_rate: None,
+        bank_deposits: partial_total(&inputs, |i| i.bank_deposits),
+        insurance_coverage: total(&inputs, |i| i.insurance_coverage),
+        sources,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::underwriting::loan_constant;
+
+    fn property(property_id: &str) -> Property {
+        Property {
+            property_id: property_id.to_string(),
+            deal_id: "deal-1".to_string(),
+            name: format!("Property {}", property_id),
+            address: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn fact(fact_id: &str, property_id: Option<&str>, fact_type: &str, value: &str) -> Fact {
+        Fact {
+            property_id: property_id.map(str::to_string),
+            ..Fact::for_test(fact_id, fact_type, value)
+        }
+    }
+
+    #[test]
+    fn test_portfolio_roll_up() {
+        let facts = vec![
+            fact("a-rent", Some("a"), "collected_rent", "300000"),
+            fact("a-opex", Some("a"), "operating_expenses", "100000"),
+            fact("a-value", Some("a"), "property_value", "2500000"),
+            fact("a-debt", Some("a"), "debt_service", "120000"),
+            fact("b-rent", Some("b"), "collected_rent", "200000"),
+            fact("b-opex", Some("b"), "operating_expenses", "80000"),
+            fact("b-value", Some("b"), "property_value", "1500000"),
+            fact("loan-balance", None, "mortgage_balance", "2800000"),
+            fact("loan-debt", None, "debt_service", "200000"),
+        ];
+        let deal = DealInput::from_facts(
+            vec![property("a"), property("b")],
+            &facts,
+            &PeriodBasis::default(),
+            &PolicyRules::default(),
+        )
+        .unwrap();
+
+        // The blanket loan replaces property A's own debt service
+        assert_eq!(deal.input.collected_rent, 500000.0);
+        assert_eq!(deal.input.operating_expenses, 180000.0);
+        assert_eq!(deal.input.property_value, Some(4000000.0));
+        assert_eq!(deal.input.debt_service, Some(200000.0));
+        assert_eq!(deal.input.mortgage_balance, Some(2800000.0));
+        assert_eq!(deal.source_fact_ids().len(), 9);
+
+        let result = calculate_underwriting(deal.input.clone(), &PolicyRules::default());
+        assert_eq!(result.noi, 320000.0);
+        assert_eq!(result.dscr, Some(1.6));
+        assert_eq!(result.ltv, Some(70.0));
+
+        let properties = deal.underwrite_properties(&PolicyRules::default());
+        assert_eq!(properties.len(), 2);
+        assert_eq!(properties[0].result.noi, 200000.0);
+        assert!(properties[1].result.dscr.is_none());
+        // Without a blanket loan, a loan given only by its balance and rate still adds its
+        // payments to the portfolio's debt service
+        let mixed = vec![
+            fact("a-rent", Some("a"), "collected_rent", "300000"),
+            fact("a-opex", Some("a"), "operating_expenses", "100000"),
+            fact("a-debt", Some("a"), "debt_service", "120000"),
+            fact("b-rent", Some("b"), "collected_rent", "200000"),
+            fact("b-opex", Some("b"), "operating_expenses", "80000"),
+            fact("b-balance", Some("b"), "mortgage_balance", "1000000"),
+            fact("b-rate", Some("b"), "interest_rate", "6"),
+            fact("b-amortization", Some("b"), "amortization_years", "30"),
+        ];
+        let deal = DealInput::from_facts(
+            vec![property("a"), property("b")],
+            &mixed,
+            &PeriodBasis::default(),
+            &PolicyRules::default(),
+        )
+        .unwrap();
+        let expected = 120000.0 + 1000000.0 * loan_constant(6.0, 30.0);
+        assert!((deal.input.debt_service.unwrap() - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_property_missing_facts() {
+        let facts = vec![
+            fact("a-rent", Some("a"), "collected_rent", "300000"),
+            fact("a-opex", Some("a"), "operating_expenses", "100000"),
+            fact("b-rent", Some("b"), "collected_rent", "200000"),
+        ];
+        let error = DealInput::from_facts(
+            vec![property("a"), property("b")],
+            &facts,
+            &PeriodBasis::default(),
+            &PolicyRules::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "Missing required facts for Property b: collected_rent and operating_expenses"
+        );
+
+        // Without properties every fact belongs to the deal
+        let deal =
+            DealInput::from_facts(vec![], &facts, &PeriodBasis::default(), &PolicyRules::default())
+                .unwrap();
+        assert!(deal.properties.is_empty());
+        assert_eq!(deal.input.operating_expenses, 100000.0);
+    }
+}
@@ -0,0 +1,554 @@
+use crate::models::underwriting_policy::PolicyRules;
+use crate::services::underwriting::{
+    potential_rent, CalculationStep, Loan, LoanYear, UnderwritingInput, MAX_LOAN_YEARS,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::ToSchema;
+
+/// Key of the assumptions in `Deal.metadata`
+pub const PRO_FORMA_METADATA_KEY: &str = "pro_forma";
+
+/// Per-deal assumptions for projecting cash flows. Rates and growth are in percent per year.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct ProFormaAssumptions {
+    pub hold_years: u32,
+    pub rent_growth: f64,
+    pub expense_growth: f64,
+    /// Vacancy applied to potential rent, the current occupancy is kept when not set
+    pub vacancy_rate: Option<f64>,
+    /// Capital reserves per unit in the first year, growing with expenses
+    pub capital_reserves_per_unit: f64,
+    /// Cap rate the property is valued at on refinance and exit
+    pub exit_cap_rate: f64,
+    /// Selling costs as a percentage of the sale price
+    pub selling_costs: f64,
+    /// Price the returns are measured against, defaults to the property value
+    pub purchase_price: Option<f64>,
+    pub refinance: Option<RefinanceAssumptions>,
+}
+
+impl Default for ProFormaAssumptions {
+    fn default() -> Self {
+        ProFormaAssumptions {
+            hold_years: 5,
+            rent_growth: 3.0,
+            expense_growth: 3.0,
+            vacancy_rate: None,
+            capital_reserves_per_unit: 250.0,
+            exit_cap_rate: 6.0,
+            selling_costs: 2.0,
+            purchase_price: None,
+            refinance: None,
+        }
+    }
+}
+
+/// A refinance at the end of a year, paying off the existing loan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RefinanceAssumptions {
+    pub year: u32,
+    pub ltv: f64,
+    pub interest_rate: f64,
+    pub amortization_years: f64,
+}
+
+impl ProFormaAssumptions {
+    /// Assumptions stored on a deal, or the defaults when none are stored
+    pub fn from_metadata(metadata: &JsonValue) -> Self {
+        metadata
+            .get(PRO_FORMA_METADATA_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(5..=10).contains(&self.hold_years) {
+            return Err("hold_years must be between 5 and 10".to_string());
+        }
+        if self.exit_cap_rate <= 0.0 {
+            return Err("exit_cap_rate must be positive".to_string());
+        }
+        if self.vacancy_rate.is_some_and(|v| !(0.0..=100.0).contains(&v)) {
+            return Err("vacancy_rate must be between 0 and 100".to_string());
+        }
+        if !(0.0..100.0).contains(&self.selling_costs) {
+            return Err("selling_costs must be between 0 and 100".to_string());
+        }
+        if let Some(refinance) = &self.refinance {
+            if refinance.year == 0 || refinance.year >= self.hold_years {
+                return Err("refinance year must fall before the exit".to_string());
+            }
+            if refinance.ltv <= 0.0 {
+                return Err("refinance ltv must be positive".to_string());
+            }
+            if refinance.amortization_years <= 0.0 || refinance.amortization_years > MAX_LOAN_YEARS
+            {
+                return Err(format!(
+                    "refinance amortization_years must be between 0 and {}",
+                    MAX_LOAN_YEARS
+                ));
+            }
+            if !(0.0..=100.0).contains(&refinance.interest_rate) {
+                return Err("refinance interest_rate must be between 0 and 100".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProFormaYear {
+    pub year: u32,
+    pub rent: f64,
+    pub operating_expenses: f64,
+    pub noi: f64,
+    pub capital_reserves: f64,
+    pub debt_service: f64,
+    pub dscr: Option<f64>,
+    pub loan_balance: f64,
+    pub unlevered_cash_flow: f64,
+    pub levered_cash_flow: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProFormaExit {
+    pub year: u32,
+    /// NOI of the year after the exit, which the buyer prices
+    pub forward_noi: f64,
+    pub sale_price: f64,
+    pub selling_costs: f64,
+    pub loan_payoff: f64,
+    pub net_proceeds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProFormaRefinance {
+    pub year: u32,
+    pub property_value: f64,
+    pub new_loan_amount: f64,
+    pub loan_payoff: f64,
+    pub cash_out: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProForma {
+    pub assumptions: ProFormaAssumptions,
+    pub purchase_price: f64,
+    pub equity: f64,
+    pub years: Vec<ProFormaYear>,
+    pub refinance: Option<ProFormaRefinance>,
+    pub exit: ProFormaExit,
+    pub unlevered_irr: Option<f64>,
+    pub levered_irr: Option<f64>,
+    pub unlevered_equity_multiple: Option<f64>,
+    pub levered_equity_multiple: Option<f64>,
+    pub audit_trail: Vec<CalculationStep>,
+    pub warnings: Vec<String>,
+}
+
+/// Debt serviced in the projection
+enum Debt {
+    /// Payments follow a loan's schedule from the end of `start_year`
+    Amortizing { schedule: Vec<LoanYear>, start_year: u32 },
+    /// Debt service without loan terms, the balance never pays down
+    Flat { debt_service: f64, balance: f64 },
+}
+
+impl Debt {
+    /// Debt service paid in a projection year and the balance left at its end
+    fn year(&self, year: u32) -> (f64, f64) {
+        match self {
+            Debt::Amortizing { schedule, start_year } => schedule
+                .get((year - start_year - 1) as usize)
+                .map(|y| (y.debt_service, y.ending_balance))
+                .unwrap_or((0.0, 0.0)),
+            Debt::Flat { debt_service, balance } => (*debt_service, *balance),
+        }
+    }
+}
+
+/// Schedule of a loan over the remaining projection, extending its term past the exit
+fn amortizing(loan: Loan, start_year: u32, hold_years: u32) -> Debt {
+    let remaining = f64::from(hold_years - start_year);
+    let loan = Loan {
+        term_years: loan.term_years.max(remaining),
+        ..loan
+    };
+    Debt::Amortizing {
+        schedule: loan.annual_schedule(),
+        start_year,
+    }
+}
+
+/// Project yearly cash flows from the base inputs and return the deal's IRR and equity multiple
+///
+/// The facts are taken as the trailing year, so the first projected year already applies a
+/// year of growth. The existing loan is carried with the policy's loan terms where the facts do
+/// not give them and is assumed to extend at the same rate when it matures before the exit.
+pub fn project_pro_forma(
+    input: &UnderwritingInput,
+    rules: &PolicyRules,
+    assumptions: &ProFormaAssumptions,
+) -> Result<ProForma, String> {
+    assumptions.validate()?;
+    let purchase_price = assumptions
+        .purchase_price
+        .or(input.property_value)
+        .filter(|price| *price > 0.0)
+        .ok_or("Purchase price or property value is required for a pro forma")?;
+
+    let mut audit_trail = Vec::new();
+    let mut warnings = Vec::new();
+    let growth = |rate: f64, year: u32| (1.0 + rate / 100.0).powi(year as i32);
+
+    // Rent before growth: potential rent net of the assumed vacancy, or rent as collected
+    let (base_rent, rent_fields) = match (assumptions.vacancy_rate, potential_rent(input)) {
+        (Some(vacancy), Some((potential, mut fields))) => {
+            let rent = potential * (1.0 - vacancy / 100.0);
+            let (sources, citations) = input.sources_for(&fields);
+            audit_trail.push(CalculationStep {
+                metric: "Stabilized Rent".to_string(),
+                formula: "Potential Rent * (1 - Vacancy Rate / 100)".to_string(),
+                inputs: vec![
+                    ("Potential Rent".to_string(), potential),
+                    ("Vacancy Rate (%)".to_string(), vacancy),
+                ],
+                result: rent,
+                sources,
+                citations,
+            });
+            fields.push("collected_rent");
+            (rent, fields)
+        }
+        (Some(_), None) => {
+            warnings.push(
+                "Note: Potential rent unknown - vacancy assumption ignored, collected rent projected"
+                    .to_string(),
+            );
+            (input.collected_rent, vec!["collected_rent"])
+        }
+        (None, _) => (input.collected_rent, vec!["collected_rent"]),
+    };
+
+    let units = input.unit_count.filter(|count| *count > 0).map(f64::from);
+    if units.is_none() && assumptions.capital_reserves_per_unit > 0.0 {
+        warnings.push("Note: Unit count unknown - capital reserves not projected".to_string());
+    }
+    let base_reserves = units.unwrap_or(0.0) * assumptions.capital_reserves_per_unit;
+
+    let project_noi = |year: u32| {
+        let rent = base_rent * growth(assumptions.rent_growth, year);
+        let expenses = input.operating_expenses * growth(assumptions.expense_growth, year);
+        (rent, expenses, rent - expenses)
+    };
+
+    let mut debt = match (Loan::from_input(input, rules), input.debt_service) {
+        (Some(loan), _) => Some(amortizing(loan, 0, assumptions.hold_years)),
+        (None, Some(debt_service)) => Some(Debt::Flat {
+            debt_service,
+            balance: input.mortgage_balance.unwrap_or(0.0),
+        }),
+        (None, None) => None,
+    };
+    let initial_balance = match debt {
+        Some(_) => input.mortgage_balance.unwrap_or(0.0),
+        None => 0.0,
+    };
+    let equity = purchase_price - initial_balance;
+    let mut noi_fields = rent_fields.clone();
+    noi_fields.push("operating_expenses");
+
+    let mut years = Vec::with_capacity(assumptions.hold_years as usize);
+    let mut refinance = None;
+    for year in 1..=assumptions.hold_years {
+        let (rent, operating_expenses, noi) = project_noi(year);
+        let capital_reserves = base_reserves * growth(assumptions.expense_growth, year - 1);
+        let (debt_service, mut loan_balance) =
+            debt.as_ref().map(|d| d.year(year)).unwrap_or((0.0, 0.0));
+        let dscr = (debt_service > 0.0).then(|| noi / debt_service);
+        let unlevered_cash_flow = noi - capital_reserves;
+        let mut levered_cash_flow = unlevered_cash_flow - debt_service;
+
+        let (sources, citations) = input.sources_for(&noi_fields);
+        audit_trail.push(CalculationStep {
+            metric: format!("Year {} NOI", year),
+            formula: "Rent * (1 + Rent Growth)^Year - Operating Expenses * (1 + Expense Growth)^Year"
+                .to_string(),
+            inputs: vec![
+                ("Rent".to_string(), rent),
+                ("Operating Expenses".to_string(), operating_expenses),
+            ],
+            result: noi,
+            sources,
+            citations,
+        });
+
+        // Refinance at the value the next year's NOI supports
+        if let Some(terms) = assumptions
+            .refinance
+            .as_ref()
+            .filter(|terms| terms.year == year)
+        {
+            let property_value = project_noi(year + 1).2 / (assumptions.exit_cap_rate / 100.0);
+            let new_loan_amount = property_value * terms.ltv / 100.0;
+            let cash_out = new_loan_amount - loan_balance;
+            audit_trail.push(CalculationStep {
+                metric: format!("Year {} Refinance Cash Out", year),
+                formula: "Next Year NOI / Exit Cap Rate * LTV - Loan Payoff".to_string(),
+                inputs: vec![
+                    ("Property Value".to_string(), property_value),
+                    ("LTV (%)".to_string(), terms.ltv),
+                    ("Loan Payoff".to_string(), loan_balance),
+                ],
+                result: cash_out,
+                sources: vec![],
+                citations: vec![],
+            });
+            refinance = Some(ProFormaRefinance {
+                year,
+                property_value,
+                new_loan_amount,
+                loan_payoff: loan_balance,
+                cash_out,
+            });
+            levered_cash_flow += cash_out;
+            loan_balance = new_loan_amount;
+            debt = Some(amortizing(
+                Loan {
+                    balance: new_loan_amount,
+                    interest_rate: terms.interest_rate,
+                    amortization_years: terms.amortization_years,
+                    interest_only_years: 0.0,
+                    term_years: f64::from(assumptions.hold_years - year),
+                },
+                year,
+                assumptions.hold_years,
+            ));
+        }
+
+        years.push(ProFormaYear {
+            year,
+            rent,
+            operating_expenses,
+            noi,
+            capital_reserves,
+            debt_service,
+            dscr,
+            loan_balance,
+            unlevered_cash_flow,
+            levered_cash_flow,
+        });
+    }
+
+    // Sell at the end of the hold to a buyer pricing the next year's NOI
+    let hold_years = assumptions.hold_years;
+    let forward_noi = project_noi(hold_years + 1).2;
+    let sale_price = forward_noi / (assumptions.exit_cap_rate / 100.0);
+    let selling_costs = sale_price * assumptions.selling_costs / 100.0;
+    let last = years.last_mut().ok_or("Pro forma has no years")?;
+    let loan_payoff = last.loan_balance;
+    let net_proceeds = sale_price - selling_costs - loan_payoff;
+    audit_trail.push(CalculationStep {
+        metric: "Exit Sale Price".to_string(),
+        formula: "Year After Exit NOI / (Exit Cap Rate / 100)".to_string(),
+        inputs: vec![
+            ("Forward NOI".to_string(), forward_noi),
+            ("Exit Cap Rate (%)".to_string(), assumptions.exit_cap_rate),
+        ],
+        result: sale_price,
+        sources: vec![],
+        citations: vec![],
+    });
+    last.unlevered_cash_flow += sale_price - selling_costs;
+    last.levered_cash_flow += net_proceeds;
+
+    let unlevered_flows: Vec<f64> = std::iter::once(-purchase_price)
+        .chain(years.iter().map(|y| y.unlevered_cash_flow))
+        .collect();
+    let levered_flows: Vec<f64> = std::iter::once(-equity)
+        .chain(years.iter().map(|y| y.levered_cash_flow))
+        .collect();
+    if equity <= 0.0 {
+        warnings.push("Note: Loan balance exceeds purchase price - levered returns not calculated".to_string());
+    }
+
+    let unlevered_irr = irr(&unlevered_flows);
+    let levered_irr = (equity > 0.0).then(|| irr(&levered_flows)).flatten();
+    let unlevered_equity_multiple = equity_multiple(&unlevered_flows);
+    let levered_equity_multiple = (equity > 0.0)
+        .then(|| equity_multiple(&levered_flows))
+        .flatten();
+
+    for (metric, flows, value) in [
+        ("Unlevered IRR", &unlevered_flows, unlevered_irr),
+        ("Levered IRR", &levered_flows, levered_irr),
+    ] {
+        if let Some(value) = value {
+            audit_trail.push(CalculationStep {
+                metric: metric.to_string(),
+                formula: "Rate at which the NPV of yearly cash flows is zero".to_string(),
+                inputs: flows
+                    .iter()
+                    .enumerate()
+                    .map(|(year, flow)| (format!("Year {} Cash Flow", year), *flow))
+                    .collect(),
+                result: value,
+                sources: vec![],
+                citations: vec![],
+            });
+        }
+    }
+
+    Ok(ProForma {
+        assumptions: assumptions.clone(),
+        purchase_price,
+        equity,
+        years,
+        refinance,
+        exit: ProFormaExit {
+            year: hold_years,
+            forward_noi,
+            sale_price,
+            selling_costs,
+            loan_payoff,
+            net_proceeds,
+        },
+        unlevered_irr,
+        levered_irr,
+        unlevered_equity_multiple,
+        levered_equity_multiple,
+        audit_trail,
+        warnings,
+    })
+}
+
+/// Net present value of yearly cash flows, the first at year zero
+fn npv(rate: f64, flows: &[f64]) -> f64 {
+    flows
+        .iter()
+        .enumerate()
+        .map(|(year, flow)| flow / (1.0 + rate).powi(year as i32))
+        .sum()
+}
+
+/// Internal rate of return in percent, found by bisection
+///
+/// Returns `None` when the NPV does not change sign between -99% and 1000%.
+pub fn irr(flows: &[f64]) -> Option<f64> {
+    let (mut low, mut high) = (-0.99, 10.0);
+    let (npv_low, npv_high) = (npv(low, flows), npv(high, flows));
+    if npv_low.signum() == npv_high.signum() {
+        return None;
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if npv(mid, flows).signum() == npv_low.signum() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0 * 100.0)
+}
+
+/// Cash returned over cash invested
+fn equity_multiple(flows: &[f64]) -> Option<f64> {
+    let invested: f64 = flows.iter().filter(|f| **f < 0.0).map(|f| -f).sum();
+    let returned: f64 = flows.iter().filter(|f| **f > 0.0).sum();
+    (invested > 0.0).then(|| returned / invested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_irr() {
+        // 1,000 returning 100 a year and the principal after 5 years yields 10%
+        let flows = [-1000.0, 100.0, 100.0, 100.0, 100.0, 1100.0];
+        assert!((irr(&flows).unwrap() - 10.0).abs() < 1e-6);
+        assert_eq!(equity_multiple(&flows), Some(1.5));
+        assert!(irr(&[100.0, 100.0]).is_none());
+    }
+
+    #[test]
+    fn test_project_pro_forma() {
+        let input = UnderwritingInput {
+            unit_count: Some(10),
+            occupancy_rate: Some(95.0),
+            gross_scheduled_rent: None,
+            collected_rent: 114000.0,
+            operating_expenses: 44000.0,
+            debt_service: None,
+            property_value: Some(1000000.0),
+            mortgage_balance: Some(600000.0),
+            interest_rate: Some(6.0),
+            amortization_years: Some(30.0),
+            interest_only_years: None,
+            loan_term_years: Some(10.0),
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
+        };
+        let assumptions = ProFormaAssumptions {
+            rent_growth: 0.0,
+            expense_growth: 0.0,
+            capital_reserves_per_unit: 0.0,
+            exit_cap_rate: 7.0,
+            selling_costs: 0.0,
+            ..ProFormaAssumptions::default()
+        };
+
+        // Flat NOI of 70,000 bought and sold at a 7% cap rate returns 7% unlevered
+        let pro_forma = project_pro_forma(&input, &PolicyRules::default(), &assumptions).unwrap();
+        assert_eq!(pro_forma.years.len(), 5);
+        assert_eq!(pro_forma.equity, 400000.0);
+        assert!((pro_forma.exit.sale_price - 1000000.0).abs() < 1e-6);
+        assert!((pro_forma.unlevered_irr.unwrap() - 7.0).abs() < 1e-6);
+        // Positive leverage at a 6% rate lifts the levered return
+        assert!(pro_forma.levered_irr.unwrap() > 7.0);
+        let first = &pro_forma.years[0];
+        assert!((first.dscr.unwrap() - 70000.0 / first.debt_service).abs() < 1e-9);
+        assert!(pro_forma.years[4].loan_balance < 600000.0);
+
+        // A 5% vacancy against 120,000 potential rent matches current occupancy
+        let with_vacancy = ProFormaAssumptions {
+            vacancy_rate: Some(5.0),
+            refinance: Some(RefinanceAssumptions {
+                year: 3,
+                ltv: 70.0,
+                interest_rate: 5.0,
+                amortization_years: 30.0,
+            }),
+            ..assumptions.clone()
+        };
+        let refinanced =
+            project_pro_forma(&input, &PolicyRules::default(), &with_vacancy).unwrap();
+        assert!((refinanced.years[0].noi - 70000.0).abs() < 1e-6);
+        let refinance = refinanced.refinance.unwrap();
+        assert!((refinance.new_loan_amount - 700000.0).abs() < 1e-6);
+        assert!(refinance.cash_out > 100000.0);
+        assert!((refinanced.exit.loan_payoff - refinanced.years[4].loan_balance).abs() < 1e-9);
+
+        let invalid = ProFormaAssumptions {
+            hold_years: 12,
+            ..assumptions
+        };
+        assert!(project_pro_forma(&input, &PolicyRules::default(), &invalid).is_err());
+        let unbounded = ProFormaAssumptions {
+            refinance: Some(RefinanceAssumptions {
+                amortization_years: 1e9,
+                ..with_vacancy.refinance.clone().unwrap()
+            }),
+            ..with_vacancy
+        };
+        assert_eq!(
+            unbounded.validate(),
+            Err("refinance amortization_years must be between 0 and 50".to_string())
+        );
+    }
+}
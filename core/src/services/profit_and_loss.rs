@@ -0,0 +1,576 @@
+use crate::models::document::Document;
+use crate::models::fact::{FactType, NewFact, PeriodBasis, SourceCitation};
+use crate::models::output::{Chunk, SegmentType};
+use crate::models::pl_line_item::{LineItemCategory, NewPlLineItem};
+use crate::services::rent_roll::parse_amount;
+use crate::utils::services::fact_extraction::segment_citation;
+use crate::utils::services::html::table_rows;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+static MONTH_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?(?:[\s\-/']*(\d{4}|\d{2}))?$")
+        .unwrap()
+});
+static MONTH_YEAR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d{1,2})[/\-](\d{4})$").unwrap());
+static YEAR_MONTH_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d{4})[/\-](\d{1,2})$").unwrap());
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Income,
+    Expense,
+}
+
+/// A line item parsed from P&L tables, with an amount for each period of the statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedLineItem {
+    pub table_idx: usize,
+    pub label: String,
+    pub category: LineItemCategory,
+    pub amounts: Vec<Option<f64>>,
+    /// Cell text of the first row of the line item, used as the citation line
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedStatement {
+    pub periods: Vec<String>,
+    pub line_items: Vec<ParsedLineItem>,
+}
+
+/// Parse a monthly column header such as `Jan 2024`, `Jan-24`, `01/2024` or `2024-01`
+///
+/// Returns `YYYY-MM` when the year is known, otherwise the month abbreviation.
+fn parse_period(cell: &str) -> Option<String> {
+    let cell = cell.trim();
+    let month_year = |month: u32, year: i32| {
+        if (1..=12).contains(&month) {
+            Some(format!("{:04}-{:02}", year, month))
+        } else {
+            None
+        }
+    };
+
+    if let Some(caps) = MONTH_REGEX.captures(cell) {
+        let month_idx = MONTHS
+            .iter()
+            .position(|m| caps[1].eq_ignore_ascii_case(m))?;
+        return match caps.get(2) {
+            Some(year) => {
+                let year: i32 = year.as_str().parse().ok()?;
+                let year = if year < 100 { 2000 + year } else { year };
+                month_year(month_idx as u32 + 1, year)
+            }
+            None => {
+                let month = MONTHS[month_idx];
+                Some(format!("{}{}", month[..1].to_uppercase(), &month[1..]))
+            }
+        };
+    }
+    if let Some(caps) = MONTH_YEAR_REGEX.captures(cell) {
+        return month_year(caps[1].parse().ok()?, caps[2].parse().ok()?);
+    }
+    if let Some(caps) = YEAR_MONTH_REGEX.captures(cell) {
+        return month_year(caps[2].parse().ok()?, caps[1].parse().ok()?);
+    }
+    None
+}
+
+/// Period of each column of a header row, or `None` when the row has fewer than two periods
+fn header_periods(cells: &[String]) -> Option<Vec<Option<String>>> {
+    let periods: Vec<Option<String>> = cells.iter().map(|c| parse_period(c)).collect();
+    if periods.iter().flatten().count() >= 2 {
+        Some(periods)
+    } else {
+        None
+    }
+}
+
+/// Category of a line, or `None` for subtotals and items below net operating income
+fn classify_line(label: &str, section: Option<Section>) -> Option<LineItemCategory> {
+    let label = label.to_lowercase();
+    let has = |keywords: &[&str]| keywords.iter().any(|k| label.contains(k));
+
+    if has(&[
+        "total",
+        "net operating income",
+        "net income",
+        "cash flow",
+        "effective gross",
+        "debt service",
+        "mortgage",
+        "interest",
+        "principal",
+        "depreciation",
+        "amortization",
+        "capital",
+        "capex",
+        "reserve",
+    ]) || label.starts_with("noi")
+    {
+        return None;
+    }
+    if has(&["vacancy", "concession", "bad debt", "loss to lease", "credit loss", "write off", "write-off"]) {
+        return Some(LineItemCategory::VacancyAndCreditLoss);
+    }
+    if has(&["reimburs", "laundry", "parking", "other income", "late fee", "application fee", "pet fee", "storage"]) {
+        return Some(LineItemCategory::OtherIncome);
+    }
+    // Before taxes, so that "Payroll Taxes" is payroll
+    if has(&["payroll", "salar", "wage", "personnel", "benefits"]) {
+        return Some(LineItemCategory::Payroll);
+    }
+    if has(&["tax"]) {
+        return Some(LineItemCategory::Taxes);
+    }
+    if has(&["insurance"]) {
+        return Some(LineItemCategory::Insurance);
+    }
+    if has(&["utilit", "electric", "water", "sewer", "gas", "trash", "refuse"]) {
+        return Some(LineItemCategory::Utilities);
+    }
+    if has(&["repair", "maint", "r&m", "turnover", "make ready", "landscap", "pest", "cleaning", "janitorial"]) {
+        return Some(LineItemCategory::RepairsAndMaintenance);
+    }
+    if has(&["management", "mgmt"]) {
+        return Some(LineItemCategory::Management);
+    }
+    if has(&["admin", "office", "legal", "accounting", "professional", "bank fee", "telephone"]) {
+        return Some(LineItemCategory::Administrative);
+    }
+    if has(&["marketing", "advertis", "leasing commission"]) {
+        return Some(LineItemCategory::Marketing);
+    }
+    if has(&["rent", "rental"]) {
+        return Some(LineItemCategory::RentalIncome);
+    }
+    match section {
+        Some(Section::Income) => Some(LineItemCategory::OtherIncome),
+        Some(Section::Expense) => Some(LineItemCategory::OtherExpense),
+        None => None,
+    }
+}
+
+/// Parse the line items of a sequence of P&L tables into a period × line item matrix
+///
+/// Tables without a header row continue the periods of the previous table. Line items with
+/// the same label in several tables, such as statements split by quarter, are merged.
+pub fn parse_profit_and_loss_tables(tables: &[&str]) -> ParsedStatement {
+    let mut columns: Option<Vec<Option<String>>> = None;
+    let mut section: Option<Section> = None;
+    let mut periods: Vec<String> = Vec::new();
+    let mut items: Vec<(ParsedLineItem, HashMap<String, f64>)> = Vec::new();
+
+    for (table_idx, html) in tables.iter().enumerate() {
+        for cells in table_rows(html) {
+            if let Some(header) = header_periods(&cells) {
+                for period in header.iter().flatten() {
+                    if !periods.contains(period) {
+                        periods.push(period.clone());
+                    }
+                }
+                columns = Some(header);
+                continue;
+            }
+            let columns = match &columns {
+                Some(columns) => columns,
+                None => continue,
+            };
+            let label = match cells
+                .iter()
+                .enumerate()
+                .find(|(idx, cell)| columns.get(*idx).is_none_or(|p| p.is_none()) && !cell.is_empty())
+            {
+                Some((_, label)) => label.clone(),
+                None => continue,
+            };
+
+            let amounts: HashMap<String, f64> = columns
+                .iter()
+                .zip(cells.iter())
+                .filter_map(|(period, cell)| Some((period.clone()?, parse_amount(cell)?)))
+                .collect();
+            if amounts.is_empty() {
+                let lowered = label.to_lowercase();
+                if lowered.contains("expense") {
+                    section = Some(Section::Expense);
+                } else if lowered.contains("income") || lowered.contains("revenue") {
+                    section = Some(Section::Income);
+                }
+                continue;
+            }
+
+            let category = match classify_line(&label, section) {
+                Some(category) => category,
+                None => continue,
+            };
+            match items
+                .iter_mut()
+                .find(|(item, _)| item.category == category && item.label.eq_ignore_ascii_case(&label))
+            {
+                Some((_, existing)) => existing.extend(amounts),
+                None => {
+                    let line = cells
+                        .iter()
+                        .filter(|c| !c.is_empty())
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(" | ");
+                    items.push((
+                        ParsedLineItem {
+                            table_idx,
+                            label,
+                            category,
+                            amounts: vec![],
+                            line,
+                        },
+                        amounts,
+                    ));
+                }
+            }
+        }
+    }
+
+    // Months with a year sort chronologically, otherwise the column order is kept
+    if periods.iter().all(|p| p.len() == 7) {
+        periods.sort();
+    }
+    let line_items = items
+        .into_iter()
+        .map(|(mut item, amounts)| {
+            item.amounts = periods.iter().map(|p| amounts.get(p).copied()).collect();
+            item
+        })
+        .collect();
+    ParsedStatement {
+        periods,
+        line_items,
+    }
+}
+
+/// Parse the line items of the table segments of a P&L document
+pub fn line_items_from_chunks(document: &Document, chunks: &[Chunk]) -> Vec<NewPlLineItem> {
+    let segments: Vec<_> = chunks
+        .iter()
+        .flat_map(|chunk| chunk.segments.iter())
+        .filter(|segment| segment.segment_type == SegmentType::Table && !segment.html.is_empty())
+        .collect();
+    let tables: Vec<&str> = segments.iter().map(|s| s.html.as_str()).collect();
+    let statement = parse_profit_and_loss_tables(&tables);
+
+    statement
+        .line_items
+        .into_iter()
+        .enumerate()
+        .map(|(row_index, item)| {
+            let mut citation = segment_citation(document, segments[item.table_idx]);
+            citation.line = Some(item.line);
+            NewPlLineItem {
+                pl_line_item_id: Uuid::new_v4().to_string(),
+                document_id: document.document_id.clone(),
+                deal_id: document.deal_id.clone(),
+                row_index: row_index as i32,
+                category: item.category.as_str().to_string(),
+                label: item.label,
+                periods: statement.periods.clone(),
+                amounts: item.amounts,
+                source_citation: json!(citation),
+            }
+        })
+        .collect()
+}
+
+/// Annual total of monthly values on the given basis, if there are enough periods and each of
+/// them has data
+fn basis_total(values: &[f64], present: &[bool], basis: &PeriodBasis) -> Option<f64> {
+    let n = values.len();
+    let start = match basis {
+        PeriodBasis::Trailing12 if n >= 12 => n - 12,
+        PeriodBasis::Trailing3 if n >= 3 => n - 3,
+        PeriodBasis::Annualized if n >= 1 => 0,
+        _ => return None,
+    };
+    // A month without data would count as zero and understate the total
+    if present[start..].iter().any(|present| !present) {
+        return None;
+    }
+    let sum: f64 = values[start..].iter().sum();
+    let total = sum * 12.0 / (n - start) as f64;
+    Some((total * 100.0).round() / 100.0)
+}
+
+/// Derive collected rent, operating expenses and NOI facts from P&L line items
+///
+/// Each figure is computed on a trailing-12, trailing-3 and annualized basis when the statement
+/// has enough periods and every period of the basis has data. Collected rent is rental income
+/// net of vacancy and credit loss; NOI also includes other income.
+pub fn facts_from_line_items(document: &Document, items: &[NewPlLineItem]) -> Vec<NewFact> {
+    let first = match items.first() {
+        Some(first) => first,
+        None => return vec![],
+    };
+    let period_count = first.periods.len();
+    let mut rent = vec![0.0; period_count];
+    let mut other_income = vec![0.0; period_count];
+    let mut expenses = vec![0.0; period_count];
+    // Periods where some rental income or expense line has a value
+    let mut rent_present = vec![false; period_count];
+    let mut expenses_present = vec![false; period_count];
+    let (mut has_rent, mut has_expenses) = (false, false);
+    for item in items {
+        let category = match LineItemCategory::from_str(&item.category) {
+            Some(category) => category,
+            None => continue,
+        };
+        for (idx, amount) in item.amounts.iter().enumerate().take(period_count) {
+            match category {
+                _ if amount.is_none() => {}
+                LineItemCategory::RentalIncome => rent_present[idx] = true,
+                category if !category.is_income() => expenses_present[idx] = true,
+                _ => {}
+            }
+            let amount = amount.unwrap_or(0.0);
+            match category {
+                LineItemCategory::RentalIncome => rent[idx] += amount,
+                LineItemCategory::VacancyAndCreditLoss => rent[idx] -= amount.abs(),
+                LineItemCategory::OtherIncome => other_income[idx] += amount,
+                _ => expenses[idx] += amount,
+            }
+        }
+        match category {
+            LineItemCategory::RentalIncome => has_rent = true,
+            category if !category.is_income() => has_expenses = true,
+            _ => {}
+        }
+    }
+    let noi: Vec<f64> = (0..period_count)
+        .map(|idx| rent[idx] + other_income[idx] - expenses[idx])
+        .collect();
+    let noi_present: Vec<bool> = (0..period_count)
+        .map(|idx| rent_present[idx] && expenses_present[idx])
+        .collect();
+
+    let citation: SourceCitation = serde_json::from_value(first.source_citation.clone())
+        .unwrap_or(SourceCitation {
+            document: document.file_name.clone(),
+            page: 1,
+            line: None,
+            bbox: None,
+            page_width: None,
+            page_height: None,
+        });
+
+    let mut figures: Vec<(FactType, &str, &[f64], &[bool])> = Vec::new();
+    if has_rent {
+        figures.push((FactType::CollectedRent, "Collected Rent", &rent, &rent_present));
+    }
+    if has_expenses {
+        figures.push((
+            FactType::OperatingExpenses,
+            "Operating Expenses",
+            &expenses,
+            &expenses_present,
+        ));
+    }
+    if has_rent && has_expenses {
+        figures.push((FactType::NetOperatingIncome, "Net Operating Income", &noi, &noi_present));
+    }
+
+    let mut facts = Vec::new();
+    for basis in [
+        PeriodBasis::Trailing12,
+        PeriodBasis::Trailing3,
+        PeriodBasis::Annualized,
+    ] {
+        let basis_label = match basis {
+            PeriodBasis::Trailing12 => "T-12",
+            PeriodBasis::Trailing3 => "T-3 annualized",
+            PeriodBasis::Annualized => "annualized",
+        };
+        for (fact_type, label, values, present) in &figures {
+            let total = match basis_total(values, present, &basis) {
+                Some(total) => total,
+                None => continue,
+            };
+            let mut citation = citation.clone();
+            citation.line = Some(format!(
+                "{} ({}) over {} periods of the operating statement",
+                label, basis_label, period_count
+            ));
+            facts.push(NewFact {
+                fact_id: Uuid::new_v4().to_string(),
+                document_id: document.document_id.clone(),
+                deal_id: document.deal_id.clone(),
+                property_id: document.property_id.clone(),
+                fact_type: fact_type.as_str().to_string(),
+                label: format!("{} ({})", label, basis_label),
+                value: total.to_string(),
+                unit: Some("USD/year".to_string()),
+                source_citation: json!(citation),
+                status: "pending_approval".to_string(),
+                confidence_score: Some(0.9),
+                period_basis: Some(basis.as_str().to_string()),
+            });
+        }
+    }
+    facts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn month_header(months: &[&str]) -> String {
+        let cells: String = months.iter().map(|m| format!("<th>{}</th>", m)).collect();
+        format!("<tr><th>Account</th>{}<th>Total</th></tr>", cells)
+    }
+
+    fn row(label: &str, amounts: &[&str]) -> String {
+        let cells: String = amounts.iter().map(|a| format!("<td>{}</td>", a)).collect();
+        format!("<tr><td>{}</td>{}</tr>", label, cells)
+    }
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(parse_period("Jan 2024").as_deref(), Some("2024-01"));
+        assert_eq!(parse_period("Sept-23").as_deref(), Some("2023-09"));
+        assert_eq!(parse_period("03/2024").as_deref(), Some("2024-03"));
+        assert_eq!(parse_period("2024-11").as_deref(), Some("2024-11"));
+        assert_eq!(parse_period("February").as_deref(), Some("Feb"));
+        assert_eq!(parse_period("Total"), None);
+        assert_eq!(parse_period("Insurance"), None);
+    }
+
+    #[test]
+    fn test_parse_profit_and_loss_tables() {
+        // Statement split by quarter across two tables, listed out of order
+        let q2 = format!(
+            "<table>{}{}{}{}</table>",
+            month_header(&["Apr 2024", "May 2024", "Jun 2024"]),
+            row("Rental Income", &["$10,000", "$10,000", "$10,500", "$30,500"]),
+            row("Real Estate Taxes", &["1,000", "1,000", "1,000", "3,000"]),
+            row("Total Expenses", &["1,000", "1,000", "1,000", "3,000"]),
+        );
+        let q1 = format!(
+            "<table>{}{}{}{}{}{}</table>",
+            month_header(&["Jan 2024", "Feb 2024", "Mar 2024"]),
+            row("Income", &[]),
+            row("Rental Income", &["$9,000", "$9,000", "$9,500", "$27,500"]),
+            row("Vacancy Loss", &["(500)", "(500)", "-", "(1,000)"]),
+            row("Expenses", &[]),
+            row("Real Estate Taxes", &["1,000", "1,000", "1,000", "3,000"]),
+        );
+        let statement = parse_profit_and_loss_tables(&[&q2, &q1]);
+        assert_eq!(
+            statement.periods,
+            vec!["2024-01", "2024-02", "2024-03", "2024-04", "2024-05", "2024-06"]
+        );
+        assert_eq!(statement.line_items.len(), 3);
+
+        let rent = &statement.line_items[0];
+        assert_eq!(rent.category, LineItemCategory::RentalIncome);
+        assert_eq!(
+            rent.amounts,
+            vec![
+                Some(9000.0),
+                Some(9000.0),
+                Some(9500.0),
+                Some(10000.0),
+                Some(10000.0),
+                Some(10500.0)
+            ]
+        );
+        assert_eq!(statement.line_items[1].category, LineItemCategory::Taxes);
+        let vacancy = &statement.line_items[2];
+        assert_eq!(vacancy.category, LineItemCategory::VacancyAndCreditLoss);
+        assert_eq!(vacancy.amounts[0], Some(-500.0));
+        assert_eq!(vacancy.amounts[2], None);
+    }
+
+    #[test]
+    fn test_facts_from_line_items() {
+        let document = Document {
+            classification_source: Some("user".to_string()),
+            ..Document::for_test("t12.pdf", "profit_and_loss")
+        };
+        let periods: Vec<String> = (1..=12).map(|m| format!("2024-{:02}", m)).collect();
+        let line = |category: LineItemCategory, amounts: Vec<Option<f64>>| NewPlLineItem {
+            pl_line_item_id: Uuid::new_v4().to_string(),
+            document_id: document.document_id.clone(),
+            deal_id: document.deal_id.clone(),
+            row_index: 0,
+            category: category.as_str().to_string(),
+            label: category.as_str().to_string(),
+            periods: periods.clone(),
+            amounts,
+            source_citation: json!({ "document": "t12.pdf", "page": 1 }),
+        };
+        // Rent steps up from 10,000 to 12,000 for the last quarter
+        let rent: Vec<Option<f64>> = (1..=12)
+            .map(|m| Some(if m > 9 { 12000.0 } else { 10000.0 }))
+            .collect();
+        let items = vec![
+            line(LineItemCategory::RentalIncome, rent),
+            line(LineItemCategory::VacancyAndCreditLoss, vec![Some(-1000.0); 12]),
+            line(LineItemCategory::Insurance, vec![Some(2000.0); 12]),
+        ];
+
+        let facts = facts_from_line_items(&document, &items);
+        assert_eq!(facts.len(), 9);
+        let value = |fact_type: FactType, basis: PeriodBasis| {
+            facts
+                .iter()
+                .find(|f| {
+                    f.fact_type == fact_type.as_str()
+                        && f.period_basis.as_deref() == Some(basis.as_str())
+                })
+                .map(|f| f.value.clone())
+        };
+        assert_eq!(
+            value(FactType::CollectedRent, PeriodBasis::Trailing12).as_deref(),
+            Some("114000")
+        );
+        assert_eq!(
+            value(FactType::CollectedRent, PeriodBasis::Trailing3).as_deref(),
+            Some("132000")
+        );
+        assert_eq!(
+            value(FactType::OperatingExpenses, PeriodBasis::Annualized).as_deref(),
+            Some("24000")
+        );
+        assert_eq!(
+            value(FactType::NetOperatingIncome, PeriodBasis::Trailing12).as_deref(),
+            Some("90000")
+        );
+
+        // Without expense data for February only the trailing quarter covers the expenses
+        let mut insurance = vec![Some(2000.0); 12];
+        insurance[1] = None;
+        let items = vec![
+            line(LineItemCategory::RentalIncome, vec![Some(10000.0); 12]),
+            line(LineItemCategory::Insurance, insurance),
+        ];
+        let facts = facts_from_line_items(&document, &items);
+        let bases = |fact_type: FactType| -> Vec<String> {
+            facts
+                .iter()
+                .filter(|f| f.fact_type == fact_type.as_str())
+                .filter_map(|f| f.period_basis.clone())
+                .collect()
+        };
+        assert_eq!(bases(FactType::CollectedRent).len(), 3);
+        assert_eq!(bases(FactType::OperatingExpenses), vec!["trailing_3".to_string()]);
+        assert_eq!(bases(FactType::NetOperatingIncome), vec!["trailing_3".to_string()]);
+        assert_eq!(classify_line("Payroll Taxes", None), Some(LineItemCategory::Payroll));
+        assert_eq!(classify_line("Real Estate Taxes", None), Some(LineItemCategory::Taxes));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::models::fact::{Fact, FactStatus, PeriodBasis};
+use crate::models::fact_resolution::{ConflictingFact, FactConflict, FactResolution};
+use crate::services::underwriting::period_basis_rank;
+use std::collections::BTreeMap;
+
+/// Relative difference above which two documents disagree on a fact
+pub const DEFAULT_CONFLICT_TOLERANCE: f64 = 0.02;
+
+/// The resolution of a fact type for a property, if the fact it picked is among the given facts
+fn active_resolution<'a>(
+    facts: &[Fact],
+    resolutions: &'a [FactResolution],
+    fact_type: &str,
+    property_id: Option<&str>,
+) -> Option<&'a FactResolution> {
+    resolutions.iter().filter(|r| r.fact_type == fact_type).find(|r| {
+        facts.iter().any(|f| {
+            f.fact_id == r.fact_id
+                && f.fact_type == fact_type
+                && f.property_id.as_deref() == property_id
+        })
+    })
+}
+
+/// Group facts by type and property and flag the groups whose documents disagree beyond the
+/// tolerance
+///
+/// Each document contributes the fact underwriting would pick from it for the period basis, so a
+/// T-12 and a T-3 figure from the same statement do not conflict. Monthly amounts are compared
+/// with yearly ones once annualized. Rejected facts and values that are not numbers are ignored.
+pub fn detect_conflicts(
+    facts: &[Fact],
+    resolutions: &[FactResolution],
+    period_basis: &PeriodBasis,
+    tolerance: f64,
+) -> Vec<FactConflict> {
+    // Fact picked from each document, with its yearly value, by fact type and property
+    type Candidates<'a> = BTreeMap<&'a str, (&'a Fact, f64)>;
+    let mut candidates: BTreeMap<(&str, Option<&str>), Candidates> = BTreeMap::new();
+    for fact in facts
+        .iter()
+        .filter(|f| f.status != FactStatus::Rejected.as_str())
+    {
+        let value = match fact.annual_amount() {
+            Some(value) => value,
+            None => continue,
+        };
+        let by_document = candidates
+            .entry((fact.fact_type.as_str(), fact.property_id.as_deref()))
+            .or_default();
+        match by_document.get(fact.document_id.as_str()) {
+            Some((current, _))
+                if period_basis_rank(current, period_basis)
+                    > period_basis_rank(fact, period_basis) => {}
+            _ => {
+                by_document.insert(fact.document_id.as_str(), (fact, value));
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|(_, by_document)| by_document.len() > 1)
+        .filter_map(|((fact_type, property_id), by_document)| {
+            let values: Vec<f64> = by_document.values().map(|(_, v)| *v).collect();
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let scale = min.abs().max(max.abs());
+            let spread = if scale > 0.0 { (max - min) / scale } else { 0.0 };
+            if spread <= tolerance {
+                return None;
+            }
+
+            Some(FactConflict {
+                fact_type: fact_type.to_string(),
+                property_id: property_id.map(str::to_string),
+                spread: (spread * 10000.0).round() / 10000.0,
+                facts: by_document
+                    .values()
+                    .map(|(fact, value)| ConflictingFact {
+                        fact_id: fact.fact_id.clone(),
+                        document_id: fact.document_id.clone(),
+                        label: fact.label.clone(),
+                        value: *value,
+                        status: fact.status.clone(),
+                        period_basis: fact.period_basis.clone(),
+                        source_citation: serde_json::from_value(fact.source_citation.clone()).ok(),
+                    })
+                    .collect(),
+                resolved_fact_id: active_resolution(facts, resolutions, fact_type, property_id)
+                    .map(|r| r.fact_id.clone()),
+            })
+        })
+        .collect()
+}
+
+/// Conflicts at the default tolerance that a reviewer has not resolved yet
+pub fn unresolved_conflicts(
+    facts: &[Fact],
+    resolutions: &[FactResolution],
+    period_basis: &PeriodBasis,
+) -> Vec<FactConflict> {
+    detect_conflicts(facts, resolutions, period_basis, DEFAULT_CONFLICT_TOLERANCE)
+        .into_iter()
+        .filter(|c| c.resolved_fact_id.is_none())
+        .collect()
+}
+
+/// Keep only the picked fact for every resolved fact type and property
+pub fn apply_resolutions(facts: &[Fact], resolutions: &[FactResolution]) -> Vec<Fact> {
+    facts
+        .iter()
+        .filter(|f| {
+            match active_resolution(facts, resolutions, &f.fact_type, f.property_id.as_deref()) {
+                Some(resolution) => resolution.fact_id == f.fact_id,
+                None => true,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fact(fact_id: &str, document_id: &str, fact_type: &str, value: &str) -> Fact {
+        Fact {
+            document_id: document_id.to_string(),
+            source_citation: serde_json::json!({ "document": document_id, "page": 1 }),
+            ..Fact::for_test(fact_id, fact_type, value)
+        }
+    }
+
+    #[test]
+    fn test_detect_and_resolve_conflicts() {
+        let mut t3_rent = fact("f-pl-t3", "pl", "collected_rent", "150000");
+        t3_rent.period_basis = Some("trailing_3".to_string());
+        let mut t12_rent = fact("f-pl-t12", "pl", "collected_rent", "120000");
+        t12_rent.period_basis = Some("trailing_12".to_string());
+        let facts = vec![
+            fact("f-rr-rent", "rent_roll", "collected_rent", "100000"),
+            t12_rent,
+            t3_rent,
+            fact("f-rr-units", "rent_roll", "unit_count", "24"),
+            fact("f-pl-opex", "pl", "operating_expenses", "40000"),
+            fact("f-tax-opex", "tax", "operating_expenses", "40500"),
+        ];
+
+        let conflicts = detect_conflicts(&facts, &[], &PeriodBasis::Trailing12, 0.02);
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.fact_type, "collected_rent");
+        assert_eq!(conflict.spread, 0.1667);
+        let fact_ids: Vec<&str> = conflict.facts.iter().map(|f| f.fact_id.as_str()).collect();
+        assert_eq!(fact_ids, vec!["f-pl-t12", "f-rr-rent"]);
+        assert!(conflict.resolved_fact_id.is_none());
+
+        let resolutions = vec![FactResolution {
+            resolution_id: "r-1".to_string(),
+            deal_id: "deal-1".to_string(),
+            fact_type: "collected_rent".to_string(),
+            fact_id: "f-rr-rent".to_string(),
+            resolved_by: "user-1".to_string(),
+            resolved_at: chrono::Utc::now(),
+        }];
+        let conflicts = detect_conflicts(&facts, &resolutions, &PeriodBasis::Trailing12, 0.02);
+        assert_eq!(conflicts[0].resolved_fact_id.as_deref(), Some("f-rr-rent"));
+
+        let resolved = apply_resolutions(&facts, &resolutions);
+        let rent_ids: Vec<&str> = resolved
+            .iter()
+            .filter(|f| f.fact_type == "collected_rent")
+            .map(|f| f.fact_id.as_str())
+            .collect();
+        assert_eq!(rent_ids, vec!["f-rr-rent"]);
+        assert_eq!(resolved.len(), 4);
+    }
+}
@@ -0,0 +1,381 @@
+use crate::models::document::Document;
+use crate::models::fact::{FactType, NewFact, SourceCitation};
+use crate::models::output::{Chunk, SegmentType};
+use crate::models::rent_roll_unit::{NewRentRollUnit, UnitStatus};
+use crate::utils::services::fact_extraction::segment_citation;
+use crate::utils::services::html::table_rows;
+use chrono::NaiveDate;
+use serde_json::json;
+use uuid::Uuid;
+
+const DATE_FORMATS: &[&str] = &[
+    "%m/%d/%Y", "%m/%d/%y", "%Y-%m-%d", "%m-%d-%Y", "%m-%d-%y", "%b %d, %Y", "%B %d, %Y", "%d-%b-%Y",
+    "%d-%b-%y",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RentRollColumn {
+    UnitId,
+    Tenant,
+    SquareFeet,
+    LeaseStart,
+    LeaseEnd,
+    ContractRent,
+    MarketRent,
+    Status,
+}
+
+/// A unit row parsed from a rent roll table
+#[derive(Debug, Clone, PartialEq)]
+pub struct RentRollRow {
+    pub unit_id: String,
+    pub tenant: Option<String>,
+    pub square_feet: Option<f64>,
+    pub lease_start: Option<NaiveDate>,
+    pub lease_end: Option<NaiveDate>,
+    pub contract_rent: Option<f64>,
+    pub market_rent: Option<f64>,
+    pub status: UnitStatus,
+    /// Cell text of the row, used as the citation line
+    pub line: String,
+}
+
+/// Map a header cell to the column it holds, if any
+fn classify_header(cell: &str) -> Option<RentRollColumn> {
+    let header = cell.to_lowercase();
+    let has = |keywords: &[&str]| keywords.iter().any(|k| header.contains(k));
+
+    if has(&["/sf", "psf", "per sf", "per sq", "type", "deposit"]) {
+        return None;
+    }
+    if has(&["market"]) {
+        return Some(RentRollColumn::MarketRent);
+    }
+    if has(&["sq ft", "sq. ft", "sqft", "square", "footage", "area", "size"]) || header == "sf" {
+        return Some(RentRollColumn::SquareFeet);
+    }
+    if has(&["start", "move in", "move-in", "commence", "lease from"]) {
+        return Some(RentRollColumn::LeaseStart);
+    }
+    if has(&["end", "expir", "move out", "move-out", "lease to"]) {
+        return Some(RentRollColumn::LeaseEnd);
+    }
+    if has(&["status", "occupancy"]) {
+        return Some(RentRollColumn::Status);
+    }
+    if has(&["tenant", "resident", "lessee", "name"]) {
+        return Some(RentRollColumn::Tenant);
+    }
+    if has(&["rent", "contract", "charge"]) {
+        return Some(RentRollColumn::ContractRent);
+    }
+    if has(&["unit", "apt", "suite", "space"]) || header == "#" {
+        return Some(RentRollColumn::UnitId);
+    }
+    None
+}
+
+/// Columns of a header row, or `None` when the row does not look like a rent roll header
+fn header_columns(cells: &[String]) -> Option<Vec<Option<RentRollColumn>>> {
+    let mut columns: Vec<Option<RentRollColumn>> = Vec::with_capacity(cells.len());
+    for cell in cells {
+        // The first column of each kind wins
+        let column = classify_header(cell).filter(|c| !columns.contains(&Some(*c)));
+        columns.push(column);
+    }
+    let recognized = columns.iter().flatten().count();
+    if columns.contains(&Some(RentRollColumn::UnitId)) && recognized >= 2 {
+        Some(columns)
+    } else {
+        None
+    }
+}
+
+/// Parse an amount such as `$1,250.00` or `(300)`
+pub fn parse_amount(text: &str) -> Option<f64> {
+    let cleaned: String = text
+        .chars()
+        .filter(|c| !matches!(c, '$' | ',' | ' '))
+        .collect();
+    if let Some(inner) = cleaned.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        return inner.parse::<f64>().ok().map(|v| -v);
+    }
+    cleaned.parse::<f64>().ok()
+}
+
+pub(crate) fn parse_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+}
+
+fn parse_row(columns: &[Option<RentRollColumn>], cells: &[String]) -> Option<RentRollRow> {
+    let cell = |column: RentRollColumn| -> Option<&str> {
+        let idx = columns.iter().position(|c| *c == Some(column))?;
+        cells
+            .get(idx)
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty() && *s != "-")
+    };
+
+    let unit_id = cell(RentRollColumn::UnitId)?;
+    let lowered = unit_id.to_lowercase();
+    if lowered.starts_with("total") || lowered.starts_with("subtotal") || lowered == "summary" {
+        return None;
+    }
+
+    let tenant_cell = cell(RentRollColumn::Tenant);
+    let status_cell = cell(RentRollColumn::Status);
+    let contract_rent = cell(RentRollColumn::ContractRent).and_then(parse_amount);
+    let market_rent = cell(RentRollColumn::MarketRent).and_then(parse_amount);
+    let square_feet = cell(RentRollColumn::SquareFeet).and_then(parse_amount);
+    let lease_start = cell(RentRollColumn::LeaseStart).and_then(parse_date);
+    let lease_end = cell(RentRollColumn::LeaseEnd).and_then(parse_date);
+
+    // Rows with nothing but a unit cell are headings such as "Building A" or "Floor 2"
+    if tenant_cell.is_none()
+        && status_cell.is_none()
+        && contract_rent.is_none()
+        && market_rent.is_none()
+        && square_feet.is_none()
+        && lease_start.is_none()
+        && lease_end.is_none()
+    {
+        return None;
+    }
+    let is_vacant = |s: Option<&str>| s.is_some_and(|s| s.to_lowercase().contains("vacan"));
+    let status = if is_vacant(status_cell) || is_vacant(tenant_cell) {
+        UnitStatus::Vacant
+    } else if status_cell.is_some()
+        || tenant_cell.is_some()
+        || contract_rent.is_some_and(|rent| rent > 0.0)
+    {
+        UnitStatus::Occupied
+    } else {
+        UnitStatus::Vacant
+    };
+
+    Some(RentRollRow {
+        unit_id: unit_id.to_string(),
+        tenant: tenant_cell
+            .filter(|t| !is_vacant(Some(t)))
+            .map(|t| t.to_string()),
+        square_feet,
+        lease_start,
+        lease_end,
+        contract_rent,
+        market_rent,
+        status,
+        line: cells
+            .iter()
+            .filter(|c| !c.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" | "),
+    })
+}
+
+/// Parse the unit rows of a sequence of rent roll tables
+///
+/// Tables without a header row continue the columns of the previous table, so rent rolls
+/// split across pages are read as one table. Rows are returned with the index of their table.
+pub fn parse_rent_roll_tables(tables: &[&str]) -> Vec<(usize, RentRollRow)> {
+    let mut columns: Option<Vec<Option<RentRollColumn>>> = None;
+    let mut rows = Vec::new();
+    for (table_idx, html) in tables.iter().enumerate() {
+        for cells in table_rows(html) {
+            if let Some(header) = header_columns(&cells) {
+                columns = Some(header);
+                continue;
+            }
+            if let Some(row) = columns.as_deref().and_then(|c| parse_row(c, &cells)) {
+                rows.push((table_idx, row));
+            }
+        }
+    }
+    rows
+}
+
+/// Parse the units of the table segments of a rent roll document
+pub fn units_from_chunks(document: &Document, chunks: &[Chunk]) -> Vec<NewRentRollUnit> {
+    let segments: Vec<_> = chunks
+        .iter()
+        .flat_map(|chunk| chunk.segments.iter())
+        .filter(|segment| segment.segment_type == SegmentType::Table && !segment.html.is_empty())
+        .collect();
+    let tables: Vec<&str> = segments.iter().map(|s| s.html.as_str()).collect();
+
+    parse_rent_roll_tables(&tables)
+        .into_iter()
+        .enumerate()
+        .map(|(row_index, (table_idx, row))| {
+            let mut citation = segment_citation(document, segments[table_idx]);
+            citation.line = Some(row.line);
+            NewRentRollUnit {
+                rent_roll_unit_id: Uuid::new_v4().to_string(),
+                document_id: document.document_id.clone(),
+                deal_id: document.deal_id.clone(),
+                row_index: row_index as i32,
+                unit_id: row.unit_id,
+                tenant: row.tenant,
+                square_feet: row.square_feet,
+                lease_start: row.lease_start,
+                lease_end: row.lease_end,
+                contract_rent: row.contract_rent,
+                market_rent: row.market_rent,
+                status: row.status.as_str().to_string(),
+                source_citation: json!(citation),
+            }
+        })
+        .collect()
+}
+
+/// Derive unit count, occupancy rate and gross scheduled rent facts from rent roll units
+///
+/// Gross scheduled rent is the annualized market rent of every unit, using the contract rent
+/// for units without a market rent.
+pub fn facts_from_units(document: &Document, units: &[NewRentRollUnit]) -> Vec<NewFact> {
+    let first = match units.first() {
+        Some(first) => first,
+        None => return vec![],
+    };
+    let mut citation: SourceCitation =
+        serde_json::from_value(first.source_citation.clone()).unwrap_or(SourceCitation {
+            document: document.file_name.clone(),
+            page: 1,
+            line: None,
+            bbox: None,
+            page_width: None,
+            page_height: None,
+        });
+    citation.line = Some(format!("Derived from {} units in the rent roll", units.len()));
+
+    let unit_count = units.len();
+    let occupied = units
+        .iter()
+        .filter(|u| u.status == UnitStatus::Occupied.as_str())
+        .count();
+    let occupancy_rate = (occupied as f64 / unit_count as f64 * 10000.0).round() / 100.0;
+    let monthly_rents: Vec<f64> = units
+        .iter()
+        .filter_map(|u| u.market_rent.or(u.contract_rent))
+        .collect();
+
+    let fact = |fact_type: FactType, label: &str, value: String, unit: &str| NewFact {
+        fact_id: Uuid::new_v4().to_string(),
+        document_id: document.document_id.clone(),
+        deal_id: document.deal_id.clone(),
+        property_id: document.property_id.clone(),
+        fact_type: fact_type.as_str().to_string(),
+        label: label.to_string(),
+        value,
+        unit: Some(unit.to_string()),
+        source_citation: json!(citation),
+        status: "pending_approval".to_string(),
+        confidence_score: Some(0.95),
+        period_basis: None,
+    };
+
+    let mut facts = vec![
+        fact(FactType::UnitCount, "Unit Count", unit_count.to_string(), "units"),
+        fact(
+            FactType::OccupancyRate,
+            "Occupancy Rate",
+            occupancy_rate.to_string(),
+            "%",
+        ),
+    ];
+    if !monthly_rents.is_empty() {
+        let annual: f64 = monthly_rents.iter().sum::<f64>() * 12.0;
+        facts.push(fact(
+            FactType::GrossScheduledRent,
+            "Gross Scheduled Rent",
+            ((annual * 100.0).round() / 100.0).to_string(),
+            "USD/year",
+        ));
+    }
+    facts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_ONE: &str = r#"<table>
+        <tr><th>Unit</th><th>Tenant</th><th>Sq Ft</th><th>Lease Start</th><th>Lease End</th><th>Rent</th><th>Market Rent</th><th>Status</th></tr>
+        <tr><td>101</td><td>Jane Doe</td><td>750</td><td>01/01/2024</td><td>12/31/2024</td><td>$1,200.00</td><td>$1,250.00</td><td>Occupied</td></tr>
+        <tr><td>102</td><td>VACANT</td><td>750</td><td></td><td></td><td>-</td><td>$1,250.00</td><td>Vacant</td></tr>
+    </table>"#;
+    const PAGE_TWO: &str = r#"<table>
+        <tr><td>Building B</td><td></td><td></td><td></td><td></td><td></td><td></td><td></td></tr>
+        <tr><td>103</td><td>John &amp; Mary Roe</td><td>900</td><td>2023-06-01</td><td>2025-05-31</td><td>1,400</td><td>1,450</td><td>Current</td></tr>
+        <tr><td colspan="5">Total</td><td>$2,600.00</td><td>$3,950.00</td><td></td></tr>
+    </table>"#;
+
+    #[test]
+    fn test_parse_rent_roll_tables() {
+        let rows = parse_rent_roll_tables(&[PAGE_ONE, PAGE_TWO]);
+        assert_eq!(rows.len(), 3);
+
+        let (table_idx, first) = &rows[0];
+        assert_eq!(*table_idx, 0);
+        assert_eq!(first.unit_id, "101");
+        assert_eq!(first.tenant.as_deref(), Some("Jane Doe"));
+        assert_eq!(first.square_feet, Some(750.0));
+        assert_eq!(first.lease_start, NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(first.contract_rent, Some(1200.0));
+        assert_eq!(first.market_rent, Some(1250.0));
+        assert_eq!(first.status, UnitStatus::Occupied);
+
+        let (_, vacant) = &rows[1];
+        assert_eq!(vacant.tenant, None);
+        assert_eq!(vacant.contract_rent, None);
+        assert_eq!(vacant.status, UnitStatus::Vacant);
+
+        let (table_idx, continued) = &rows[2];
+        assert_eq!(*table_idx, 1);
+        assert_eq!(continued.tenant.as_deref(), Some("John & Mary Roe"));
+        assert_eq!(continued.lease_end, NaiveDate::from_ymd_opt(2025, 5, 31));
+        assert_eq!(continued.status, UnitStatus::Occupied);
+    }
+
+    #[test]
+    fn test_facts_from_units() {
+        let document = Document {
+            page_count: Some(2),
+            classification_source: Some("user".to_string()),
+            ..Document::for_test("rent_roll.pdf", "rent_roll")
+        };
+        let units: Vec<NewRentRollUnit> = parse_rent_roll_tables(&[PAGE_ONE, PAGE_TWO])
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (_, row))| NewRentRollUnit {
+                rent_roll_unit_id: format!("unit-{}", idx),
+                document_id: document.document_id.clone(),
+                deal_id: document.deal_id.clone(),
+                row_index: idx as i32,
+                unit_id: row.unit_id,
+                tenant: row.tenant,
+                square_feet: row.square_feet,
+                lease_start: row.lease_start,
+                lease_end: row.lease_end,
+                contract_rent: row.contract_rent,
+                market_rent: row.market_rent,
+                status: row.status.as_str().to_string(),
+                source_citation: json!({ "document": "rent_roll.pdf", "page": 1 }),
+            })
+            .collect();
+
+        let facts = facts_from_units(&document, &units);
+        let value = |fact_type: FactType| {
+            facts
+                .iter()
+                .find(|f| f.fact_type == fact_type.as_str())
+                .map(|f| f.value.clone())
+        };
+        assert_eq!(value(FactType::UnitCount).as_deref(), Some("3"));
+        assert_eq!(value(FactType::OccupancyRate).as_deref(), Some("66.67"));
+        assert_eq!(value(FactType::GrossScheduledRent).as_deref(), Some("47400"));
+    }
+}
@@ -1,6 +1,65 @@
+use crate::models::fact::{Fact, PeriodBasis, SourceCitation};
+use crate::models::underwriting_policy::PolicyRules;
+use crate::models::underwriting_run::{MetricDiff, UnderwritingRun, UnderwritingRunDiff};
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap};
 use utoipa::ToSchema;
 
+/// Fact types that feed `UnderwritingInput`
+pub(crate) const INPUT_FACT_TYPES: &[&str] = &[
+    "unit_count",
+    "occupancy_rate",
+    "gross_scheduled_rent",
+    "collected_rent",
+    "operating_expenses",
+    "debt_service",
+    "property_value",
+    "mortgage_balance",
+    "interest_rate",
+    "amortization_years",
+    "interest_only_years",
+    "loan_term_years",
+    "appraised_value",
+    "appraisal_cap_rate",
+    "bank_deposits",
+    "insurance_coverage",
+];
+
+/// How well a fact matches the requested period basis: an exact match ranks highest, then
+/// facts without a basis, then facts computed over another basis
+pub fn period_basis_rank(fact: &Fact, period_basis: &PeriodBasis) -> u8 {
+    match fact.period_basis.as_deref() {
+        Some(basis) if basis == period_basis.as_str() => 2,
+        None => 1,
+        Some(_) => 0,
+    }
+}
+
+/// The fact used for each input
+///
+/// Facts computed over the requested period basis are preferred, then facts without a basis;
+/// among equally ranked facts of the same type, later ones take precedence.
+fn select_input_facts<'a>(
+    facts: &'a [Fact],
+    period_basis: &PeriodBasis,
+) -> HashMap<&'a str, &'a Fact> {
+    let rank = |fact: &Fact| period_basis_rank(fact, period_basis);
+    let mut selected: HashMap<&str, &Fact> = HashMap::new();
+    for fact in facts
+        .iter()
+        .filter(|f| INPUT_FACT_TYPES.contains(&f.fact_type.as_str()))
+    {
+        match selected.get(fact.fact_type.as_str()) {
+            Some(current) if rank(current) > rank(fact) => {}
+            _ => {
+                selected.insert(fact.fact_type.as_str(), fact);
+            }
+        }
+    }
+    selected
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UnderwritingInput {
     pub unit_count: Option<i32>,
@@ -12,6 +71,308 @@ pub struct UnderwritingInput {
     pub property_value: Option<f64>,
     pub mortgage_balance: Option<f64>,
     pub interest_rate: Option<f64>,
+    /// Terms of the existing loan, the policy's terms are assumed when missing
+    #[serde(default)]
+    pub amortization_years: Option<f64>,
+    #[serde(default)]
+    pub interest_only_years: Option<f64>,
+    #[serde(default)]
+    pub loan_term_years: Option<f64>,
+    /// Cap rate concluded by an appraisal, in percent
+    #[serde(default)]
+    pub appraisal_cap_rate: Option<f64>,
+    /// Annualized deposits from bank statements
+    #[serde(default)]
+    pub bank_deposits: Option<f64>,
+    /// Building coverage of the insurance policy
+    #[serde(default)]
+    pub insurance_coverage: Option<f64>,
+    /// The fact each field was read from, keyed by field name
+    #[serde(default)]
+    pub sources: BTreeMap<String, FactSource>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FactSource {
+    pub fact_id: String,
+    pub citation: Option<SourceCitation>,
+}
+
+/// Values of the facts selected for each input field, with the facts they were read from
+///
+/// An appraisal's as-is value takes precedence over other property values, such as tax
+/// assessments. Amounts reported monthly or quarterly are converted to yearly figures, and facts
+/// whose value cannot be read are left out.
+pub(crate) fn input_values(
+    facts: &[Fact],
+    period_basis: &PeriodBasis,
+) -> (BTreeMap<String, f64>, BTreeMap<String, FactSource>) {
+    let mut selected = select_input_facts(facts, period_basis);
+    if let Some(appraisal) = selected.remove("appraised_value") {
+        selected.insert("property_value", appraisal);
+    }
+
+    let mut values = BTreeMap::new();
+    let mut sources = BTreeMap::new();
+    for (field, fact) in selected {
+        let Some(value) = fact.annual_amount() else {
+            continue;
+        };
+        values.insert(field.to_string(), value);
+        sources.insert(
+            field.to_string(),
+            FactSource {
+                fact_id: fact.fact_id.clone(),
+                citation: serde_json::from_value(fact.source_citation.clone()).ok(),
+            },
+        );
+    }
+    (values, sources)
+}
+
+impl UnderwritingInput {
+    /// Build the input from a deal's facts, preferring facts computed over the given period basis
+    ///
+    /// Returns `None` when collected rent or operating expenses are missing.
+    pub fn from_facts(facts: &[Fact], period_basis: &PeriodBasis) -> Option<Self> {
+        let (values, sources) = input_values(facts, period_basis);
+        let value = |field: &str| values.get(field).copied();
+
+        let unit_count = value("unit_count").map(|count| count.round() as i32);
+        let occupancy_rate = value("occupancy_rate");
+        let gross_scheduled_rent = value("gross_scheduled_rent");
+        let collected_rent = value("collected_rent");
+        let operating_expenses = value("operating_expenses");
+        let debt_service = value("debt_service");
+        let property_value = value("property_value");
+        let mortgage_balance = value("mortgage_balance");
+        let interest_rate = value("interest_rate");
+        let amortization_years = value("amortization_years");
+        let interest_only_years = value("interest_only_years");
+        let loan_term_years = value("loan_term_years");
+        let appraisal_cap_rate = value("appraisal_cap_rate");
+        let bank_deposits = value("bank_deposits");
+        let insurance_coverage = value("insurance_coverage");
+
+        Some(UnderwritingInput {
+            unit_count,
+            occupancy_rate,
+            gross_scheduled_rent,
+            collected_rent: collected_rent?,
+            operating_expenses: operating_expenses?,
+            debt_service,
+            property_value,
+            mortgage_balance,
+            interest_rate,
+            amortization_years,
+            interest_only_years,
+            loan_term_years,
+            appraisal_cap_rate,
+            bank_deposits,
+            insurance_coverage,
+            sources,
+        })
+    }
+
+    /// IDs of the facts the input was read from
+    pub fn source_fact_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.sources.values().map(|s| s.fact_id.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Fact ids and citations behind the given input fields
+    pub(crate) fn sources_for(&self, fields: &[&str]) -> (Vec<String>, Vec<SourceCitation>) {
+        let mut fact_ids: Vec<String> = Vec::new();
+        let mut citations = Vec::new();
+        for source in fields.iter().filter_map(|field| self.sources.get(*field)) {
+            if fact_ids.contains(&source.fact_id) {
+                continue;
+            }
+            fact_ids.push(source.fact_id.clone());
+            if let Some(citation) = &source.citation {
+                citations.push(citation.clone());
+            }
+        }
+        (fact_ids, citations)
+    }
+}
+
+/// Longest amortization, interest-only period or term a loan is scheduled over, in years
+pub const MAX_LOAN_YEARS: f64 = 50.0;
+
+/// Loan years given by a fact, when within (0, MAX_LOAN_YEARS] or zero where allowed
+fn loan_years(years: Option<f64>, allow_zero: bool) -> Option<f64> {
+    years.filter(|years| (allow_zero || *years > 0.0) && (0.0..=MAX_LOAN_YEARS).contains(years))
+}
+
+/// A mortgage paying interest only for its first years, then amortizing monthly until a balloon at
+/// the end of its term
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Loan {
+    pub balance: f64,
+    pub interest_rate: f64, // percent
+    pub amortization_years: f64,
+    pub interest_only_years: f64,
+    pub term_years: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LoanPayment {
+    pub month: u32,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    pub balance: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LoanYear {
+    pub year: u32,
+    pub debt_service: f64,
+    pub interest: f64,
+    pub principal: f64,
+    pub ending_balance: f64,
+}
+
+impl Loan {
+    /// The existing loan described by the input's facts, assuming the policy's amortization,
+    /// interest-only period and term where the facts do not give them or give them outside
+    /// `MAX_LOAN_YEARS`
+    ///
+    /// Returns `None` without a mortgage balance and interest rate.
+    pub fn from_input(input: &UnderwritingInput, rules: &PolicyRules) -> Option<Self> {
+        let thresholds = &rules.thresholds;
+        let balance = input.mortgage_balance.filter(|b| *b > 0.0)?;
+        Some(Loan {
+            balance,
+            interest_rate: input.interest_rate?,
+            amortization_years: loan_years(input.amortization_years, false)
+                .unwrap_or(thresholds.amortization_years),
+            interest_only_years: loan_years(input.interest_only_years, true)
+                .unwrap_or(thresholds.interest_only_years),
+            term_years: loan_years(input.loan_term_years, false)
+                .unwrap_or(thresholds.loan_term_years),
+        })
+    }
+
+    /// Warnings for loan years given by the input's facts that `from_input` replaced with the
+    /// policy's
+    pub fn ignored_years_warnings(input: &UnderwritingInput, rules: &PolicyRules) -> Vec<String> {
+        let thresholds = &rules.thresholds;
+        [
+            ("Amortization", input.amortization_years, false, thresholds.amortization_years),
+            (
+                "Interest-only period",
+                input.interest_only_years,
+                true,
+                thresholds.interest_only_years,
+            ),
+            ("Loan term", input.loan_term_years, false, thresholds.loan_term_years),
+        ]
+        .into_iter()
+        .filter(|(_, years, allow_zero, _)| {
+            years.is_some() && loan_years(*years, *allow_zero).is_none()
+        })
+        .map(|(name, years, _, fallback)| {
+            format!(
+                "Warning: {} of {} years is outside 0 to {} years, {} years assumed instead",
+                name,
+                years.unwrap_or_default(),
+                MAX_LOAN_YEARS,
+                fallback
+            )
+        })
+        .collect()
+    }
+
+    /// The same loan at a shocked rate, floored at zero
+    pub fn with_rate_adjustment(&self, interest_adj_bps: f64) -> Self {
+        Loan {
+            interest_rate: (self.interest_rate + interest_adj_bps / 100.0).max(0.0),
+            ..self.clone()
+        }
+    }
+
+    /// Monthly payments over the term, the last one leaving the balloon balance
+    pub fn schedule(&self) -> Vec<LoanPayment> {
+        self.payments(self.term_months())
+    }
+
+    fn term_months(&self) -> u32 {
+        (self.term_years.clamp(0.0, MAX_LOAN_YEARS) * 12.0).round() as u32
+    }
+
+    /// The first `months` monthly payments
+    fn payments(&self, months: u32) -> Vec<LoanPayment> {
+        let rate = self.interest_rate / 100.0 / 12.0;
+        let interest_only_months = (self.interest_only_years * 12.0).round() as u32;
+        // Amortization starts once the interest-only period ends
+        let amortizing_payment =
+            self.balance * loan_constant(self.interest_rate, self.amortization_years) / 12.0;
+
+        let mut balance = self.balance;
+        let mut payments = Vec::with_capacity(months as usize);
+        for month in 1..=months {
+            let interest = balance * rate;
+            let payment = if month <= interest_only_months {
+                interest
+            } else {
+                amortizing_payment.min(balance + interest)
+            };
+            let principal = payment - interest;
+            balance -= principal;
+            payments.push(LoanPayment {
+                month,
+                payment,
+                interest,
+                principal,
+                balance,
+            });
+        }
+        payments
+    }
+
+    /// Payments totalled by loan year
+    pub fn annual_schedule(&self) -> Vec<LoanYear> {
+        let mut years: Vec<LoanYear> = Vec::new();
+        for payment in self.schedule() {
+            let year = (payment.month - 1) / 12 + 1;
+            match years.last_mut() {
+                Some(current) if current.year == year => {
+                    current.debt_service += payment.payment;
+                    current.interest += payment.interest;
+                    current.principal += payment.principal;
+                    current.ending_balance = payment.balance;
+                }
+                _ => years.push(LoanYear {
+                    year,
+                    debt_service: payment.payment,
+                    interest: payment.interest,
+                    principal: payment.principal,
+                    ending_balance: payment.balance,
+                }),
+            }
+        }
+        years
+    }
+
+    /// Debt service over the first loan year
+    pub fn annual_debt_service(&self) -> f64 {
+        self.payments(self.term_months().min(12))
+            .iter()
+            .map(|p| p.payment)
+            .sum()
+    }
+
+    /// Balance due at the end of the term
+    pub fn balloon_balance(&self) -> f64 {
+        self.schedule()
+            .last()
+            .map(|p| p.balance)
+            .unwrap_or(self.balance)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -22,10 +383,50 @@ pub struct UnderwritingResult {
     pub cap_rate: Option<f64>,
     pub ltv: Option<f64>,
     pub gross_rent_multiplier: Option<f64>,
+    /// Debt service the metrics were calculated with, derived from the loan when not provided
+    pub annual_debt_service: Option<f64>,
+    /// Yearly totals of the existing loan's payments over its term
+    pub amortization_schedule: Option<Vec<LoanYear>>,
+    /// NOI as a percentage of the mortgage balance
+    pub debt_yield: Option<f64>,
+    /// Occupancy at which rent covers operating expenses and debt service
+    pub break_even_occupancy: Option<f64>,
+    /// Operating expenses as a percentage of collected rent
+    pub expense_ratio: Option<f64>,
+    pub rent_per_unit: Option<f64>,
+    pub expenses_per_unit: Option<f64>,
+    pub noi_per_unit: Option<f64>,
+    pub value_per_unit: Option<f64>,
+    /// Largest loan satisfying every sizing constraint of the policy
+    pub max_loan_amount: Option<f64>,
+    pub loan_sizing: Option<LoanSizing>,
+    /// Annualized bank deposits as a percentage of collected rent
+    pub deposit_coverage: Option<f64>,
+    /// Insurance coverage as a percentage of the property value
+    pub insurance_coverage_ratio: Option<f64>,
     pub audit_trail: Vec<CalculationStep>,
     pub warnings: Vec<String>,
 }
 
+/// Loan amounts supported by each sizing constraint, for a fully amortizing loan with a balloon
+/// at the end of the term
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LoanSizing {
+    pub interest_rate: f64, // percent
+    pub amortization_years: f64,
+    pub term_years: f64,
+    /// Annual debt service per dollar borrowed
+    pub loan_constant: f64,
+    pub dscr_max_loan: f64,
+    pub ltv_max_loan: Option<f64>,
+    pub debt_yield_max_loan: f64,
+    pub max_loan_amount: f64,
+    /// The constraint that limits the loan: "dscr", "ltv" or "debt_yield"
+    pub binding_constraint: String,
+    pub annual_debt_service: f64,
+    pub balloon_balance: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CalculationStep {
     pub metric: String,
@@ -33,12 +434,15 @@ pub struct CalculationStep {
     pub inputs: Vec<(String, f64)>,
     pub result: f64,
     pub sources: Vec<String>, // fact_ids that contributed to this calculation
+    /// Where each source fact was found in its document
+    #[serde(default)]
+    pub citations: Vec<SourceCitation>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct StressTestInput {
-    pub base_result: UnderwritingResult,
-    pub occupancy_adjustment: Option<f64>, // percentage change
+    pub base_input: UnderwritingInput,
+    pub occupancy_adjustment: Option<f64>, // percentage points
     pub rent_adjustment: Option<f64>,      // percentage change
     pub expense_adjustment: Option<f64>,   // percentage change
     pub interest_rate_adjustment: Option<f64>, // basis points
@@ -60,13 +464,61 @@ pub struct StressTestComparison {
     pub cash_flow_change: Option<f64>,
 }
 
-/// Calculate underwriting metrics from input facts
-pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
+/// Maximum number of scenario combinations a single stress grid may expand to
+pub const MAX_STRESS_SCENARIOS: usize = 1000;
+
+/// Shocks to combine into a stress test matrix. An empty axis is not shocked.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct StressTestGrid {
+    #[serde(default)]
+    pub rent_adjustments: Vec<f64>, // percentage change
+    #[serde(default)]
+    pub expense_adjustments: Vec<f64>, // percentage change
+    #[serde(default)]
+    pub occupancy_adjustments: Vec<f64>, // percentage points
+    #[serde(default)]
+    pub interest_rate_adjustments: Vec<f64>, // basis points
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StressScenario {
+    pub rent_adjustment: f64,
+    pub expense_adjustment: f64,
+    pub occupancy_adjustment: f64,
+    pub interest_rate_adjustment: f64,
+    pub result: StressTestResult,
+}
+
+/// Single-variable shocks at which cash flow after debt reaches zero (NOI when there is no debt)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StressBreakevens {
+    pub rent_change_pct: Option<f64>,
+    pub expense_change_pct: Option<f64>,
+    pub occupancy_rate: Option<f64>,
+    pub interest_rate_change_bps: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StressTestMatrix {
+    pub base_result: UnderwritingResult,
+    pub scenarios: Vec<StressScenario>,
+    pub breakevens: StressBreakevens,
+}
+
+/// Calculate underwriting metrics from input facts, warning where they breach the policy
+pub fn calculate_underwriting(input: UnderwritingInput, rules: &PolicyRules) -> UnderwritingResult {
     let mut audit_trail = Vec::new();
     let mut warnings = Vec::new();
+    let thresholds = &rules.thresholds;
+    let severities = &rules.severities;
+    let loan = Loan::from_input(&input, rules);
+    if loan.is_some() {
+        warnings.extend(Loan::ignored_years_warnings(&input, rules));
+    }
 
     // Calculate NOI
     let noi = input.collected_rent - input.operating_expenses;
+    let (sources, citations) = input.sources_for(&["collected_rent", "operating_expenses"]);
     audit_trail.push(CalculationStep {
         metric: "NOI (Net Operating Income)".to_string(),
         formula: "Collected Rent - Operating Expenses".to_string(),
@@ -75,12 +527,47 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
             ("Operating Expenses".to_string(), input.operating_expenses),
         ],
         result: noi,
-        sources: vec![], // Would be populated with actual fact_ids
+        sources,
+        citations,
     });
 
+    // Use the debt service provided, otherwise derive it from the loan
+    let debt_fields: &[&str] = if input.debt_service.is_some() {
+        &["debt_service"]
+    } else {
+        &LOAN_FIELDS
+    };
+    let debt_service = match (input.debt_service, &loan) {
+        (Some(ds), _) => Some(ds),
+        (None, Some(loan)) => {
+            let ds = loan.annual_debt_service();
+            let (sources, citations) = input.sources_for(&LOAN_FIELDS);
+            
+            audit_trail.push(CalculationStep {
+                metric: "Annual Debt Service".to_string(),
+                formula: "Sum of first-year loan payments".to_string(),
+                inputs: vec![
+                    ("Mortgage Balance".to_string(), loan.balance),
+                    ("Interest Rate (%)".to_string(), loan.interest_rate),
+                    ("Amortization (years)".to_string(), loan.amortization_years),
+                    ("Interest-only Period (years)".to_string(), loan.interest_only_years),
+                ],
+                result: ds,
+                sources,
+                citations,
+            });
+            
+            Some(ds)
+        }
+        (None, None) => None,
+    };
+    let mut debt_source_fields = vec!["collected_rent", "operating_expenses"];
+    debt_source_fields.extend(debt_fields);
+
     // Calculate DSCR if debt service is available
-    let dscr = input.debt_service.map(|ds| {
+    let dscr = debt_service.map(|ds| {
         let ratio = if ds > 0.0 { noi / ds } else { 0.0 };
+        let (sources, citations) = input.sources_for(&debt_source_fields);
         
         audit_trail.push(CalculationStep {
             metric: "DSCR (Debt Service Coverage Ratio)".to_string(),
@@ -90,21 +577,33 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
                 ("Annual Debt Service".to_string(), ds),
             ],
             result: ratio,
-            sources: vec![],
+            sources,
+            citations,
         });
         
-        if ratio < 1.0 {
-            warnings.push(format!("Critical: DSCR of {:.2} is below 1.0 - property cannot cover debt service", ratio));
-        } else if ratio < 1.25 {
-            warnings.push(format!("Warning: DSCR of {:.2} is below recommended 1.25 threshold", ratio));
+        if ratio < thresholds.critical_dscr {
+            warnings.push(format!(
+                "{}: DSCR of {:.2} is below critical {:.2} threshold",
+                severities.dscr_below_critical.warning_prefix(),
+                ratio,
+                thresholds.critical_dscr
+            ));
+        } else if ratio < thresholds.min_dscr {
+            warnings.push(format!(
+                "{}: DSCR of {:.2} is below required {:.2} threshold",
+                severities.dscr_below_minimum.warning_prefix(),
+                ratio,
+                thresholds.min_dscr
+            ));
         }
         
         ratio
     });
 
     // Calculate cash flow after debt
-    let cash_flow_after_debt = input.debt_service.map(|ds| {
+    let cash_flow_after_debt = debt_service.map(|ds| {
         let cash_flow = noi - ds;
+        let (sources, citations) = input.sources_for(&debt_source_fields);
         
         audit_trail.push(CalculationStep {
             metric: "Cash Flow After Debt".to_string(),
@@ -114,11 +613,16 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
                 ("Annual Debt Service".to_string(), ds),
             ],
             result: cash_flow,
-            sources: vec![],
+            sources,
+            citations,
         });
         
         if cash_flow < 0.0 {
-            warnings.push(format!("Critical: Negative cash flow of ${:.2}", cash_flow.abs()));
+            warnings.push(format!(
+                "{}: Negative cash flow of ${:.2}",
+                severities.negative_cash_flow.warning_prefix(),
+                cash_flow.abs()
+            ));
         }
         
         cash_flow
@@ -127,6 +631,8 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
     // Calculate cap rate if property value is available
     let cap_rate = input.property_value.map(|value| {
         let rate = if value > 0.0 { (noi / value) * 100.0 } else { 0.0 };
+        let (sources, citations) =
+            input.sources_for(&["collected_rent", "operating_expenses", "property_value"]);
         
         audit_trail.push(CalculationStep {
             metric: "Cap Rate".to_string(),
@@ -136,13 +642,25 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
                 ("Property Value".to_string(), value),
             ],
             result: rate,
-            sources: vec![],
+            sources,
+            citations,
         });
         
-        if rate < 4.0 {
-            warnings.push(format!("Note: Cap rate of {:.2}% is relatively low", rate));
-        } else if rate > 12.0 {
-            warnings.push(format!("Note: Cap rate of {:.2}% is relatively high - may indicate higher risk", rate));
+        let prefix = severities.cap_rate_out_of_range.warning_prefix();
+        if rate < thresholds.min_cap_rate {
+            warnings.push(format!("{}: Cap rate of {:.2}% is relatively low", prefix, rate));
+        } else if rate > thresholds.max_cap_rate {
+            warnings.push(format!("{}: Cap rate of {:.2}% is relatively high - may indicate higher risk", prefix, rate));
+        }
+        if let Some(appraisal_rate) = input.appraisal_cap_rate {
+            if (rate - appraisal_rate).abs() > thresholds.max_appraisal_cap_rate_variance {
+                warnings.push(format!(
+                    "{}: Cap rate of {:.2}% differs from the appraisal's {:.2}%",
+                    severities.appraisal_cap_rate_variance.warning_prefix(),
+                    rate,
+                    appraisal_rate
+                ));
+            }
         }
         
         rate
@@ -152,6 +670,7 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
     let ltv = match (input.mortgage_balance, input.property_value) {
         (Some(mortgage), Some(value)) if value > 0.0 => {
             let ratio = (mortgage / value) * 100.0;
+            let (sources, citations) = input.sources_for(&["mortgage_balance", "property_value"]);
             
             audit_trail.push(CalculationStep {
                 metric: "LTV (Loan-to-Value)".to_string(),
@@ -161,11 +680,17 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
                     ("Property Value".to_string(), value),
                 ],
                 result: ratio,
-                sources: vec![],
+                sources,
+                citations,
             });
             
-            if ratio > 80.0 {
-                warnings.push(format!("Warning: LTV of {:.2}% is above 80%", ratio));
+            if ratio > thresholds.max_ltv {
+                warnings.push(format!(
+                    "{}: LTV of {:.2}% is above {:.2}%",
+                    severities.ltv_above_maximum.warning_prefix(),
+                    ratio,
+                    thresholds.max_ltv
+                ));
             }
             
             Some(ratio)
@@ -177,6 +702,8 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
     let gross_rent_multiplier = match (input.gross_scheduled_rent, input.property_value) {
         (Some(gsr), Some(value)) if gsr > 0.0 => {
             let grm = value / gsr;
+            let (sources, citations) =
+                input.sources_for(&["property_value", "gross_scheduled_rent"]);
             
             audit_trail.push(CalculationStep {
                 metric: "Gross Rent Multiplier".to_string(),
@@ -186,7 +713,8 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
                     ("Gross Scheduled Rent".to_string(), gsr),
                 ],
                 result: grm,
-                sources: vec![],
+                sources,
+                citations,
             });
             
             Some(grm)
@@ -194,9 +722,235 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
         _ => None,
     };
 
+    // Calculate debt yield if mortgage balance is available
+    let debt_yield = match input.mortgage_balance {
+        Some(balance) if balance > 0.0 => {
+            let yield_pct = (noi / balance) * 100.0;
+            let (sources, citations) =
+                input.sources_for(&["collected_rent", "operating_expenses", "mortgage_balance"]);
+            
+            audit_trail.push(CalculationStep {
+                metric: "Debt Yield".to_string(),
+                formula: "(NOI / Mortgage Balance) * 100".to_string(),
+                inputs: vec![
+                    ("NOI".to_string(), noi),
+                    ("Mortgage Balance".to_string(), balance),
+                ],
+                result: yield_pct,
+                sources,
+                citations,
+            });
+            
+            if yield_pct < thresholds.min_debt_yield {
+                warnings.push(format!(
+                    "{}: Debt yield of {:.2}% is below required {:.2}%",
+                    severities.debt_yield_below_minimum.warning_prefix(),
+                    yield_pct,
+                    thresholds.min_debt_yield
+                ));
+            }
+            
+            Some(yield_pct)
+        }
+        _ => None,
+    };
+
+    // Calculate operating expense ratio
+    let expense_ratio = (input.collected_rent > 0.0).then(|| {
+        let ratio = (input.operating_expenses / input.collected_rent) * 100.0;
+        let (sources, citations) = input.sources_for(&["collected_rent", "operating_expenses"]);
+        
+        audit_trail.push(CalculationStep {
+            metric: "Expense Ratio".to_string(),
+            formula: "(Operating Expenses / Collected Rent) * 100".to_string(),
+            inputs: vec![
+                ("Operating Expenses".to_string(), input.operating_expenses),
+                ("Collected Rent".to_string(), input.collected_rent),
+            ],
+            result: ratio,
+            sources,
+            citations,
+        });
+        
+        ratio
+    });
+
+    // Calculate break-even occupancy against the rent the property could collect when full
+    let break_even_occupancy = potential_rent(&input).map(|(potential, fields)| {
+        let debt_service = debt_service.unwrap_or(0.0);
+        let occupancy = ((input.operating_expenses + debt_service) / potential) * 100.0;
+        let mut source_fields = vec!["operating_expenses"];
+        source_fields.extend(debt_fields);
+        source_fields.extend(fields);
+        let (sources, citations) = input.sources_for(&source_fields);
+        
+        audit_trail.push(CalculationStep {
+            metric: "Break-even Occupancy".to_string(),
+            formula: "((Operating Expenses + Annual Debt Service) / Potential Rent) * 100"
+                .to_string(),
+            inputs: vec![
+                ("Operating Expenses".to_string(), input.operating_expenses),
+                ("Annual Debt Service".to_string(), debt_service),
+                ("Potential Rent".to_string(), potential),
+            ],
+            result: occupancy,
+            sources,
+            citations,
+        });
+        
+        occupancy
+    });
+
+    // Calculate per-unit metrics if the unit count is available
+    let units = input.unit_count.filter(|count| *count > 0).map(f64::from);
+    let mut per_unit = |metric: &str, label: &str, amount: f64, fields: &[&str]| {
+        units.map(|count| {
+            let mut source_fields = fields.to_vec();
+            source_fields.push("unit_count");
+            let (sources, citations) = input.sources_for(&source_fields);
+            let value = amount / count;
+            
+            audit_trail.push(CalculationStep {
+                metric: metric.to_string(),
+                formula: format!("{} / Unit Count", label),
+                inputs: vec![(label.to_string(), amount), ("Unit Count".to_string(), count)],
+                result: value,
+                sources,
+                citations,
+            });
+            
+            value
+        })
+    };
+    let rent_per_unit = per_unit(
+        "Rent per Unit",
+        "Collected Rent",
+        input.collected_rent,
+        &["collected_rent"],
+    );
+    let expenses_per_unit = per_unit(
+        "Expenses per Unit",
+        "Operating Expenses",
+        input.operating_expenses,
+        &["operating_expenses"],
+    );
+    let noi_per_unit = per_unit(
+        "NOI per Unit",
+        "NOI",
+        noi,
+        &["collected_rent", "operating_expenses"],
+    );
+    let value_per_unit = input.property_value.and_then(|value| {
+        per_unit("Value per Unit", "Property Value", value, &["property_value"])
+    });
+
+    // Size the largest loan the property supports under the policy
+    let loan_sizing = size_max_loan(noi, &input, rules);
+    if let Some(sizing) = &loan_sizing {
+        let (sources, citations) = input.sources_for(&[
+            "collected_rent",
+            "operating_expenses",
+            "interest_rate",
+            "property_value",
+        ]);
+        let mut inputs = vec![
+            ("NOI".to_string(), noi),
+            ("Loan Constant".to_string(), sizing.loan_constant),
+            ("Min DSCR".to_string(), thresholds.min_dscr),
+            ("Min Debt Yield (%)".to_string(), thresholds.min_debt_yield),
+        ];
+        if let Some(value) = input.property_value {
+            inputs.push(("Property Value".to_string(), value));
+            inputs.push(("Max LTV (%)".to_string(), thresholds.max_ltv));
+        }
+        
+        audit_trail.push(CalculationStep {
+            metric: "Max Loan Amount".to_string(),
+            formula: "min(NOI / (Min DSCR * Loan Constant), Property Value * Max LTV / 100, NOI / (Min Debt Yield / 100))".to_string(),
+            inputs,
+            result: sizing.max_loan_amount,
+            sources,
+            citations,
+        });
+        
+        if let Some(balance) = input.mortgage_balance.filter(|b| *b > sizing.max_loan_amount) {
+            warnings.push(format!(
+                "{}: Mortgage balance of ${:.2} exceeds the maximum supportable loan of ${:.2} ({} constrained)",
+                severities.loan_above_max_sizing.warning_prefix(),
+                balance,
+                sizing.max_loan_amount,
+                sizing.binding_constraint
+            ));
+        }
+    }
+
+    // Check that bank deposits support the reported rent
+    let deposit_coverage = match input.bank_deposits {
+        Some(deposits) if input.collected_rent > 0.0 => {
+            let coverage = (deposits / input.collected_rent) * 100.0;
+            let (sources, citations) = input.sources_for(&["bank_deposits", "collected_rent"]);
+            
+            audit_trail.push(CalculationStep {
+                metric: "Deposit Coverage".to_string(),
+                formula: "(Annualized Bank Deposits / Collected Rent) * 100".to_string(),
+                inputs: vec![
+                    ("Annualized Bank Deposits".to_string(), deposits),
+                    ("Collected Rent".to_string(), input.collected_rent),
+                ],
+                result: coverage,
+                sources,
+                citations,
+            });
+            
+            if coverage < thresholds.min_deposit_coverage {
+                warnings.push(format!(
+                    "{}: Bank deposits cover only {:.1}% of reported rent",
+                    severities.deposits_below_rent.warning_prefix(),
+                    coverage
+                ));
+            }
+            
+            Some(coverage)
+        }
+        _ => None,
+    };
+
+    // Check that the property is insured for enough of its value
+    let insurance_coverage_ratio = match (input.insurance_coverage, input.property_value) {
+        (Some(coverage), Some(value)) if value > 0.0 => {
+            let ratio = (coverage / value) * 100.0;
+            let (sources, citations) = input.sources_for(&["insurance_coverage", "property_value"]);
+            
+            audit_trail.push(CalculationStep {
+                metric: "Insurance Coverage".to_string(),
+                formula: "(Insurance Coverage / Property Value) * 100".to_string(),
+                inputs: vec![
+                    ("Insurance Coverage".to_string(), coverage),
+                    ("Property Value".to_string(), value),
+                ],
+                result: ratio,
+                sources,
+                citations,
+            });
+            
+            if ratio < thresholds.min_insurance_coverage {
+                warnings.push(format!(
+                    "{}: Insurance coverage of ${:.2} is {:.1}% of the property value, below {:.1}%",
+                    severities.insufficient_insurance.warning_prefix(),
+                    coverage,
+                    ratio,
+                    thresholds.min_insurance_coverage
+                ));
+            }
+            
+            Some(ratio)
+        }
+        _ => None,
+    };
+
     // Check for missing critical data
-    if input.debt_service.is_none() {
-        warnings.push("Note: Debt service not provided - DSCR cannot be calculated".to_string());
+    if debt_service.is_none() {
+        warnings.push("Note: Debt service or loan terms not provided - DSCR cannot be calculated".to_string());
     }
     if input.property_value.is_none() {
         warnings.push("Note: Property value not provided - Cap Rate and LTV cannot be calculated".to_string());
@@ -209,61 +963,218 @@ pub fn calculate_underwriting(input: UnderwritingInput) -> UnderwritingResult {
         cap_rate,
         ltv,
         gross_rent_multiplier,
+        annual_debt_service: debt_service,
+        amortization_schedule: loan.as_ref().map(Loan::annual_schedule),
+        debt_yield,
+        break_even_occupancy,
+        expense_ratio,
+        rent_per_unit,
+        expenses_per_unit,
+        noi_per_unit,
+        value_per_unit,
+        max_loan_amount: loan_sizing.as_ref().map(|sizing| sizing.max_loan_amount),
+        loan_sizing,
+        deposit_coverage,
+        insurance_coverage_ratio,
         audit_trail,
         warnings,
     }
 }
 
-/// Apply stress test scenarios to underwriting results
-pub fn apply_stress_test(input: StressTestInput) -> StressTestResult {
-    let base = &input.base_result;
-    
-    // Get base values
-    let mut collected_rent = 0.0;
-    let mut operating_expenses = 0.0;
-    let mut debt_service = 0.0;
-
-    // Extract from audit trail
-    for step in &base.audit_trail {
-        for (name, value) in &step.inputs {
-            if name == "Collected Rent" {
-                collected_rent = *value;
-            } else if name == "Operating Expenses" {
-                operating_expenses = *value;
-            } else if name == "Annual Debt Service" {
-                debt_service = *value;
-            }
+/// Input fields the existing loan is read from
+const LOAN_FIELDS: [&str; 5] = [
+    "mortgage_balance",
+    "interest_rate",
+    "amortization_years",
+    "interest_only_years",
+    "loan_term_years",
+];
+
+/// Debt service the base underwriting uses: as provided, otherwise derived from the loan
+pub(crate) fn base_debt_service(input: &UnderwritingInput, rules: &PolicyRules) -> Option<f64> {
+    input
+        .debt_service
+        .or_else(|| Loan::from_input(input, rules).map(|loan| loan.annual_debt_service()))
+}
+
+/// Rent the property would collect fully occupied, with the input fields it was derived from
+///
+/// Uses the gross scheduled rent when available, otherwise grosses collected rent up by the
+/// occupancy rate.
+pub(crate) fn potential_rent(input: &UnderwritingInput) -> Option<(f64, Vec<&'static str>)> {
+    match (input.gross_scheduled_rent, input.occupancy_rate) {
+        (Some(gsr), _) if gsr > 0.0 => Some((gsr, vec!["gross_scheduled_rent"])),
+        (_, Some(occupancy)) if occupancy > 0.0 && input.collected_rent > 0.0 => Some((
+            input.collected_rent / (occupancy / 100.0),
+            vec!["collected_rent", "occupancy_rate"],
+        )),
+        _ => None,
+    }
+}
+
+/// Annual debt service per dollar of a loan amortizing monthly
+pub fn loan_constant(interest_rate: f64, amortization_years: f64) -> f64 {
+    let rate = interest_rate / 100.0 / 12.0;
+    let payments = amortization_years * 12.0;
+    if rate == 0.0 {
+        return 12.0 / payments;
+    }
+    12.0 * rate / (1.0 - (1.0 + rate).powf(-payments))
+}
+
+/// Find the largest loan that satisfies the policy's minimum DSCR, maximum LTV and minimum debt
+/// yield at once
+///
+/// Needs a positive NOI and the interest rate; the LTV constraint only applies when the property
+/// value is known.
+pub fn size_max_loan(
+    noi: f64,
+    input: &UnderwritingInput,
+    rules: &PolicyRules,
+) -> Option<LoanSizing> {
+    let thresholds = &rules.thresholds;
+    let interest_rate = input.interest_rate?;
+    if noi <= 0.0 || thresholds.min_dscr <= 0.0 || thresholds.min_debt_yield <= 0.0 {
+        return None;
+    }
+
+    let constant = loan_constant(interest_rate, thresholds.amortization_years);
+    let dscr_max_loan = noi / (thresholds.min_dscr * constant);
+    let ltv_max_loan = input
+        .property_value
+        .map(|value| value * thresholds.max_ltv / 100.0);
+    let debt_yield_max_loan = noi / (thresholds.min_debt_yield / 100.0);
+
+    let mut constraints = vec![("dscr", dscr_max_loan), ("debt_yield", debt_yield_max_loan)];
+    if let Some(ltv_loan) = ltv_max_loan {
+        constraints.push(("ltv", ltv_loan));
+    }
+    let (binding_constraint, max_loan_amount) = constraints
+        .into_iter()
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    Some(LoanSizing {
+        interest_rate,
+        amortization_years: thresholds.amortization_years,
+        term_years: thresholds.loan_term_years,
+        loan_constant: constant,
+        dscr_max_loan,
+        ltv_max_loan,
+        debt_yield_max_loan,
+        max_loan_amount,
+        binding_constraint: binding_constraint.to_string(),
+        annual_debt_service: max_loan_amount * constant,
+        balloon_balance: Loan {
+            balance: max_loan_amount,
+            interest_rate,
+            amortization_years: thresholds.amortization_years,
+            interest_only_years: 0.0,
+            term_years: thresholds.loan_term_years,
         }
+        .balloon_balance(),
+    })
+}
+
+/// Current occupancy rate, falling back to collected over scheduled rent
+fn base_occupancy_rate(input: &UnderwritingInput) -> Option<f64> {
+    input.occupancy_rate.or_else(|| {
+        input
+            .gross_scheduled_rent
+            .filter(|gsr| *gsr > 0.0)
+            .map(|gsr| (input.collected_rent / gsr) * 100.0)
+    })
+}
+
+/// Annual debt service after an interest rate shock
+///
+/// Reprices the loan's payments when its balance and rate are known. Otherwise shifts interest on
+/// the mortgage balance, or scales debt service by the relative change in the interest rate.
+fn stressed_debt_service(
+    input: &UnderwritingInput,
+    rules: &PolicyRules,
+    interest_adj_bps: f64,
+) -> Option<f64> {
+    if let Some(loan) = Loan::from_input(input, rules) {
+        let base = loan.annual_debt_service();
+        let change = loan.with_rate_adjustment(interest_adj_bps).annual_debt_service() - base;
+        return Some(input.debt_service.unwrap_or(base) + change);
     }
+    let debt_service = input.debt_service?;
+    match (input.mortgage_balance, input.interest_rate) {
+        (Some(balance), _) => Some(debt_service + balance * (interest_adj_bps / 10000.0)),
+        (None, Some(rate)) if rate > 0.0 => {
+            Some(debt_service * (rate + interest_adj_bps / 100.0) / rate)
+        }
+        _ => Some(debt_service),
+    }
+}
+
+/// Rate shock at which the loan's repriced debt service uses up the NOI, found by bisection
+fn loan_rate_breakeven(input: &UnderwritingInput, rules: &PolicyRules, noi: f64) -> Option<f64> {
+    let rate = input.interest_rate?;
+    let shortfall = |bps: f64| stressed_debt_service(input, rules, bps).map(|ds| noi - ds);
+    let (mut low, mut high) = (-rate * 100.0, 10000.0);
+    // No breakeven when even a zero rate cannot be covered, or a 100% shock still can
+    if shortfall(low)? < 0.0 || shortfall(high)? > 0.0 {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if shortfall(mid)? > 0.0 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+/// Apply stress test scenarios to underwriting inputs, assuming the policy's loan terms where the
+/// facts do not give them
+pub fn apply_stress_test(input: StressTestInput, rules: &PolicyRules) -> StressTestResult {
+    let base_input = &input.base_input;
+    let base = calculate_underwriting(base_input.clone(), rules);
+
+    let mut collected_rent = base_input.collected_rent;
+    let mut operating_expenses = base_input.operating_expenses;
 
     // Apply stress adjustments
     if let Some(rent_adj) = input.rent_adjustment {
         collected_rent *= 1.0 + (rent_adj / 100.0);
     }
 
+    // Occupancy shifts collected rent in proportion to occupied units
+    if let Some(occupancy_adj) = input.occupancy_adjustment {
+        match base_occupancy_rate(base_input).filter(|occ| *occ > 0.0) {
+            Some(occupancy) => {
+                let stressed_occupancy = (occupancy + occupancy_adj).clamp(0.0, 100.0);
+                collected_rent *= stressed_occupancy / occupancy;
+            }
+            None => collected_rent *= 1.0 + (occupancy_adj / 100.0),
+        }
+    }
+
     if let Some(expense_adj) = input.expense_adjustment {
         operating_expenses *= 1.0 + (expense_adj / 100.0);
     }
 
-    if let Some(interest_adj) = input.interest_rate_adjustment {
-        // Simplified: adjust debt service proportionally to interest rate change
-        // In reality, this would require loan balance and term
-        debt_service *= 1.0 + (interest_adj / 10000.0);
-    }
+    let debt_service = stressed_debt_service(
+        base_input,
+        rules,
+        input.interest_rate_adjustment.unwrap_or(0.0),
+    );
 
     // Calculate stressed metrics
     let stressed_noi = collected_rent - operating_expenses;
-    let stressed_dscr = if debt_service > 0.0 {
-        Some(stressed_noi / debt_service)
-    } else {
-        None
-    };
-    let stressed_cash_flow = Some(stressed_noi - debt_service);
+    let stressed_dscr = debt_service
+        .filter(|ds| *ds > 0.0)
+        .map(|ds| stressed_noi / ds);
+    let stressed_cash_flow = debt_service.map(|ds| stressed_noi - ds);
 
     // Calculate changes
     let noi_change = stressed_noi - base.noi;
     let noi_change_pct = if base.noi != 0.0 {
-        (noi_change / base.noi) * 100.0
+        (noi_change / base.noi.abs()) * 100.0
     } else {
         0.0
     };
@@ -291,9 +1202,189 @@ pub fn apply_stress_test(input: StressTestInput) -> StressTestResult {
     }
 }
 
+/// Solve the single-variable breakevens for the base inputs
+pub fn calculate_breakevens(input: &UnderwritingInput, rules: &PolicyRules) -> StressBreakevens {
+    let base_debt_service = base_debt_service(input, rules);
+    let debt_service = base_debt_service.unwrap_or(0.0);
+    let noi = input.collected_rent - input.operating_expenses;
+    let required_rent = input.operating_expenses + debt_service;
+
+    let rent_change_pct = (input.collected_rent > 0.0)
+        .then(|| (required_rent / input.collected_rent - 1.0) * 100.0);
+
+    let expense_change_pct = (input.operating_expenses > 0.0).then(|| {
+        ((input.collected_rent - debt_service) / input.operating_expenses - 1.0) * 100.0
+    });
+
+    let occupancy_rate = base_occupancy_rate(input)
+        .filter(|_| input.collected_rent > 0.0)
+        .map(|occupancy| occupancy * required_rent / input.collected_rent);
+
+    let interest_rate_change_bps = match (base_debt_service, input.mortgage_balance, input.interest_rate) {
+        (Some(_), Some(balance), Some(_)) if balance > 0.0 => loan_rate_breakeven(input, rules, noi),
+        (Some(ds), Some(balance), None) if balance > 0.0 => Some((noi - ds) / balance * 10000.0),
+        (Some(ds), None, Some(rate)) if ds > 0.0 && rate > 0.0 => {
+            Some((noi / ds - 1.0) * rate * 100.0)
+        }
+        _ => None,
+    };
+
+    StressBreakevens {
+        rent_change_pct,
+        expense_change_pct,
+        occupancy_rate,
+        interest_rate_change_bps,
+    }
+}
+
+/// Run every combination of the grid's shocks against the base inputs
+pub fn run_stress_grid(
+    base_input: UnderwritingInput,
+    grid: &StressTestGrid,
+    rules: &PolicyRules,
+) -> Result<StressTestMatrix, String> {
+    fn axis(values: &[f64]) -> Vec<f64> {
+        if values.is_empty() {
+            vec![0.0]
+        } else {
+            values.to_vec()
+        }
+    }
+
+    let rent_axis = axis(&grid.rent_adjustments);
+    let expense_axis = axis(&grid.expense_adjustments);
+    let occupancy_axis = axis(&grid.occupancy_adjustments);
+    let interest_axis = axis(&grid.interest_rate_adjustments);
+
+    let scenario_count =
+        rent_axis.len() * expense_axis.len() * occupancy_axis.len() * interest_axis.len();
+    if scenario_count > MAX_STRESS_SCENARIOS {
+        return Err(format!(
+            "Stress grid expands to {} scenarios, maximum is {}",
+            scenario_count, MAX_STRESS_SCENARIOS
+        ));
+    }
+
+    let mut scenarios = Vec::with_capacity(scenario_count);
+    for &rent_adjustment in &rent_axis {
+        for &expense_adjustment in &expense_axis {
+            for &occupancy_adjustment in &occupancy_axis {
+                for &interest_rate_adjustment in &interest_axis {
+                    let result = apply_stress_test(
+                        StressTestInput {
+                            base_input: base_input.clone(),
+                            occupancy_adjustment: Some(occupancy_adjustment),
+                            rent_adjustment: Some(rent_adjustment),
+                            expense_adjustment: Some(expense_adjustment),
+                            interest_rate_adjustment: Some(interest_rate_adjustment),
+                        },
+                        rules,
+                    );
+                    scenarios.push(StressScenario {
+                        rent_adjustment,
+                        expense_adjustment,
+                        occupancy_adjustment,
+                        interest_rate_adjustment,
+                        result,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(StressTestMatrix {
+        breakevens: calculate_breakevens(&base_input, rules),
+        base_result: calculate_underwriting(base_input, rules),
+        scenarios,
+    })
+}
+
+/// Metrics of a result without its audit trail and warnings, as stored on a run
+pub fn result_snapshot(result: &UnderwritingResult) -> Result<JsonValue, serde_json::Error> {
+    let mut snapshot = serde_json::to_value(result)?;
+    if let Some(obj) = snapshot.as_object_mut() {
+        obj.remove("audit_trail");
+        obj.remove("warnings");
+    }
+    Ok(snapshot)
+}
+
+/// Numeric (or missing) fields of a JSON object, keyed by field name
+fn numeric_fields(value: &JsonValue) -> BTreeMap<String, Option<f64>> {
+    value
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter(|(_, v)| v.is_number() || v.is_null())
+                .map(|(k, v)| (k.clone(), v.as_f64()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Compare two JSON snapshots field by field
+pub fn diff_metrics(from: &JsonValue, to: &JsonValue) -> Vec<MetricDiff> {
+    let from_fields = numeric_fields(from);
+    let to_fields = numeric_fields(to);
+    let mut metrics: Vec<&String> = from_fields.keys().chain(to_fields.keys()).collect();
+    metrics.sort();
+    metrics.dedup();
+
+    metrics
+        .into_iter()
+        .map(|metric| {
+            let from_value = from_fields.get(metric).copied().flatten();
+            let to_value = to_fields.get(metric).copied().flatten();
+            let change = match (from_value, to_value) {
+                (Some(a), Some(b)) => Some(b - a),
+                _ => None,
+            };
+            let change_pct = match (from_value, change) {
+                (Some(a), Some(c)) if a != 0.0 => Some((c / a.abs()) * 100.0),
+                _ => None,
+            };
+            MetricDiff {
+                metric: metric.clone(),
+                from: from_value,
+                to: to_value,
+                change,
+                change_pct,
+            }
+        })
+        .collect()
+}
+
+/// Compare two underwriting runs metric by metric
+pub fn diff_underwriting_runs(from: &UnderwritingRun, to: &UnderwritingRun) -> UnderwritingRunDiff {
+    let added_fact_ids = to
+        .input_fact_ids
+        .iter()
+        .filter(|id| !from.input_fact_ids.contains(id))
+        .cloned()
+        .collect();
+    let removed_fact_ids = from
+        .input_fact_ids
+        .iter()
+        .filter(|id| !to.input_fact_ids.contains(id))
+        .cloned()
+        .collect();
+
+    UnderwritingRunDiff {
+        from_run_id: from.run_id.clone(),
+        to_run_id: to.run_id.clone(),
+        from_version: from.version,
+        to_version: to.version,
+        metrics: diff_metrics(&from.result, &to.result),
+        inputs: diff_metrics(&from.inputs, &to.inputs),
+        added_fact_ids,
+        removed_fact_ids,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::deal_agent::Severity;
 
     #[test]
     fn test_basic_underwriting() {
@@ -307,9 +1398,16 @@ mod tests {
             property_value: Some(1000000.0),
             mortgage_balance: Some(700000.0),
             interest_rate: Some(4.5),
+            amortization_years: None,
+            interest_only_years: None,
+            loan_term_years: None,
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
         };
 
-        let result = calculate_underwriting(input);
+        let result = calculate_underwriting(input, &PolicyRules::default());
 
         assert_eq!(result.noi, 69000.0);
         assert!(result.dscr.is_some());
@@ -334,13 +1432,107 @@ mod tests {
             property_value: None,
             mortgage_balance: None,
             interest_rate: None,
+            amortization_years: None,
+            interest_only_years: None,
+            loan_term_years: None,
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
         };
 
-        let result = calculate_underwriting(input);
+        let result = calculate_underwriting(input, &PolicyRules::default());
 
         assert!(result.warnings.iter().any(|w| w.contains("DSCR")));
     }
 
+    #[test]
+    fn test_extended_metrics_and_loan_sizing() {
+        let input = UnderwritingInput {
+            unit_count: Some(20),
+            occupancy_rate: Some(90.0),
+            gross_scheduled_rent: None,
+            collected_rent: 108000.0,
+            operating_expenses: 48000.0,
+            debt_service: Some(42000.0),
+            property_value: Some(1000000.0),
+            mortgage_balance: Some(650000.0),
+            interest_rate: Some(6.0),
+            amortization_years: None,
+            interest_only_years: None,
+            loan_term_years: None,
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
+        };
+        let mut rules = PolicyRules::default();
+
+        let result = calculate_underwriting(input.clone(), &rules);
+        assert!((result.debt_yield.unwrap() - 60000.0 / 6500.0).abs() < 1e-9);
+        assert!((result.expense_ratio.unwrap() - 44.444).abs() < 1e-3);
+        // Potential rent is 120,000 so expenses and debt service need 75% occupancy
+        assert!((result.break_even_occupancy.unwrap() - 75.0).abs() < 1e-9);
+        assert_eq!(result.noi_per_unit, Some(3000.0));
+        assert_eq!(result.value_per_unit, Some(50000.0));
+
+        // The 1.25 DSCR covenant binds below the 80% LTV and 8% debt yield loans
+        let sizing = result.loan_sizing.unwrap();
+        assert_eq!(sizing.binding_constraint, "dscr");
+        assert!((60000.0 / sizing.annual_debt_service - 1.25).abs() < 1e-9);
+        assert_eq!(sizing.ltv_max_loan, Some(800000.0));
+        assert_eq!(sizing.debt_yield_max_loan, 750000.0);
+        assert!(sizing.balloon_balance > 0.0 && sizing.balloon_balance < sizing.max_loan_amount);
+        assert!(!result.warnings.iter().any(|w| w.contains("maximum supportable loan")));
+
+        rules.thresholds.min_dscr = 1.0;
+        rules.thresholds.min_debt_yield = 9.5;
+        let result = calculate_underwriting(input, &rules);
+        let sizing = result.loan_sizing.unwrap();
+        assert_eq!(sizing.binding_constraint, "debt_yield");
+        assert!((sizing.max_loan_amount - 60000.0 / 0.095).abs() < 1e-6);
+        assert!(result.warnings.iter().any(|w| w.contains("maximum supportable loan")));
+        assert!(result.warnings.iter().any(|w| w.contains("Debt yield")));
+    }
+
+    #[test]
+    fn test_policy_thresholds() {
+        let input = UnderwritingInput {
+            unit_count: None,
+            occupancy_rate: None,
+            gross_scheduled_rent: None,
+            collected_rent: 100000.0,
+            operating_expenses: 40000.0,
+            debt_service: Some(45000.0),
+            property_value: Some(1000000.0),
+            mortgage_balance: Some(780000.0),
+            interest_rate: None,
+            amortization_years: None,
+            interest_only_years: None,
+            loan_term_years: None,
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
+        };
+
+        // DSCR 1.33 and LTV 78% pass the standard covenants
+        let result = calculate_underwriting(input.clone(), &PolicyRules::default());
+        assert!(!result.warnings.iter().any(|w| w.contains("DSCR") || w.contains("LTV")));
+
+        let mut rules = PolicyRules::default();
+        rules.thresholds.min_dscr = 1.4;
+        rules.thresholds.max_ltv = 75.0;
+        rules.severities.ltv_above_maximum = Severity::Critical;
+        let result = calculate_underwriting(input, &rules);
+        assert!(result
+            .warnings
+            .contains(&"Warning: DSCR of 1.33 is below required 1.40 threshold".to_string()));
+        assert!(result
+            .warnings
+            .contains(&"Critical: LTV of 78.00% is above 75.00%".to_string()));
+    }
+
     #[test]
     fn test_stress_test() {
         let base_input = UnderwritingInput {
@@ -353,22 +1545,265 @@ mod tests {
             property_value: None,
             mortgage_balance: None,
             interest_rate: None,
+            amortization_years: None,
+            interest_only_years: None,
+            loan_term_years: None,
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
         };
 
-        let base_result = calculate_underwriting(base_input);
+        let base_result = calculate_underwriting(base_input.clone(), &PolicyRules::default());
 
         let stress_input = StressTestInput {
-            base_result: base_result.clone(),
+            base_input,
             occupancy_adjustment: None,
             rent_adjustment: Some(-10.0), // 10% rent decrease
             expense_adjustment: Some(5.0), // 5% expense increase
             interest_rate_adjustment: None,
         };
 
-        let stress_result = apply_stress_test(stress_input);
+        let stress_result = apply_stress_test(stress_input, &PolicyRules::default());
 
         assert!(stress_result.stressed_noi < base_result.noi);
         assert!(stress_result.comparison.noi_change < 0.0);
     }
-}
 
+    #[test]
+    fn test_occupancy_and_interest_stress() {
+        let base_input = UnderwritingInput {
+            unit_count: Some(20),
+            occupancy_rate: Some(95.0),
+            gross_scheduled_rent: None,
+            collected_rent: 190000.0,
+            operating_expenses: 80000.0,
+            debt_service: Some(70000.0),
+            property_value: None,
+            mortgage_balance: Some(1000000.0),
+            interest_rate: Some(6.0),
+            amortization_years: None,
+            interest_only_years: None,
+            loan_term_years: None,
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
+        };
+        let stress = StressTestInput {
+            base_input,
+            occupancy_adjustment: Some(-10.0),
+            rent_adjustment: None,
+            expense_adjustment: None,
+            interest_rate_adjustment: Some(100.0),
+        };
+
+        // An interest-only loan over its whole term
+        let mut rules = PolicyRules::default();
+        rules.thresholds.interest_only_years = 10.0;
+        let result = apply_stress_test(stress.clone(), &rules);
+
+        // 95% -> 85% occupancy: 190,000 * 85 / 95 = 170,000
+        assert!((result.stressed_noi - 90000.0).abs() < 1e-6);
+        // +100bps on a 1,000,000 balance adds 10,000 of debt service
+        assert!((result.stressed_cash_flow.unwrap() - 10000.0).abs() < 1e-6);
+
+        // Amortizing over 30 years, payments go from 71,946 to 79,836
+        let result = apply_stress_test(stress, &PolicyRules::default());
+        assert!((result.stressed_cash_flow.unwrap() - (90000.0 - 70000.0 - 7890.2)).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_loan_schedule() {
+        let loan = Loan {
+            balance: 1000000.0,
+            interest_rate: 6.0,
+            amortization_years: 30.0,
+            interest_only_years: 2.0,
+            term_years: 10.0,
+        };
+
+        let schedule = loan.annual_schedule();
+        assert_eq!(schedule.len(), 10);
+        // Interest only for two years, then fully amortizing payments
+        assert!((schedule[0].debt_service - 60000.0).abs() < 1e-6);
+        assert_eq!(schedule[1].ending_balance, 1000000.0);
+        assert!((schedule[2].debt_service - 71946.06).abs() < 0.01);
+        assert!(schedule[2].principal > 0.0);
+        assert_eq!(loan.balloon_balance(), schedule[9].ending_balance);
+        assert_eq!(loan.annual_debt_service(), schedule[0].debt_service);
+
+        // Derived debt service feeds DSCR when no debt service fact exists
+        let input = UnderwritingInput {
+            unit_count: None,
+            occupancy_rate: None,
+            gross_scheduled_rent: None,
+            collected_rent: 150000.0,
+            operating_expenses: 60000.0,
+            debt_service: None,
+            property_value: None,
+            mortgage_balance: Some(1000000.0),
+            interest_rate: Some(6.0),
+            amortization_years: Some(30.0),
+            interest_only_years: Some(2.0),
+            loan_term_years: Some(10.0),
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
+        };
+        let result = calculate_underwriting(input.clone(), &PolicyRules::default());
+        assert_eq!(result.annual_debt_service, Some(loan.annual_debt_service()));
+        assert!((result.dscr.unwrap() - 1.5).abs() < 1e-9);
+        assert_eq!(result.amortization_schedule.unwrap().len(), 10);
+
+        // Rates can rise until interest-only payments reach the 90,000 NOI
+        let breakevens = calculate_breakevens(&input, &PolicyRules::default());
+        assert!((breakevens.interest_rate_change_bps.unwrap() - 300.0).abs() < 1e-6);
+
+        // A term read as a billion years falls back to the policy's ten
+        let input = UnderwritingInput {
+            loan_term_years: Some(1e9),
+            ..input
+        };
+        let result = calculate_underwriting(input, &PolicyRules::default());
+        assert_eq!(result.amortization_schedule.unwrap().len(), 10);
+        assert!(result.warnings.contains(
+            &"Warning: Loan term of 1000000000 years is outside 0 to 50 years, 10 years assumed \
+              instead"
+                .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_stress_grid_and_breakevens() {
+        let base_input = UnderwritingInput {
+            unit_count: None,
+            occupancy_rate: Some(90.0),
+            gross_scheduled_rent: None,
+            collected_rent: 100000.0,
+            operating_expenses: 40000.0,
+            debt_service: Some(45000.0),
+            property_value: None,
+            mortgage_balance: Some(500000.0),
+            interest_rate: None,
+            amortization_years: None,
+            interest_only_years: None,
+            loan_term_years: None,
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
+        };
+
+        let grid = StressTestGrid {
+            rent_adjustments: vec![0.0, -5.0, -10.0],
+            expense_adjustments: vec![0.0, 10.0],
+            occupancy_adjustments: vec![],
+            interest_rate_adjustments: vec![0.0, 200.0],
+        };
+        let matrix = run_stress_grid(base_input.clone(), &grid, &PolicyRules::default()).unwrap();
+        assert_eq!(matrix.scenarios.len(), 12);
+        assert_eq!(matrix.base_result.noi, 60000.0);
+
+        // Rent can fall 15% before cash flow reaches zero
+        let breakevens = matrix.breakevens;
+        assert!((breakevens.rent_change_pct.unwrap() + 15.0).abs() < 1e-6);
+        assert!((breakevens.expense_change_pct.unwrap() - 37.5).abs() < 1e-6);
+        assert!((breakevens.occupancy_rate.unwrap() - 76.5).abs() < 1e-6);
+        assert!((breakevens.interest_rate_change_bps.unwrap() - 300.0).abs() < 1e-6);
+
+        let at_rent_breakeven = apply_stress_test(
+            StressTestInput {
+                base_input: base_input.clone(),
+                occupancy_adjustment: None,
+                rent_adjustment: breakevens.rent_change_pct,
+                expense_adjustment: None,
+                interest_rate_adjustment: None,
+            },
+            &PolicyRules::default(),
+        );
+        assert!(at_rent_breakeven.stressed_cash_flow.unwrap().abs() < 1e-6);
+
+        let oversized = StressTestGrid {
+            rent_adjustments: vec![0.0; 11],
+            expense_adjustments: vec![0.0; 10],
+            occupancy_adjustments: vec![0.0; 10],
+            interest_rate_adjustments: vec![],
+        };
+        assert!(run_stress_grid(base_input, &oversized, &PolicyRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_diff_metrics() {
+        let base_input = UnderwritingInput {
+            unit_count: None,
+            occupancy_rate: None,
+            gross_scheduled_rent: None,
+            collected_rent: 100000.0,
+            operating_expenses: 40000.0,
+            debt_service: Some(50000.0),
+            property_value: None,
+            mortgage_balance: None,
+            interest_rate: None,
+            amortization_years: None,
+            interest_only_years: None,
+            loan_term_years: None,
+            appraisal_cap_rate: None,
+            bank_deposits: None,
+            insurance_coverage: None,
+            sources: BTreeMap::new(),
+        };
+        let corrected_input = UnderwritingInput {
+            operating_expenses: 30000.0,
+            ..base_input.clone()
+        };
+
+        let rules = PolicyRules::default();
+        let before = result_snapshot(&calculate_underwriting(base_input, &rules)).unwrap();
+        let after = result_snapshot(&calculate_underwriting(corrected_input, &rules)).unwrap();
+        assert!(before.get("audit_trail").is_none());
+
+        let diff = diff_metrics(&before, &after);
+        let dscr = diff.iter().find(|m| m.metric == "dscr").unwrap();
+        assert_eq!(dscr.from, Some(1.2));
+        assert_eq!(dscr.to, Some(1.4));
+        assert!((dscr.change.unwrap() - 0.2).abs() < 1e-9);
+
+        let cap_rate = diff.iter().find(|m| m.metric == "cap_rate").unwrap();
+        assert_eq!(cap_rate.from, None);
+        assert_eq!(cap_rate.change, None);
+    }
+
+    #[test]
+    fn test_calculation_sources() {
+        fn fact(fact_id: &str, fact_type: &str, value: &str, page: i32) -> Fact {
+            Fact {
+                source_citation: serde_json::json!({ "document": "doc-1", "page": page }),
+                ..Fact::for_test(fact_id, fact_type, value)
+            }
+        }
+
+        let facts = vec![
+            fact("f-rent", "collected_rent", "100000", 1),
+            fact("f-opex", "operating_expenses", "40000", 2),
+            fact("f-ds", "debt_service", "45000", 3),
+            fact("f-value", "property_value", "not a number", 4),
+        ];
+        let input = UnderwritingInput::from_facts(&facts, &PeriodBasis::default()).unwrap();
+        assert_eq!(input.source_fact_ids(), vec!["f-ds", "f-opex", "f-rent"]);
+
+        let result = calculate_underwriting(input, &PolicyRules::default());
+        let noi = &result.audit_trail[0];
+        assert_eq!(noi.sources, vec!["f-rent", "f-opex"]);
+        assert_eq!(noi.citations.len(), 2);
+        assert_eq!(noi.citations[1].page, 2);
+
+        let dscr = result
+            .audit_trail
+            .iter()
+            .find(|step| step.metric.starts_with("DSCR"))
+            .unwrap();
+        assert_eq!(dscr.sources, vec!["f-rent", "f-opex", "f-ds"]);
+    }
+}
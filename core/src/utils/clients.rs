@@ -1,4 +1,6 @@
-use crate::configs::postgres_config::{create_pool, Pool};
+use crate::configs::postgres_config::{
+    create_diesel_pool, create_pool, DieselConnection, DieselPool, Pool,
+};
 use crate::configs::redis_config::create_pool as create_redis_pool;
 use crate::configs::s3_config::{create_client, create_external_client};
 use crate::utils::rate_limit::init_throttle;
@@ -12,6 +14,7 @@ static REQWEST_CLIENT: OnceCell<ReqwestClient> = OnceCell::new();
 static S3_CLIENT: OnceCell<S3Client> = OnceCell::new();
 static S3_EXTERNAL_CLIENT: OnceCell<S3Client> = OnceCell::new();
 static PG_POOL: OnceCell<Pool> = OnceCell::new();
+static DIESEL_POOL: OnceCell<DieselPool> = OnceCell::new();
 static REDIS_POOL: OnceCell<RedisPool> = OnceCell::new();
 
 pub async fn initialize() {
@@ -27,6 +30,7 @@ pub async fn initialize() {
             .unwrap()
     });
     PG_POOL.get_or_init(|| async { create_pool() }.now_or_never().unwrap());
+    DIESEL_POOL.get_or_init(create_diesel_pool);
     init_throttle();
     REDIS_POOL.get_or_init(create_redis_pool);
     println!("Initialized clients and rate limiters");
@@ -48,6 +52,10 @@ pub async fn get_pg_client() -> Result<deadpool_postgres::Client, deadpool_postg
     PG_POOL.get().unwrap().get().await
 }
 
+pub fn get_diesel_conn() -> Result<DieselConnection, diesel::r2d2::PoolError> {
+    DIESEL_POOL.get().unwrap().get()
+}
+
 pub fn get_redis_pool() -> &'static RedisPool {
     REDIS_POOL.get().unwrap()
 }
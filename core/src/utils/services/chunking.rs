@@ -171,6 +171,7 @@ mod tests {
             ocr_strategy: OcrStrategy::All,
             segment_processing: SegmentProcessing::default(),
             segmentation_strategy: SegmentationStrategy::LayoutAnalysis,
+            structured_extraction: None,
             target_chunk_length: None,
             error_handling: ErrorHandlingStrategy::default(),
             llm_processing: LlmProcessing::default(),
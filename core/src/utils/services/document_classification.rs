@@ -0,0 +1,200 @@
+use crate::configs::llm_config::create_messages_from_template;
+use crate::models::document::{ClassificationAlternative, ClassificationSource, Document, DocumentType};
+use crate::models::llm::LlmProcessing;
+use crate::models::output::Chunk;
+use crate::models::structured_extraction::{ExtractionType, JsonSchema, StructuredExtraction};
+use crate::services::document_classifier::{
+    DocumentClassification, CLASSIFICATION_PAGES, MAX_ALTERNATIVES,
+};
+use crate::utils::services::llm::structured_llm_handler;
+use crate::utils::services::structured_extraction::validate_against_schema;
+use opentelemetry::Context;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Maximum number of characters of the first pages sent to the LLM
+const MAX_PROMPT_LENGTH: usize = 12_000;
+
+const DOCUMENT_TYPES: &[DocumentType] = &[
+    DocumentType::RentRoll,
+    DocumentType::ProfitAndLoss,
+    DocumentType::MortgageStatement,
+    DocumentType::TaxDocument,
+    DocumentType::BankStatement,
+    DocumentType::PropertyDeed,
+    DocumentType::InsurancePolicy,
+    DocumentType::Lease,
+    DocumentType::Appraisal,
+    DocumentType::Other,
+];
+
+/// JSON schema of the classification the LLM returns
+fn classification_schema() -> Value {
+    let types: Vec<&str> = DOCUMENT_TYPES.iter().map(|t| t.as_str()).collect();
+    json!({
+        "type": "object",
+        "properties": {
+            "document_type": {
+                "type": "string",
+                "enum": types,
+                "description": "The most likely type of the document"
+            },
+            "confidence": {
+                "type": "number",
+                "description": "Confidence between 0 and 1 that the document is of this type"
+            },
+            "alternatives": {
+                "type": "array",
+                "description": "Other plausible types, most likely first",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "document_type": { "type": "string", "enum": types },
+                        "confidence": { "type": "number" }
+                    },
+                    "required": ["document_type", "confidence"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["document_type", "confidence", "alternatives"],
+        "additionalProperties": false
+    })
+}
+
+/// Render the segments of the first pages with their layout type, up to the prompt length
+fn first_pages_to_prompt_content(chunks: &[Chunk]) -> String {
+    let mut content = String::new();
+    let segments = chunks
+        .iter()
+        .flat_map(|chunk| chunk.segments.iter())
+        .filter(|segment| segment.page_number <= CLASSIFICATION_PAGES)
+        .filter(|segment| !segment.content.trim().is_empty());
+    for segment in segments {
+        let rendered = format!(
+            "<segment type=\"{:?}\" page=\"{}\">\n{}\n</segment>\n",
+            segment.segment_type,
+            segment.page_number,
+            segment.content.trim()
+        );
+        if content.len() + rendered.len() > MAX_PROMPT_LENGTH {
+            break;
+        }
+        content.push_str(&rendered);
+    }
+    content
+}
+
+/// Map a schema-conforming LLM response to a classification
+fn classification_from_llm_response(response: &Value) -> Option<DocumentClassification> {
+    let document_type = DocumentType::from_str(response.get("document_type")?.as_str()?)?;
+    let confidence = response.get("confidence")?.as_f64()?.clamp(0.0, 1.0);
+    let alternatives = response
+        .get("alternatives")
+        .and_then(|a| a.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|alternative| {
+            let alternative_type = alternative.get("document_type")?.as_str()?;
+            if alternative_type == document_type.as_str() {
+                return None;
+            }
+            Some(ClassificationAlternative {
+                document_type: DocumentType::from_str(alternative_type)?.as_str().to_string(),
+                confidence: alternative.get("confidence")?.as_f64()?.clamp(0.0, 1.0),
+            })
+        })
+        .take(MAX_ALTERNATIVES)
+        .collect();
+
+    Some(DocumentClassification {
+        document_type,
+        confidence,
+        alternatives,
+        source: ClassificationSource::Llm,
+    })
+}
+
+/// Classify the document from its first pages with the LLM
+pub async fn classify_document_with_llm(
+    document: &Document,
+    chunks: &[Chunk],
+    llm_processing: LlmProcessing,
+    tracer: &opentelemetry::global::BoxedTracer,
+) -> Result<DocumentClassification, Box<dyn Error + Send + Sync>> {
+    let content = first_pages_to_prompt_content(chunks);
+    if content.is_empty() {
+        return Err("Document has no content to classify".into());
+    }
+
+    let schema = classification_schema();
+    let mut values = HashMap::new();
+    values.insert("file_name".to_string(), document.file_name.clone());
+    values.insert("content".to_string(), content);
+    let mut messages =
+        create_messages_from_template("document_classification_system", &HashMap::new())?;
+    messages.extend(create_messages_from_template(
+        "document_classification_user",
+        &values,
+    )?);
+
+    let response_format = serde_json::to_value(StructuredExtraction {
+        r#type: ExtractionType::JsonSchema,
+        json_schema: JsonSchema {
+            description: "Type of a commercial real estate deal document".to_string(),
+            name: "document_classification".to_string(),
+            schema: schema.clone(),
+            strict: true,
+        },
+    })?;
+    let response_text = structured_llm_handler(
+        llm_processing,
+        messages,
+        response_format,
+        tracer,
+        &Context::current(),
+    )
+    .await?;
+    let response: Value = serde_json::from_str(&response_text)?;
+    validate_against_schema(&response, &schema)?;
+
+    classification_from_llm_response(&response)
+        .ok_or_else(|| "Invalid document classification response".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classification_from_llm_response() {
+        let response = json!({
+            "document_type": "mortgage_statement",
+            "confidence": 0.92,
+            "alternatives": [
+                { "document_type": "mortgage_statement", "confidence": 0.92 },
+                { "document_type": "bank_statement", "confidence": 0.05 },
+                { "document_type": "tax_document", "confidence": 1.4 }
+            ]
+        });
+        assert!(validate_against_schema(&response, &classification_schema()).is_ok());
+
+        let classification = classification_from_llm_response(&response).unwrap();
+        assert_eq!(classification.document_type.as_str(), "mortgage_statement");
+        assert_eq!(classification.source, ClassificationSource::Llm);
+        assert_eq!(
+            classification.alternatives,
+            vec![
+                ClassificationAlternative {
+                    document_type: "bank_statement".to_string(),
+                    confidence: 0.05,
+                },
+                ClassificationAlternative {
+                    document_type: "tax_document".to_string(),
+                    confidence: 1.0,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,499 @@
+use crate::configs::llm_config::create_messages_from_template;
+use crate::models::document::{Document, DocumentType};
+use crate::models::fact::{BoundingBox, FactType, NewFact, SourceCitation};
+use crate::models::llm::LlmProcessing;
+use crate::models::output::{Chunk, Segment};
+use crate::models::structured_extraction::{ExtractionType, JsonSchema, StructuredExtraction};
+use crate::utils::services::llm::structured_llm_handler;
+use crate::utils::services::structured_extraction::validate_against_schema;
+use chrono::NaiveDate;
+use opentelemetry::Context;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use uuid::Uuid;
+
+/// Maximum number of characters of the source segment kept on a citation
+const CITATION_LINE_LENGTH: usize = 200;
+
+/// Unit of facts whose value is a date, stored as YYYY-MM-DD
+const DATE_UNIT: &str = "date";
+
+/// A fact the LLM is asked to find in a document
+struct FactField {
+    fact_type: FactType,
+    label: &'static str,
+    unit: &'static str,
+    description: &'static str,
+}
+
+const RENT_ROLL_FIELDS: &[FactField] = &[
+    FactField {
+        fact_type: FactType::UnitCount,
+        label: "Unit Count",
+        unit: "units",
+        description: "Total number of units in the property, occupied or vacant",
+    },
+    FactField {
+        fact_type: FactType::OccupancyRate,
+        label: "Occupancy Rate",
+        unit: "%",
+        description: "Physical occupancy as a percentage between 0 and 100",
+    },
+    FactField {
+        fact_type: FactType::GrossScheduledRent,
+        label: "Gross Scheduled Rent",
+        unit: "USD/year",
+        description: "Annual rent if every unit were leased at its scheduled or market rent",
+    },
+    FactField {
+        fact_type: FactType::CollectedRent,
+        label: "Collected Rent",
+        unit: "USD/year",
+        description: "Annual rent actually charged to or collected from current tenants",
+    },
+];
+
+const PROFIT_AND_LOSS_FIELDS: &[FactField] = &[
+    FactField {
+        fact_type: FactType::CollectedRent,
+        label: "Collected Rent",
+        unit: "USD/year",
+        description: "Annual rental income",
+    },
+    FactField {
+        fact_type: FactType::OperatingExpenses,
+        label: "Operating Expenses",
+        unit: "USD/year",
+        description: "Total annual operating expenses, excluding debt service, depreciation and capital expenditures",
+    },
+    FactField {
+        fact_type: FactType::NetOperatingIncome,
+        label: "Net Operating Income",
+        unit: "USD/year",
+        description: "Annual net operating income as stated in the document",
+    },
+];
+
+const MORTGAGE_STATEMENT_FIELDS: &[FactField] = &[
+    FactField {
+        fact_type: FactType::MortgageBalance,
+        label: "Mortgage Balance",
+        unit: "USD",
+        description: "Outstanding principal balance of the loan",
+    },
+    FactField {
+        fact_type: FactType::InterestRate,
+        label: "Interest Rate",
+        unit: "%",
+        description: "Annual interest rate of the loan as a percentage",
+    },
+    FactField {
+        fact_type: FactType::DebtService,
+        label: "Debt Service",
+        unit: "USD/year",
+        description: "Annual debt service (principal and interest). Multiply a monthly payment by 12",
+    },
+];
+
+const TAX_DOCUMENT_FIELDS: &[FactField] = &[FactField {
+    fact_type: FactType::PropertyValue,
+    label: "Property Value",
+    unit: "USD",
+    description: "Assessed or market value of the property",
+}];
+
+const BANK_STATEMENT_FIELDS: &[FactField] = &[
+    FactField {
+        fact_type: FactType::AverageBankBalance,
+        label: "Average Bank Balance",
+        unit: "USD",
+        description: "Average daily or ledger balance of the account over the statement period",
+    },
+    FactField {
+        fact_type: FactType::BankDeposits,
+        label: "Bank Deposits",
+        unit: "USD/year",
+        description: "Total deposits and credits over the statement period, annualized by the number of months it covers",
+    },
+];
+
+const INSURANCE_POLICY_FIELDS: &[FactField] = &[
+    FactField {
+        fact_type: FactType::InsuranceCoverage,
+        label: "Insurance Coverage",
+        unit: "USD",
+        description: "Building or property coverage limit of the policy",
+    },
+    FactField {
+        fact_type: FactType::InsurancePremium,
+        label: "Insurance Premium",
+        unit: "USD/year",
+        description: "Total annual premium of the policy",
+    },
+    FactField {
+        fact_type: FactType::InsuranceExpiration,
+        label: "Insurance Expiration",
+        unit: "date",
+        description: "Date the policy period ends",
+    },
+];
+
+const LEASE_FIELDS: &[FactField] = &[
+    FactField {
+        fact_type: FactType::LeaseTermMonths,
+        label: "Lease Term",
+        unit: "months",
+        description: "Length of the lease term in months",
+    },
+    FactField {
+        fact_type: FactType::LeaseBaseRent,
+        label: "Lease Base Rent",
+        unit: "USD/year",
+        description: "Base rent of the first lease year. Multiply a monthly rent by 12",
+    },
+    FactField {
+        fact_type: FactType::RentEscalation,
+        label: "Rent Escalation",
+        unit: "%/year",
+        description: "Annual increase of the base rent as a percentage",
+    },
+    FactField {
+        fact_type: FactType::LeaseExpiration,
+        label: "Lease Expiration",
+        unit: "date",
+        description: "Date the lease term ends",
+    },
+];
+
+const APPRAISAL_FIELDS: &[FactField] = &[
+    FactField {
+        fact_type: FactType::AppraisedValue,
+        label: "Appraised Value",
+        unit: "USD",
+        description: "As-is market value concluded by the appraiser",
+    },
+    FactField {
+        fact_type: FactType::AppraisalCapRate,
+        label: "Appraisal Cap Rate",
+        unit: "%",
+        description: "Overall capitalization rate used in the income approach, as a percentage",
+    },
+];
+
+fn fact_fields(document_type: &DocumentType) -> &'static [FactField] {
+    match document_type {
+        DocumentType::RentRoll => RENT_ROLL_FIELDS,
+        DocumentType::ProfitAndLoss => PROFIT_AND_LOSS_FIELDS,
+        DocumentType::MortgageStatement => MORTGAGE_STATEMENT_FIELDS,
+        DocumentType::TaxDocument => TAX_DOCUMENT_FIELDS,
+        DocumentType::BankStatement => BANK_STATEMENT_FIELDS,
+        DocumentType::InsurancePolicy => INSURANCE_POLICY_FIELDS,
+        DocumentType::Lease => LEASE_FIELDS,
+        DocumentType::Appraisal => APPRAISAL_FIELDS,
+        _ => &[],
+    }
+}
+
+/// JSON schema the LLM response must follow for the given document type
+///
+/// Every fact is reported with the segment it was read from so it can be cited.
+pub fn fact_schema(document_type: &DocumentType) -> Option<Value> {
+    let fields = fact_fields(document_type);
+    if fields.is_empty() {
+        return None;
+    }
+
+    let properties: serde_json::Map<String, Value> = fields
+        .iter()
+        .map(|field| {
+            let value = if field.unit == DATE_UNIT {
+                json!({
+                    "type": ["string", "null"],
+                    "description": "The date as YYYY-MM-DD, or null if it is not in the document"
+                })
+            } else {
+                json!({
+                    "type": ["number", "null"],
+                    "description": format!("The value in {}, or null if it is not in the document", field.unit)
+                })
+            };
+            (
+                field.fact_type.as_str().to_string(),
+                json!({
+                    "type": "object",
+                    "description": field.description,
+                    "properties": {
+                        "value": value,
+                        "segment_id": {
+                            "type": ["string", "null"],
+                            "description": "The id of the segment the value was read from"
+                        },
+                        "confidence": {
+                            "type": "number",
+                            "description": "Confidence between 0 and 1 that the value is correct"
+                        }
+                    },
+                    "required": ["value", "segment_id", "confidence"],
+                    "additionalProperties": false
+                }),
+            )
+        })
+        .collect();
+    let required: Vec<&str> = fields.iter().map(|f| f.fact_type.as_str()).collect();
+
+    Some(json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false
+    }))
+}
+
+/// Render the segments of the chunks with their ids so the LLM can reference them
+fn segments_to_prompt_content(chunks: &[Chunk]) -> String {
+    chunks
+        .iter()
+        .flat_map(|chunk| chunk.segments.iter())
+        .filter(|segment| !segment.content.trim().is_empty())
+        .map(|segment| {
+            let text = if segment.markdown.trim().is_empty() {
+                &segment.content
+            } else {
+                &segment.markdown
+            };
+            format!(
+                "<segment id=\"{}\" page=\"{}\">\n{}\n</segment>",
+                segment.segment_id,
+                segment.page_number,
+                text.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Cite a segment by its page, bounding box and the page's dimensions
+pub fn segment_citation(document: &Document, segment: &Segment) -> SourceCitation {
+    let line: String = segment
+        .content
+        .trim()
+        .chars()
+        .take(CITATION_LINE_LENGTH)
+        .collect();
+    SourceCitation {
+        document: document.file_name.clone(),
+        page: segment.page_number as i32,
+        line: Some(line),
+        bbox: Some(BoundingBox {
+            left: segment.bbox.left as f64,
+            top: segment.bbox.top as f64,
+            width: segment.bbox.width as f64,
+            height: segment.bbox.height as f64,
+        }),
+        page_width: Some(segment.page_width as f64),
+        page_height: Some(segment.page_height as f64),
+    }
+}
+
+/// Map a schema-conforming LLM response to facts cited by segment
+///
+/// Values that do not reference a segment of the document are dropped, as they cannot be cited.
+fn facts_from_llm_response(
+    document: &Document,
+    document_type: &DocumentType,
+    response: &Value,
+    chunks: &[Chunk],
+) -> Vec<NewFact> {
+    let segments: HashMap<&str, &Segment> = chunks
+        .iter()
+        .flat_map(|chunk| chunk.segments.iter())
+        .map(|segment| (segment.segment_id.as_str(), segment))
+        .collect();
+
+    fact_fields(document_type)
+        .iter()
+        .filter_map(|field| {
+            let extracted = response.get(field.fact_type.as_str())?;
+            let value = extracted.get("value")?;
+            let value = if field.unit == DATE_UNIT {
+                let date = NaiveDate::parse_from_str(value.as_str()?, "%Y-%m-%d").ok()?;
+                date.format("%Y-%m-%d").to_string()
+            } else {
+                match (&field.fact_type, value.as_f64()?) {
+                    (FactType::UnitCount | FactType::LeaseTermMonths, v) => {
+                        (v.round() as i64).to_string()
+                    }
+                    (_, v) => v.to_string(),
+                }
+            };
+            let segment_id = extracted.get("segment_id")?.as_str()?;
+            let segment = match segments.get(segment_id) {
+                Some(segment) => segment,
+                None => {
+                    println!(
+                        "Dropping {} fact citing unknown segment {}",
+                        field.fact_type.as_str(),
+                        segment_id
+                    );
+                    return None;
+                }
+            };
+            let confidence = extracted
+                .get("confidence")
+                .and_then(|c| c.as_f64())
+                .map(|c| c.clamp(0.0, 1.0));
+            Some(NewFact {
+                fact_id: Uuid::new_v4().to_string(),
+                document_id: document.document_id.clone(),
+                deal_id: document.deal_id.clone(),
+                property_id: document.property_id.clone(),
+                fact_type: field.fact_type.as_str().to_string(),
+                label: field.label.to_string(),
+                value,
+                unit: Some(field.unit.to_string()),
+                source_citation: json!(segment_citation(document, segment)),
+                status: "pending_approval".to_string(),
+                confidence_score: confidence,
+                period_basis: None,
+            })
+        })
+        .collect()
+}
+
+/// Extract facts from the chunked document with the LLM, using the schema of its document type
+pub async fn extract_facts_with_llm(
+    document: &Document,
+    document_type: &DocumentType,
+    chunks: &[Chunk],
+    llm_processing: LlmProcessing,
+    tracer: &opentelemetry::global::BoxedTracer,
+) -> Result<Vec<NewFact>, Box<dyn Error + Send + Sync>> {
+    let schema = fact_schema(document_type).ok_or_else(|| {
+        format!(
+            "No fact schema for document type {}",
+            document_type.as_str()
+        )
+    })?;
+    let content = segments_to_prompt_content(chunks);
+    if content.is_empty() {
+        return Err("Document has no content to extract facts from".into());
+    }
+
+    let mut values = HashMap::new();
+    values.insert(
+        "document_type".to_string(),
+        document_type.as_str().replace('_', " "),
+    );
+    values.insert("content".to_string(), content);
+    let mut messages = create_messages_from_template("fact_extraction_system", &HashMap::new())?;
+    messages.extend(create_messages_from_template(
+        "fact_extraction_user",
+        &values,
+    )?);
+
+    let response_format = serde_json::to_value(StructuredExtraction {
+        r#type: ExtractionType::JsonSchema,
+        json_schema: JsonSchema {
+            description: format!("Facts extracted from a {}", document_type.as_str()),
+            name: format!("{}_facts", document_type.as_str()),
+            schema: schema.clone(),
+            strict: true,
+        },
+    })?;
+    let response_text = structured_llm_handler(
+        llm_processing,
+        messages,
+        response_format,
+        tracer,
+        &Context::current(),
+    )
+    .await?;
+    let response: Value = serde_json::from_str(&response_text)?;
+    validate_against_schema(&response, &schema)?;
+
+    Ok(facts_from_llm_response(
+        document,
+        document_type,
+        &response,
+        chunks,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::output::{BoundingBox as SegmentBoundingBox, SegmentType};
+
+    fn test_document() -> Document {
+        Document {
+            classification_source: Some("user".to_string()),
+            ..Document::for_test("mortgage.pdf", "mortgage_statement")
+        }
+    }
+
+    fn test_chunks() -> Vec<Chunk> {
+        let mut segment = Segment::new(
+            SegmentBoundingBox::new(10.0, 20.0, 300.0, 40.0),
+            None,
+            vec![],
+            1000.0,
+            800.0,
+            2,
+            SegmentType::Text,
+        );
+        segment.segment_id = "seg-1".to_string();
+        segment.content = "Principal balance: $1,250,000.00".to_string();
+        vec![Chunk {
+            chunk_id: "chunk-1".to_string(),
+            chunk_length: 4,
+            segments: vec![segment],
+            embed: None,
+        }]
+    }
+
+    #[test]
+    fn test_fact_schema() {
+        let schema = fact_schema(&DocumentType::MortgageStatement).unwrap();
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|r| r.as_str())
+            .collect();
+        assert_eq!(
+            required,
+            vec!["mortgage_balance", "interest_rate", "debt_service"]
+        );
+        assert!(fact_schema(&DocumentType::Other).is_none());
+    }
+
+    #[test]
+    fn test_facts_from_llm_response() {
+        let document = test_document();
+        let chunks = test_chunks();
+        let response = json!({
+            "mortgage_balance": { "value": 1250000.0, "segment_id": "seg-1", "confidence": 0.97 },
+            "interest_rate": { "value": 6.25, "segment_id": "seg-404", "confidence": 0.9 },
+            "debt_service": { "value": null, "segment_id": null, "confidence": 0.0 }
+        });
+        let schema = fact_schema(&DocumentType::MortgageStatement).unwrap();
+        assert!(validate_against_schema(&response, &schema).is_ok());
+
+        let facts = facts_from_llm_response(
+            &document,
+            &DocumentType::MortgageStatement,
+            &response,
+            &chunks,
+        );
+        assert_eq!(facts.len(), 1);
+        assert_eq!(facts[0].fact_type, "mortgage_balance");
+        assert_eq!(facts[0].value, "1250000");
+        assert_eq!(facts[0].confidence_score, Some(0.97));
+
+        let citation: SourceCitation =
+            serde_json::from_value(facts[0].source_citation.clone()).unwrap();
+        assert_eq!(citation.page, 2);
+        assert_eq!(citation.bbox.unwrap().width, 300.0);
+    }
+}
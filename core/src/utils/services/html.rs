@@ -8,6 +8,12 @@ static TABLE_CONTENT_REGEX: Lazy<Regex> =
 static IMG_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r#"<img(?:[^>]*?alt=["']([^"']*?)["'])?[^>]*>"#).unwrap());
 static TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"</?([a-zA-Z][a-zA-Z0-9]*).*?>").unwrap());
+static ANY_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static ROW_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<tr[^>]*>(.*?)</tr>").unwrap());
+static CELL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<t[hd]([^>]*)>(.*?)</t[hd]>").unwrap());
+static COLSPAN_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)colspan\s*=\s*["']?(\d+)"#).unwrap());
 
 // TODO: Deal with multiple tables
 pub fn extract_table_html(html: String) -> String {
@@ -23,6 +29,39 @@ pub fn extract_table_html(html: String) -> String {
     }
 }
 
+/// Rows of an HTML table as cell text, with `colspan` cells repeated so columns stay aligned
+pub fn table_rows(html: &str) -> Vec<Vec<String>> {
+    ROW_REGEX
+        .captures_iter(html)
+        .map(|row| {
+            let mut cells = Vec::new();
+            for cell in CELL_REGEX.captures_iter(&row[1]) {
+                let span = COLSPAN_REGEX
+                    .captures(&cell[1])
+                    .and_then(|c| c[1].parse::<usize>().ok())
+                    .unwrap_or(1)
+                    .max(1);
+                let text = cell_text(&cell[2]);
+                cells.push(text);
+                cells.extend(std::iter::repeat_n(String::new(), span - 1));
+            }
+            cells
+        })
+        .collect()
+}
+
+fn cell_text(html: &str) -> String {
+    let text = ANY_TAG_REGEX.replace_all(html, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 /// Cleans the image tags from the HTML
 ///
 /// Replaces HTML image tags with their alt text (if available) or removes them entirely.
@@ -137,12 +137,11 @@ async fn open_ai_call_handler(
     {
         Ok(response) => Ok(response),
         Err(e) => {
-            if let Some(llm_error) = e.downcast_ref::<LLMError>() {
-                if let LLMError::JsonParseError { response, .. } = llm_error {
-                    let attributes = otel_config::extract_llm_error_attributes(response);
-                    for attr in attributes {
-                        ctx.span().set_attribute(attr);
-                    }
+            if let Some(LLMError::JsonParseError { response, .. }) = e.downcast_ref::<LLMError>()
+            {
+                let attributes = otel_config::extract_llm_error_attributes(response);
+                for attr in attributes {
+                    ctx.span().set_attribute(attr);
                 }
             }
             Err(e)
@@ -257,11 +256,9 @@ fn extract_fenced_content(content: &str, fence_type: Option<&str>) -> Option<Str
         .map(|content| content.trim().to_string())
 }
 
-/// Try to extract fenced content from an OpenAI response
-/// Returns message content if extraction succeeds, error otherwise
-fn try_extract_from_response(
+/// Get the content from a complete, non-empty OpenAI response
+fn try_get_content_from_response(
     response: &OpenAiResponse,
-    fence_type: Option<&str>,
 ) -> Result<String, Box<dyn Error + Send + Sync>> {
     if response.choices.is_empty() {
         println!("Response contains no choices");
@@ -282,6 +279,16 @@ fn try_extract_from_response(
         println!("Content is empty");
         return Err(Box::new(LLMError::Generic("Content is empty".to_string())));
     }
+    Ok(content)
+}
+
+/// Try to extract fenced content from an OpenAI response
+/// Returns message content if extraction succeeds, error otherwise
+fn try_extract_from_response(
+    response: &OpenAiResponse,
+    fence_type: Option<&str>,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let content = try_get_content_from_response(response)?;
     let extracted = extract_fenced_content(&content, fence_type).ok_or_else(|| {
         println!("No content could be extracted from response content");
         Box::new(LLMError::Generic(
@@ -399,3 +406,62 @@ pub async fn try_extract_from_llm(
     )
     .await
 }
+
+/// Process an OpenAI request constrained by a `response_format` (e.g. a JSON schema).
+/// The raw message content is returned, as structured outputs are not fenced.
+pub async fn structured_llm_handler(
+    llm_processing: LlmProcessing,
+    messages: Vec<Message>,
+    response_format: serde_json::Value,
+    tracer: &opentelemetry::global::BoxedTracer,
+    ctx: &Context,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    // Requests without a model use the configured default model
+    let model_id = llm_processing.model_id.clone();
+    let fallback_strategy = llm_processing.fallback_strategy;
+    let max_completion_tokens = llm_processing.max_completion_tokens;
+    let temperature = llm_processing.temperature;
+
+    retry_with_backoff(|| async {
+        let llm_config = LlmConfig::from_env().unwrap();
+        let model = llm_config.get_model(model_id.clone())?;
+        let fallback_model = llm_config.get_fallback_model(fallback_strategy.clone())?;
+
+        let result = match open_ai_call_handler(
+            model,
+            messages.clone(),
+            max_completion_tokens,
+            Some(temperature),
+            Some(response_format.clone()),
+            tracer,
+            ctx,
+        )
+        .await
+        .and_then(|response| try_get_content_from_response(&response))
+        {
+            Ok(content) => Ok(content),
+            Err(e) => match fallback_model {
+                Some(fallback_model) => open_ai_call_handler(
+                    fallback_model,
+                    messages.clone(),
+                    max_completion_tokens,
+                    Some(temperature),
+                    Some(response_format.clone()),
+                    tracer,
+                    ctx,
+                )
+                .await
+                .and_then(|response| try_get_content_from_response(&response)),
+                None => Err(e),
+            },
+        };
+        result.inspect_err(|e| {
+            ctx.span()
+                .set_status(opentelemetry::trace::Status::error(e.to_string()));
+            ctx.span().record_error(e.as_ref());
+            ctx.span()
+                .set_attribute(opentelemetry::KeyValue::new("error", e.to_string()));
+        })
+    })
+    .await
+}
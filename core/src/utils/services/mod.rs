@@ -1,5 +1,7 @@
 pub mod azure;
 pub mod chunking;
+pub mod document_classification;
+pub mod fact_extraction;
 pub mod file_operations;
 pub mod html;
 pub mod images;
@@ -9,4 +11,5 @@ pub mod ocr;
 pub mod payload;
 pub mod pdf;
 pub mod segmentation;
-// pub mod structured_extraction;
+pub mod structured_extraction;
+pub mod xlsx;
@@ -1,14 +1,13 @@
-use crate::configs::{
-    llm_config::{get_prompt, Config as LlmConfig},
-    search_config::Config as SearchConfig,
-};
-use crate::models::open_ai::MessageContent;
+use crate::configs::llm_config::create_messages_from_template;
+use crate::configs::search_config::Config as SearchConfig;
+use crate::models::llm::LlmProcessing;
 use crate::models::search::{Search, SearchResult};
 use crate::models::structured_extraction::{
     StructuredExtractionRequest, StructuredExtractionResponse,
 };
-use crate::utils::services::llm::{create_basic_message, process_openai_request};
+use crate::utils::services::llm::structured_llm_handler;
 use futures::future::try_join_all;
+use opentelemetry::Context;
 use std::collections::HashMap;
 use std::error::Error;
 
@@ -53,29 +52,123 @@ fn extract_search_queries(schema: &serde_json::Value) -> Vec<String> {
     queries
 }
 
+/// Resolves a local `$ref` (e.g. `#/$defs/Address`) against the root schema
+fn resolve_ref<'a>(
+    schema: &'a serde_json::Value,
+    root: &'a serde_json::Value,
+) -> Result<&'a serde_json::Value, String> {
+    match schema.get("$ref").and_then(|r| r.as_str()) {
+        Some(reference) => root
+            .pointer(reference.trim_start_matches('#'))
+            .ok_or_else(|| format!("Unresolved schema reference: {}", reference)),
+        None => Ok(schema),
+    }
+}
+
+fn matches_type(value: &serde_json::Value, schema_type: &str) -> bool {
+    match schema_type {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn validate_value(
+    value: &serde_json::Value,
+    schema: &serde_json::Value,
+    root: &serde_json::Value,
+    path: &str,
+) -> Result<(), String> {
+    let schema = resolve_ref(schema, root)?;
+
+    if let Some(any_of) = schema.get("anyOf").and_then(|a| a.as_array()) {
+        return match any_of
+            .iter()
+            .any(|option| validate_value(value, option, root, path).is_ok())
+        {
+            true => Ok(()),
+            false => Err(format!("{}: value does not match any allowed schema", path)),
+        };
+    }
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(serde_json::Value::String(t)) => vec![t.as_str()],
+        Some(serde_json::Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => vec![],
+    };
+    if !types.is_empty() && !types.iter().any(|t| matches_type(value, t)) {
+        return Err(format!("{}: expected {}", path, types.join(" or ")));
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(value) {
+            return Err(format!("{}: value is not one of the allowed values", path));
+        }
+    }
+
+    if let Some(obj) = value.as_object() {
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !obj.contains_key(key) {
+                    return Err(format!("{}: missing required property '{}'", path, key));
+                }
+            }
+        }
+        for (key, property_value) in obj {
+            let property_path = format!("{}.{}", path, key);
+            match properties.and_then(|p| p.get(key)) {
+                Some(property_schema) => {
+                    validate_value(property_value, property_schema, root, &property_path)?
+                }
+                None => {
+                    if schema.get("additionalProperties") == Some(&serde_json::Value::Bool(false))
+                    {
+                        return Err(format!("{}: unexpected property", property_path));
+                    }
+                }
+            }
+        }
+    }
+
+    if let (Some(items), Some(item_schema)) = (value.as_array(), schema.get("items")) {
+        for (idx, item) in items.iter().enumerate() {
+            validate_value(item, item_schema, root, &format!("{}[{}]", path, idx))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates a JSON value against the subset of JSON schema supported by structured outputs
+pub fn validate_against_schema(
+    value: &serde_json::Value,
+    schema: &serde_json::Value,
+) -> Result<(), String> {
+    validate_value(value, schema, schema, "$")
+}
+
 pub async fn perform_structured_extraction(
     structured_extraction_request: StructuredExtractionRequest,
-) -> Result<StructuredExtractionResponse, Box<dyn Error>> {
-    let llm_config = LlmConfig::from_env().unwrap();
-    let search_config = SearchConfig::from_env().unwrap();
-    let schema = &structured_extraction_request
-        .structured_extraction
-        .json_schema
-        .schema;
+    llm_processing: LlmProcessing,
+    tracer: &opentelemetry::global::BoxedTracer,
+) -> Result<StructuredExtractionResponse, Box<dyn Error + Send + Sync>> {
+    let search_config = SearchConfig::from_env()?;
+    let structured_extraction = structured_extraction_request.structured_extraction;
+    let schema = &structured_extraction.json_schema.schema;
     let search_queries = extract_search_queries(schema);
-    let search = match Search::new(structured_extraction_request.contents).await {
-        Ok(search) => search,
-        Err(e) => return Err(e.to_string().into()),
-    };
+    let search = Search::new(structured_extraction_request.contents).await?;
     let search_futures: Vec<_> = search_queries
         .iter()
         .map(|query| search.search(query))
         .collect();
 
-    let search_results = match try_join_all(search_futures).await {
-        Ok(search_results) => search_results,
-        Err(e) => return Err(e.to_string().into()),
-    };
+    let search_results = try_join_all(search_futures).await?;
     let mut seen_chunks = HashMap::new();
     for result in search_results.into_iter().flatten() {
         seen_chunks
@@ -105,37 +198,24 @@ pub async fn perform_structured_extraction(
             .collect::<Vec<String>>()
             .join("\n"),
     );
-    let system_message = create_basic_message(
-        "system".to_string(),
-        get_prompt("structured_extraction_system", &HashMap::new())?,
-    )?;
-    let user_message = create_basic_message(
-        "user".to_string(),
-        get_prompt("structured_extraction_user", &user_values)?,
-    )?;
-    println!("System message: {:?}", system_message);
-    println!("User message: {:?}", user_message);
-    let response = match process_openai_request(
-        llm_config.url,
-        llm_config.key,
-        llm_config.model,
-        vec![system_message, user_message],
-        None,
-        None,
-        Some(structured_extraction_request.structured_extraction),
+    let mut messages =
+        create_messages_from_template("structured_extraction_system", &HashMap::new())?;
+    messages.extend(create_messages_from_template(
+        "structured_extraction_user",
+        &user_values,
+    )?);
+
+    let response_text = structured_llm_handler(
+        llm_processing,
+        messages,
+        serde_json::to_value(&structured_extraction)?,
+        tracer,
+        &Context::current(),
     )
-    .await
-    {
-        Ok(response) => response,
-        Err(e) => return Err(e.to_string().into()),
-    };
-    let response_text = match &response.choices[0].message.content {
-        MessageContent::String { content } => content.clone(),
-        _ => return Err("Invalid response format".into()),
-    };
-    Ok(StructuredExtractionResponse {
-        response: response_text,
-    })
+    .await?;
+    let response: serde_json::Value = serde_json::from_str(&response_text)?;
+    validate_against_schema(&response, schema)?;
+    Ok(StructuredExtractionResponse { response })
 }
 
 #[cfg(test)]
@@ -146,8 +226,49 @@ mod tests {
         ExtractionType, JsonSchema, StructuredExtraction,
     };
     use crate::utils::clients;
+    use serde_json::json;
     use tokio;
 
+    #[test]
+    fn test_validate_against_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "units": { "type": "integer" },
+                "tenants": {
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/tenant" }
+                }
+            },
+            "required": ["name", "units"],
+            "additionalProperties": false,
+            "$defs": {
+                "tenant": {
+                    "type": "object",
+                    "properties": { "rent": { "type": ["number", "null"] } },
+                    "required": ["rent"]
+                }
+            }
+        });
+
+        let valid = json!({ "name": "Elm Court", "units": 24, "tenants": [{ "rent": 1450.0 }, { "rent": null }] });
+        assert!(validate_against_schema(&valid, &schema).is_ok());
+
+        let missing = json!({ "name": "Elm Court" });
+        assert!(validate_against_schema(&missing, &schema).is_err());
+
+        let wrong_type = json!({ "name": "Elm Court", "units": "24" });
+        assert!(validate_against_schema(&wrong_type, &schema).is_err());
+
+        let extra = json!({ "name": "Elm Court", "units": 24, "city": "Austin" });
+        assert!(validate_against_schema(&extra, &schema).is_err());
+
+        let bad_item = json!({ "name": "Elm Court", "units": 24, "tenants": [{ "rent": "high" }] });
+        let err = validate_against_schema(&bad_item, &schema).unwrap_err();
+        assert!(err.starts_with("$.tenants[0].rent"));
+    }
+
     #[tokio::test]
     async fn test_structured_extraction() {
         clients::initialize().await;
@@ -183,7 +304,7 @@ mod tests {
             },
         };
 
-        let contents = vec![
+        let contents = [
             SimpleChunk {
                 id: uuid::Uuid::new_v4().to_string(),
                 content: "Oranges are bright orange citrus fruits that contain about 47 calories per 100g serving.".to_string(),
@@ -198,13 +319,18 @@ mod tests {
             },
         ];
 
-        let response = perform_structured_extraction(StructuredExtractionRequest {
-            contents: contents
-                .iter()
-                .map(|c| ChunkContent::Simple(c.clone()))
-                .collect(),
-            structured_extraction: json_schema,
-        })
+        let tracer = opentelemetry::global::tracer("test");
+        let response = perform_structured_extraction(
+            StructuredExtractionRequest {
+                contents: contents
+                    .iter()
+                    .map(|c| ChunkContent::Simple(c.clone()))
+                    .collect(),
+                structured_extraction: json_schema,
+            },
+            LlmProcessing::default(),
+            &tracer,
+        )
         .await;
         println!("Response: {:?}", response);
         assert!(response.is_ok());
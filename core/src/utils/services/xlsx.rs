@@ -0,0 +1,322 @@
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Maximum length Excel accepts for a sheet name
+const MAX_SHEET_NAME_LEN: usize = 31;
+
+/// Style index of the bold header row in `styles.xml`
+const HEADER_STYLE: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Cell::Text(value.to_string())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+        Cell::Text(value)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(value: f64) -> Self {
+        Cell::Number(value)
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Cell::Empty)
+    }
+}
+
+/// A sheet with a bold, frozen header row
+#[derive(Debug, Clone)]
+pub struct Worksheet {
+    pub name: String,
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Worksheet {
+    pub fn new(name: &str, header: &[&str]) -> Self {
+        Worksheet {
+            name: name.to_string(),
+            header: header.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push_row(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+
+    fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+        );
+        if !self.header.is_empty() {
+            xml.push_str(
+                r#"<sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews>"#,
+            );
+        }
+        xml.push_str("<sheetData>");
+
+        let header: Vec<Cell> = self.header.iter().map(|h| Cell::Text(h.clone())).collect();
+        let rows = std::iter::once((&header, Some(HEADER_STYLE)))
+            .filter(|(header, _)| !header.is_empty())
+            .chain(self.rows.iter().map(|row| (row, None)));
+        for (row_idx, (row, style)) in rows.enumerate() {
+            let row_number = row_idx + 1;
+            xml.push_str(&format!(r#"<row r="{}">"#, row_number));
+            for (col_idx, cell) in row.iter().enumerate() {
+                let reference = format!("{}{}", column_name(col_idx), row_number);
+                let style = style.map(|s| format!(r#" s="{}""#, s)).unwrap_or_default();
+                match cell {
+                    Cell::Text(text) => xml.push_str(&format!(
+                        r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                        reference,
+                        style,
+                        escape_xml(text)
+                    )),
+                    Cell::Number(number) if number.is_finite() => xml.push_str(&format!(
+                        r#"<c r="{}"{}><v>{}</v></c>"#,
+                        reference, style, number
+                    )),
+                    Cell::Number(_) | Cell::Empty => {}
+                }
+            }
+            xml.push_str("</row>");
+        }
+
+        xml.push_str("</sheetData></worksheet>");
+        xml
+    }
+}
+
+/// An Office Open XML workbook written without any external tooling
+#[derive(Debug, Clone, Default)]
+pub struct Workbook {
+    pub sheets: Vec<Worksheet>,
+}
+
+impl Workbook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_sheet(&mut self, sheet: Worksheet) {
+        self.sheets.push(sheet);
+    }
+
+    /// Zip the workbook parts into an `.xlsx` file
+    pub fn to_bytes(&self) -> zip::result::ZipResult<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        let sheet_names = self.sheet_names();
+        let sheet_count = sheet_names.len().max(1);
+
+        let mut parts: Vec<(String, String)> = vec![
+            ("[Content_Types].xml".to_string(), content_types_xml(sheet_count)),
+            ("_rels/.rels".to_string(), ROOT_RELS_XML.to_string()),
+            ("xl/workbook.xml".to_string(), workbook_xml(&sheet_names)),
+            (
+                "xl/_rels/workbook.xml.rels".to_string(),
+                workbook_rels_xml(sheet_count),
+            ),
+            ("xl/styles.xml".to_string(), STYLES_XML.to_string()),
+        ];
+        if self.sheets.is_empty() {
+            // A workbook needs at least one sheet to open
+            parts.push((
+                "xl/worksheets/sheet1.xml".to_string(),
+                Worksheet::new("Sheet1", &[]).to_xml(),
+            ));
+        }
+        for (idx, sheet) in self.sheets.iter().enumerate() {
+            parts.push((format!("xl/worksheets/sheet{}.xml", idx + 1), sheet.to_xml()));
+        }
+
+        for (path, content) in parts {
+            zip.start_file(path, options)?;
+            zip.write_all(content.as_bytes())?;
+        }
+        Ok(zip.finish()?.into_inner())
+    }
+
+    /// Sheet names made valid and unique, in sheet order
+    fn sheet_names(&self) -> Vec<String> {
+        if self.sheets.is_empty() {
+            return vec!["Sheet1".to_string()];
+        }
+
+        let mut names: Vec<String> = Vec::with_capacity(self.sheets.len());
+        for (idx, sheet) in self.sheets.iter().enumerate() {
+            let mut name: String = sheet
+                .name
+                .chars()
+                .map(|c| match c {
+                    ':' | '\\' | '/' | '?' | '*' | '[' | ']' => '_',
+                    c => c,
+                })
+                .take(MAX_SHEET_NAME_LEN)
+                .collect();
+            if name.trim().is_empty() {
+                name = format!("Sheet{}", idx + 1);
+            }
+            if names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                let suffix = format!(" ({})", idx + 1);
+                name = name
+                    .chars()
+                    .take(MAX_SHEET_NAME_LEN - suffix.len())
+                    .collect::<String>()
+                    + &suffix;
+            }
+            names.push(name);
+        }
+        names
+    }
+}
+
+/// Spreadsheet column letters for a zero-based column index: A, B, ..., Z, AA, AB, ...
+pub fn column_name(index: usize) -> String {
+    let mut name = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        name.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Escape text for XML, dropping control characters XML 1.0 cannot represent
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn content_types_xml(sheet_count: usize) -> String {
+    let sheets: String = (1..=sheet_count)
+        .map(|i| {
+            format!(
+                r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+                i
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>{}</Types>"#,
+        sheets
+    )
+}
+
+fn workbook_xml(sheet_names: &[String]) -> String {
+    let sheets: String = sheet_names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            format!(
+                r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#,
+                escape_xml(name),
+                idx + 1,
+                idx + 1
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>{}</sheets></workbook>"#,
+        sheets
+    )
+}
+
+fn workbook_rels_xml(sheet_count: usize) -> String {
+    let sheets: String = (1..=sheet_count)
+        .map(|i| {
+            format!(
+                r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#,
+                i, i
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">{}<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#,
+        sheets,
+        sheet_count + 1
+    )
+}
+
+const ROOT_RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const STYLES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs></styleSheet>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_workbook_to_bytes() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(52), "BA");
+
+        let mut sheet = Worksheet::new("Facts", &["Label", "Value"]);
+        sheet.push_row(vec!["NOI <T-12> & more".into(), 125000.5.into()]);
+        sheet.push_row(vec![Cell::from(None::<String>), f64::NAN.into()]);
+        let mut workbook = Workbook::new();
+        workbook.add_sheet(sheet);
+        workbook.add_sheet(Worksheet::new("Facts", &[]));
+        workbook.add_sheet(Worksheet::new("P&L: 2024/2025", &[]));
+
+        let bytes = workbook.to_bytes().unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut read = |path: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(path)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+
+        let sheet_xml = read("xl/worksheets/sheet1.xml");
+        assert!(sheet_xml.contains(
+            r#"<c r="A1" s="1" t="inlineStr"><is><t xml:space="preserve">Label</t></is></c>"#
+        ));
+        assert!(sheet_xml.contains("NOI &lt;T-12&gt; &amp; more"));
+        assert!(sheet_xml.contains(r#"<c r="B2"><v>125000.5</v></c>"#));
+        assert!(sheet_xml.contains(r#"<row r="3"></row>"#));
+
+        let workbook_xml = read("xl/workbook.xml");
+        assert!(workbook_xml.contains(r#"<sheet name="Facts" sheetId="1" r:id="rId1"/>"#));
+        assert!(workbook_xml.contains(r#"<sheet name="Facts (2)" sheetId="2" r:id="rId2"/>"#));
+        assert!(workbook_xml.contains(r#"<sheet name="P&amp;L_ 2024_2025" sheetId="3""#));
+        assert!(read("[Content_Types].xml").contains("/xl/worksheets/sheet3.xml"));
+    }
+}
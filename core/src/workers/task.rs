@@ -16,13 +16,13 @@ use memtrack::track_mem;
 ///
 /// This function defines the order of the steps in the pipeline.
 fn orchestrate_task(
-    _pipeline: &mut Pipeline,
+    pipeline: &mut Pipeline,
 ) -> Result<Vec<PipelineStep>, Box<dyn std::error::Error>> {
     let mut steps = vec![PipelineStep::ConvertToImages];
 
     #[cfg(feature = "azure")]
     {
-        match _pipeline.get_task()?.configuration.pipeline.clone() {
+        match pipeline.get_task()?.configuration.pipeline.clone() {
             Some(core::models::task::PipelineType::Azure) => {
                 steps.push(PipelineStep::AzureAnalysis)
             }
@@ -37,6 +37,15 @@ fn orchestrate_task(
     steps.push(PipelineStep::Crop);
     steps.push(PipelineStep::SegmentProcessing);
     steps.push(PipelineStep::Chunking);
+    if pipeline
+        .get_task()?
+        .configuration
+        .structured_extraction
+        .is_some()
+    {
+        steps.push(PipelineStep::StructuredExtraction);
+    }
+    steps.push(PipelineStep::FactExtraction);
     Ok(steps)
 }
 